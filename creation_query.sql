@@ -1,33 +1,154 @@
 PRAGMA foreign_keys = ON;
 PRAGMA journal_mode = WAL;
 
+-- Scan info table --
+-- Provenance of the database: the OS it was built on, the paths that were
+-- scanned, the dora version and when the scan started (UTC).
+-- "launch_paths" and "scan_dirs" are JSON arrays. "root" is the folder the
+-- scanned system was mounted at ("dora scan --root"), NULL for the host.
+CREATE TABLE IF NOT EXISTS scan_info (
+    id INTEGER PRIMARY KEY,
+    product_name TEXT,
+    product_version TEXT,
+    build_version TEXT,
+    launch_paths TEXT,
+    scan_dirs TEXT,
+    dora_version TEXT,
+    scanned_at TEXT,
+    root TEXT
+);
+
 -- Service table --
 -- This table is used to store information about services.
+-- "run_as_user" is the account a daemon runs as ("UserName", "root" by
+-- default) or "standard" for agents, which run as the logged-in user;
+-- "run_as_group" is the "GroupName" of a daemon, if set.
+-- "keep_alive_conditions" is the KeepAlive dictionary as JSON, when KeepAlive
+-- is a set of conditions rather than a boolean (keep_alive is then 1).
+-- "writable_by_nonroot" is 1 when a non-root user could replace the binary
+-- (through the file itself or one of its parent directories), NULL when the
+-- binary couldn't be inspected.
+-- "codesign_flags" lists the CodeDirectory flag names of the binary's signature
+-- (e.g. "library-validation,runtime"), empty when it has none and NULL when
+-- codesign couldn't read it. "platform_identifier" is only set for platform
+-- binaries. "team_id" is the TeamIdentifier of the signature, "not set" for
+-- Apple platform binaries and ad-hoc signatures.
+-- "binary_type" is 'macho' or 'script' when the launched file is on disk;
+-- "interpreter" is the shebang line of a script (e.g. "/bin/sh", "/usr/bin/env python3").
+-- "min_os" and "sdk" are the minimum OS version the binary supports and the
+-- SDK it was built with (LC_BUILD_VERSION or LC_VERSION_MIN_MACOSX), NULL
+-- when the binary has neither load command or otool couldn't read it.
+-- "source" is where the service was found: 'plist' for launchd jobs, 'binary'
+-- for Mach-O binaries labelled with their code-signing identifier. A job and
+-- a binary may share a label, so both are kept.
+-- "program" is the program of a job as written in its plist, only set when
+-- it's a relative path: "path" is then resolved against the job's
+-- "WorkingDirectory", or left relative when it has none.
+-- "plist_target" is the file a symlinked "plist_path" resolves to, NULL when
+-- the plist isn't a symlink.
+-- "arch" lists the architectures of the binary, comma-separated (e.g.
+-- "x86_64,arm64e"), as read from its Mach-O header. "is_32bit_only" is 1 when
+-- none of them is 64-bit (e.g. "i386"), which current macOS can't run.
+-- "is_restricted" is 1 when dyld ignores the "DYLD_*" environment variables
+-- of the binary (no "DYLD_INSERT_LIBRARIES" injection): it has a "__RESTRICT"
+-- segment or is signed with the "restrict" flag. NULL when its load commands
+-- weren't read.
+-- "uses_dynamic_loading" is 1 when the binary imports a function loading code
+-- at runtime (dlopen, dlsym, NSAddImage), 0 when it imports none of them and
+-- NULL when its symbols weren't extracted.
+-- "bundle_path" is the application bundle the program lies in, the outermost
+-- one for a helper app nested in another (e.g. "/Applications/Foo.app"), and
+-- "bundle_id" the "CFBundleIdentifier" of its Info.plist. Both are NULL when
+-- the program isn't inside a ".app".
+-- "entitlements_status" tells how the entitlements of a Mach-O binary were
+-- read: 'present' when it has some, 'none' when it is signed without any,
+-- 'error' when they couldn't be read (unsigned or unreadable binary, tool
+-- failure). NULL for scripts and missing binaries.
+-- "session_types" lists the "LimitLoadToSessionType" of a job, comma-separated
+-- (e.g. "Aqua,LoginWindow"): the sessions launchd loads it in. NULL when the
+-- plist doesn't limit them.
+-- "third_party" is 1 for third-party software: services whose plist or
+-- binary lies below "/Library/LaunchDaemons", "/Library/LaunchAgents",
+-- "/Library/PrivilegedHelperTools", "/Library/Extensions",
+-- "/Library/DriverExtensions" or "/usr/local".
+-- "risk_score" orders services for triage: the sum of the weights of the
+-- "risk_factors" that apply to the service (comma-separated, e.g.
+-- "root,run_at_load,writable_binary"), computed at the end of a scan. The
+-- rules are in "risk_factors" of src/sqlite.rs.
+-- "kind" tells what the service is: 'daemon' or 'agent' for launchd jobs,
+-- 'binary' for plain binaries, 'kext' or 'dext' for the executable of a kernel
+-- or driver extension bundle.
 CREATE TABLE IF NOT EXISTS service (
     id INTEGER PRIMARY KEY,
-    label TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    source TEXT NOT NULL,
     path TEXT NOT NULL,
     run_as_user TEXT,
+    run_as_group TEXT,
     run_at_load TEXT,
     keep_alive TEXT, 
-    plist_path TEXT UNIQUE
+    plist_path TEXT UNIQUE,
+    disabled TEXT,
+    keep_alive_conditions TEXT,
+    writable_by_nonroot INTEGER,
+    codesign_flags TEXT,
+    platform_identifier INTEGER,
+    binary_type TEXT,
+    interpreter TEXT,
+    min_os TEXT,
+    sdk TEXT,
+    kind TEXT,
+    arch TEXT,
+    plist_target TEXT,
+    program TEXT,
+    uses_dynamic_loading INTEGER,
+    team_id TEXT,
+    bundle_path TEXT,
+    bundle_id TEXT,
+    entitlements_status TEXT,
+    session_types TEXT,
+    is_32bit_only INTEGER,
+    third_party INTEGER DEFAULT 0,
+    risk_score INTEGER,
+    risk_factors TEXT,
+    is_restricted INTEGER,
+    UNIQUE (label, source)
 );
 
 -- Mach service table --
 -- This table is used to store Mach services associated with a service.
+-- "value" is the plist value as JSON (usually "true", or a dictionary of options);
+-- "host_special_port" is set when the options declare a HostSpecialPort.
 CREATE TABLE IF NOT EXISTS mach_service (
     id INTEGER PRIMARY KEY,
     name TEXT UNIQUE,
-    value INTEGER,
+    value TEXT,
+    service_id INTEGER,
+    host_special_port INTEGER,
+    FOREIGN KEY (service_id) REFERENCES service(id)
+);
+
+-- Environment variable table --
+-- This table is used to store the "EnvironmentVariables" set by a service's plist
+-- ("DYLD_*" ones being a classic injection vector).
+CREATE TABLE IF NOT EXISTS env_var (
+    id INTEGER PRIMARY KEY,
     service_id INTEGER,
+    name TEXT NOT NULL,
+    value TEXT,
+    UNIQUE (service_id, name),
     FOREIGN KEY (service_id) REFERENCES service(id)
 );
 
 -- Entitlement table --
 -- This table is used to store entitlements associated with services.        
+-- "first_seen" is when the entitlement was first inserted (UTC, same format
+-- as "scan_info.scanned_at"); rows are never replaced, so entitlements that
+-- only appear on a later refresh keep a later date. Same for libraries and symbols.
 CREATE TABLE IF NOT EXISTS entitlement (
     id INTEGER PRIMARY KEY,
-    name TEXT NOT NULL UNIQUE
+    name TEXT NOT NULL UNIQUE,
+    first_seen TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
 );
 
 
@@ -45,10 +166,27 @@ CREATE TABLE IF NOT EXISTS service_entitlement (
 );
 
 -- Library table --
+-- "identifier" and "team_id" come from the library's own code signature
+-- and stay NULL when codesign can't read it (e.g. dyld shared cache residents).
+-- "on_disk" is 1 when the absolute path is a file, 0 when it isn't, NULL for
+-- "@rpath/..." paths. "in_shared_cache" is 1 when the library is one of the
+-- images of the dyld shared cache. When no cache could be read, libraries
+-- missing from disk are assumed to be in it.
+-- Libraries neither on disk nor in the cache are phantom dependencies:
+-- "writable_by_nonroot" is then 1 when a non-root user could create the file
+-- (its closest existing parent directory is writable by them).
+-- Libraries are identified by their path: several may share a name (e.g. a
+-- framework binary and a copy embedded in another bundle).
 CREATE TABLE IF NOT EXISTS library (
     id INTEGER PRIMARY KEY,
-    name TEXT NOT NULL UNIQUE,
-    path TEXT NOT NULL UNIQUE
+    name TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    identifier TEXT,
+    team_id TEXT,
+    in_shared_cache INTEGER,
+    on_disk INTEGER,
+    writable_by_nonroot INTEGER,
+    first_seen TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
 );
 
 -- Library service table --
@@ -63,11 +201,27 @@ CREATE TABLE IF NOT EXISTS service_library (
     FOREIGN KEY (library_id) REFERENCES library(id)
 );
 
+-- Library dependency table --
+-- Libraries imported by a library ("otool -L" of the library itself), only
+-- known for libraries that are on disk. Together with "service_library" it
+-- gives the transitive dependency closure of a service.
+CREATE TABLE IF NOT EXISTS library_dependency (
+    library_id INTEGER,
+    dependency_id INTEGER,
+    PRIMARY KEY (library_id, dependency_id),
+    FOREIGN KEY (library_id) REFERENCES library(id),
+    FOREIGN KEY (dependency_id) REFERENCES library(id)
+);
+
 
 -- Symbol table --
+-- "demangled" holds the readable form of Rust, C++ and Swift symbol names,
+-- NULL for plain C symbols or when the name couldn't be demangled.
 CREATE TABLE IF NOT EXISTS symbol (
     id INTEGER PRIMARY KEY,
-    name TEXT NOT NULL UNIQUE
+    name TEXT NOT NULL UNIQUE,
+    demangled TEXT,
+    first_seen TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
 );
 
 -- Symbol service table --
@@ -80,4 +234,44 @@ CREATE TABLE IF NOT EXISTS service_symbol (
     PRIMARY KEY (service_id, symbol_id),
     FOREIGN KEY (service_id) REFERENCES service(id),
     FOREIGN KEY (symbol_id) REFERENCES symbol(id)
-);
\ No newline at end of file
+);
+
+-- XPC service table --
+-- XPC services embedded in bundles ("XPCServices/*.xpc"), reachable by their
+-- bundle identifier. "executable" is the binary of the service and
+-- "container" the bundle embedding it (e.g. a framework). "service_type" is
+-- the ServiceType of the XPCService dictionary of its Info.plist
+-- ('Application' when unset, 'User' or 'System').
+-- "service_id" is the binary service analyzed from the executable, if any.
+CREATE TABLE IF NOT EXISTS xpc_service (
+    id INTEGER PRIMARY KEY,
+    bundle_id TEXT NOT NULL,
+    bundle_path TEXT UNIQUE,
+    executable TEXT,
+    container TEXT,
+    service_type TEXT,
+    service_id INTEGER,
+    FOREIGN KEY (service_id) REFERENCES service(id)
+);
+-- Review table --
+-- Triage status set from the service pages while auditing: 'reviewed' or
+-- 'flagged', with an optional note. It's the only table the web server writes.
+CREATE TABLE IF NOT EXISTS review (
+    service_id INTEGER PRIMARY KEY,
+    status TEXT NOT NULL,
+    note TEXT,
+    updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    FOREIGN KEY (service_id) REFERENCES service(id)
+);
+-- Indexes --
+-- The join tables' primary keys start with "service_id", so looking services
+-- up from an entitlement, library or symbol needs an index on the other column.
+-- The "name" columns of entitlements and symbols are UNIQUE and thus already
+-- indexed. Library names aren't unique, and are only searched with LIKE
+-- patterns, which an index doesn't serve.
+-- Labels are compared case insensitively.
+CREATE INDEX IF NOT EXISTS service_label_nocase ON service (label COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS mach_service_service_id ON mach_service (service_id);
+CREATE INDEX IF NOT EXISTS service_entitlement_entitlement_id ON service_entitlement (entitlement_id);
+CREATE INDEX IF NOT EXISTS service_library_library_id ON service_library (library_id);
+CREATE INDEX IF NOT EXISTS service_symbol_symbol_id ON service_symbol (symbol_id);
@@ -1,8 +1,41 @@
 // HTML Constants
-pub const HTML_HEADER: &str = "
-    <head>
+pub const HTML_HEADER: &str = r#"
         <title>Dora - the explorer</title>
-    </head>";
+        <script>
+            // Sort the rows of "table.sortable" by the clicked column, toggling the direction
+            document.addEventListener("click", (event) => {
+                const th = event.target.closest("table.sortable th");
+                if (!th) return;
+                const tbody = th.closest("table").tBodies[0];
+                const column = th.cellIndex;
+                const ascending = th.dataset.order !== "asc";
+                th.dataset.order = ascending ? "asc" : "desc";
+                Array.from(tbody.rows)
+                    .sort((a, b) => {
+                        const order = a.cells[column].textContent.localeCompare(b.cells[column].textContent);
+                        return ascending ? order : -order;
+                    })
+                    .forEach((row) => tbody.appendChild(row));
+            });
+        </script>"#;
+
+// Left out with "--no-style", for scraping
+pub const HTML_STYLE: &str = r#"
+        <style>
+            body { font-family: ui-monospace, Menlo, Consolas, monospace; font-size: 13px; margin: 1em 2em; }
+            table { border-collapse: collapse; margin: 0.5em 0; }
+            th, td { border: 1px solid #ddd; padding: 2px 8px; text-align: left; vertical-align: top; }
+            thead th { background: #eee; }
+            table.sortable thead th { cursor: pointer; }
+            tbody tr:nth-child(even) { background: #f6f6f6; }
+            table.details th { background: #eee; white-space: nowrap; }
+            section.service { border-top: 2px solid #ccc; margin-top: 1em; }
+            summary { cursor: pointer; }
+            summary h3 { display: inline; }
+            details { margin: 0.5em 0; }
+            mark { background: #fff3a0; }
+            pre { background: #f6f6f6; padding: 0.5em; }
+        </style>"#;
 
 pub const HTML_BODY_TITLE: &str = "
     <h1>Dora - the explorer</h1>
@@ -11,87 +44,681 @@ pub const HTML_BODY_TITLE: &str = "
 pub const HTML_FORM_FIELDS: &str = r#"<br>
                     <label for="service">Service:</label>
                     <input type="text" name="service" id="service">
+                    <input type="checkbox" name="fuzzy" id="fuzzy">
+                    <label for="fuzzy">Fuzzy match, closest labels first</label>
+                    <br>
+                    <label for="path">Path:</label>
+                    <input type="text" name="path" id="path">
                     <br>
                     <label for="entitlement">Entitlement:</label>
                     <input type="text" name="entitlement" id="entitlement">
                     <br>
+                    <label for="entitlement_value">Entitlement value:</label>
+                    <input type="text" name="entitlement_value" id="entitlement_value">
+                    <br>
                     <label for="library">Library:</label>
                     <input type="text" name="library" id="library">
                     <br>
                     <label for="symbol">Symbol:</label>
                     <input type="text" name="symbol" id="symbol">
+                    <input type="checkbox" name="exact" id="exact">
+                    <label for="exact">Exact symbol match</label>
+                    <input type="checkbox" name="case_sensitive" id="case_sensitive">
+                    <label for="case_sensitive">Case-sensitive symbol match</label>
+                    <br>
+                    <input type="checkbox" name="wildcards" id="wildcards">
+                    <label for="wildcards">Interpret wildcards in search terms</label>
+                    <br>
+                    <label for="sort">Sort by:</label>
+                    <select name="sort" id="sort">
+                        <option value="label">Label</option>
+                        <option value="path">Path</option>
+                        <option value="natural">Label, numbers by value</option>
+                    </select>
                     <br>
                     <button type="submit">Submit</button>"#;
 
+// Value of the "db" parameter that searches every available database
+pub const ALL_DATABASES: &str = "all";
+
 // Web server IP and port
 pub static LISTENING_ADDRESS: &str = "127.0.0.1";
 pub static LISTENING_PORT: u16 = 8778;
 
+// Cookie remembering the "--token" secret once given as a "token" parameter,
+// so that the links of the pages keep working in a browser
+pub const TOKEN_COOKIE: &str = "dora_token";
+
+// Maximum number of labels "/api/services/batch" looks up in one request
+pub const MAX_BATCH_LABELS: usize = 1000;
+
+// Number of searches remembered in the "Recent searches" list of the index page
+pub const MAX_RECENT_QUERIES: usize = 20;
+
+// Exit codes of "dora scan": the database was built but errors were logged
+// along the way, or the scan couldn't run at all (invalid name, missing
+// tools, database error, ...)
+pub const EXIT_PARTIAL: i32 = 1;
+pub const EXIT_FATAL: i32 = 2;
+
+// Number of processed files between two progress log lines when "--no-progress" is set
+pub const PROGRESS_LOG_INTERVAL: u64 = 100;
+
+// Number of files whose rows a scan commits at once: committing every
+// statement would sync the database file tens of thousands of times, and a
+// single transaction would lose the whole scan to an interruption
+pub const SCAN_BATCH_SIZE: usize = 500;
+
+// Tables of the schema, as listed by an up-to-date database
+pub const SCHEMA_TABLES: &str =
+    "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'";
+
+// Defaults "dora migrate" gives columns the schema declares NOT NULL without
+// one, as (table, column, default): services of databases built before binaries
+// were scanned all came from plists
+pub const MIGRATION_DEFAULTS: &[(&str, &str, &str)] = &[("service", "source", "'plist'")];
+
+// Connection settings of a scan. The database is in WAL mode (see
+// creation_query.sql), where syncing at checkpoints only is safe; temporary
+// tables and indexes stay in memory.
+pub const SCAN_PRAGMAS: &str = "PRAGMA synchronous = NORMAL; PRAGMA temp_store = MEMORY;";
+
+// Number of services a page of "/services", "/by-flag" or "/origin" lists
+// when "per_page" isn't given, and the most it accepts
+pub const DEFAULT_PER_PAGE: usize = 100;
+pub const MAX_PER_PAGE: usize = 1000;
+
+// Depth of the "/closure" route when not given, and the maximum it accepts
+pub const CLOSURE_DEFAULT_DEPTH: u32 = 5;
+pub const CLOSURE_MAX_DEPTH: u32 = 20;
+
+// Seconds an external tool may run on a binary before it is killed, unless
+// set with "--tool-timeout"
+pub const TOOL_TIMEOUT_SECS: u64 = 60;
+
+// Number of times launching an external tool is retried when the system is
+// temporarily out of processes
+pub const TOOL_SPAWN_RETRIES: u32 = 3;
+
+// Content type of the databases sent by "/download"
+pub const SQLITE_CONTENT_TYPE: &str = "application/vnd.sqlite3";
+
+// Content type of the XML plist services sent by "/service/export"
+pub const PLIST_CONTENT_TYPE: &str = "application/x-plist";
+
+// Values of the "source" column of the service table
+pub const SOURCE_PLIST: &str = "plist";
+pub const SOURCE_BINARY: &str = "binary";
+
+// Values of the "kind" column of the service table
+pub const KIND_DAEMON: &str = "daemon";
+pub const KIND_AGENT: &str = "agent";
+pub const KIND_BINARY: &str = "binary";
+pub const KIND_KEXT: &str = "kext";
+pub const KIND_DEXT: &str = "dext";
+
+// Values of the "binary_type" column of the service table
+pub const BINARY_TYPE_MACHO: &str = "macho";
+pub const BINARY_TYPE_SCRIPT: &str = "script";
+
+// Values of the "entitlements_status" column of the service table
+pub const ENTITLEMENTS_PRESENT: &str = "present";
+pub const ENTITLEMENTS_NONE: &str = "none";
+pub const ENTITLEMENTS_ERROR: &str = "error";
+
+// Number of bytes read at the start of a script to find its shebang line
+pub const SHEBANG_MAX_LENGTH: u64 = 512;
+
+// Built-in launchd folder to execution context mapping: jobs of a plist under
+// one of these folders run as "root" (system domain) or as the logged-in
+// "user" (per-user domain). The longest matching folder wins.
+// It can be extended or overridden with "--context-map <FILE>".
+pub const EXECUTION_CONTEXTS: &[(&str, &str)] = &[
+    ("/System/Library/LaunchDaemons", "root"),
+    ("/System/Library/LaunchAgents", "user"),
+    ("/Library/LaunchDaemons", "root"),
+    ("/Library/LaunchAgents", "user"),
+];
+
+// Launchd folders and folders of binaries of third-party software, only
+// scanned with "--third-party": jobs installed by applications, and tools
+// installed by hand or by a package manager
+pub const THIRD_PARTY_LAUNCH_PATHS: &[&str] = &["/Library/LaunchAgents", "/Library/LaunchDaemons"];
+pub const THIRD_PARTY_BINARY_FOLDERS: &[&str] = &["/usr/local"];
+
+// Services whose plist or binary lies below one of these folders are tagged
+// as third-party software rather than part of the OS
+pub const THIRD_PARTY_FOLDERS: &[&str] = &[
+    "/Library/LaunchAgents",
+    "/Library/LaunchDaemons",
+    "/Library/PrivilegedHelperTools",
+    "/Library/Extensions",
+    "/Library/DriverExtensions",
+    "/usr/local",
+];
+
+// Folders holding the dyld shared cache, from the current layout (macOS 13
+// and later) back to the oldest one
+pub const SHARED_CACHE_FOLDERS: &[&str] = &[
+    "/System/Volumes/Preboot/Cryptexes/OS/System/Library/dyld",
+    "/System/Library/dyld",
+    "/System/Library/Caches/com.apple.dyld",
+];
+
+// Built-in entitlement to symbol mapping used to flag over-privileged services:
+// a service holding one of these entitlements is expected to import at least one
+// symbol matching the (GLOB) patterns next to it.
+// It can be extended or overridden with "--entitlement-symbols <FILE>".
+pub const ENTITLEMENT_SYMBOLS: &[(&str, &[&str])] = &[
+    ("com.apple.private.tcc.allow", &["_TCC*"]),
+    (
+        "com.apple.system-task-ports",
+        &["_task_for_pid", "_task_read_for_pid"],
+    ),
+    (
+        "com.apple.security.cs.debugger",
+        &["_task_for_pid", "_ptrace"],
+    ),
+    (
+        "com.apple.security.cs.allow-jit",
+        &["_pthread_jit_write_protect_np", "_mmap"],
+    ),
+    (
+        "com.apple.private.security.clear-library-validation",
+        &["_dlopen*"],
+    ),
+    (
+        "com.apple.private.kernel.jetsam",
+        &["_memorystatus_control"],
+    ),
+];
+
+// Built-in list of dangerous symbols (GLOB patterns, as "nm" names them) for
+// "/dangerous-symbols": importing one of them is worth a closer look.
+// It can be replaced with "--dangerous-symbols <FILE>".
+pub const DANGEROUS_SYMBOLS: &[&str] = &[
+    "_task_for_pid",
+    "_task_read_for_pid",
+    "_processor_set_tasks",
+    "_mach_vm_write",
+    "_mach_vm_protect",
+    "_vm_write",
+    "_thread_create_running",
+    "_ptrace",
+    "_NSCreateObjectFileImageFromMemory",
+    "_NSLinkModule",
+    "_dlopen_from_memory",
+    "_csops",
+];
+
 // SQLite queries
 
 // Insert queries
-pub const INSERT_MACH_SERVICE: &str =
-    "INSERT OR IGNORE INTO mach_service (name, value, service_id) VALUES (?1, ?2, ?3)";
+pub const INSERT_BINARY_SERVICE: &str =
+    "INSERT INTO service (label, source, path, kind) VALUES (?1, ?2, ?3, ?4)";
+
+pub const INSERT_MACH_SERVICE: &str = "INSERT OR IGNORE INTO mach_service (name, value, service_id, host_special_port) VALUES (?1, ?2, ?3, ?4)";
+
+pub const INSERT_ENV_VAR: &str =
+    "INSERT OR IGNORE INTO env_var (service_id, name, value) VALUES (?1, ?2, ?3)";
 
 pub const INSERT_SERVICE_ENTITLEMENT: &str = "INSERT OR IGNORE INTO service_entitlement (service_id, entitlement_id, value) VALUES (?1, ?2, ?3)";
 
 pub const INSERT_LIBRARY: &str =
     "INSERT OR IGNORE INTO service_library (service_id, library_id) VALUES (?1, ?2)";
 
+pub const INSERT_LIBRARY_DEPENDENCY: &str =
+    "INSERT OR IGNORE INTO library_dependency (library_id, dependency_id) VALUES (?1, ?2)";
+
+pub const UPDATE_KEEP_ALIVE_CONDITIONS: &str =
+    "UPDATE service SET keep_alive_conditions = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_SIGNING_INFO: &str = "UPDATE service SET codesign_flags = ?1, \
+     platform_identifier = ?2, team_id = ?3 WHERE id = ?4";
+
+pub const UPDATE_SERVICE_PROGRAM: &str = "UPDATE service SET program = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_PLIST_TARGET: &str = "UPDATE service SET plist_target = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_ENTITLEMENTS_STATUS: &str =
+    "UPDATE service SET entitlements_status = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_BUNDLE: &str =
+    "UPDATE service SET bundle_path = ?1, bundle_id = ?2 WHERE id = ?3";
+
+pub const UPDATE_SERVICE_RESTRICTED: &str = "UPDATE service SET is_restricted = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_ARCH: &str =
+    "UPDATE service SET arch = ?1, is_32bit_only = ?2 WHERE id = ?3";
+
+// Imported symbols, as listed by nm, of the functions loading code at runtime
+pub const DYNAMIC_LOADING_SYMBOLS: &[&str] = &["_dlopen", "_dlsym", "_NSAddImage"];
+
+pub const UPDATE_SERVICE_DYNAMIC_LOADING: &str =
+    "UPDATE service SET uses_dynamic_loading = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_BUILD_VERSION: &str =
+    "UPDATE service SET min_os = ?1, sdk = ?2 WHERE id = ?3";
+
+pub const UPDATE_SERVICE_RUN_AS_GROUP: &str = "UPDATE service SET run_as_group = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_SESSION_TYPES: &str =
+    "UPDATE service SET session_types = ?1 WHERE id = ?2";
+
+// What the risk score of every service is computed from (see "risk_factors"):
+// id, whether it runs as root, RunAtLoad, KeepAlive, whether a non-root user
+// could replace its binary or one of its libraries
+pub const SERVICE_RISK_FACTS: &str = "SELECT s.id, s.run_as_user = 'root', s.run_at_load = 1, \
+     s.keep_alive = 1, s.writable_by_nonroot = 1, \
+     EXISTS (SELECT 1 FROM service_library sl JOIN library l ON l.id = sl.library_id \
+          WHERE sl.service_id = s.id AND l.writable_by_nonroot = 1) \
+     FROM service s";
+
+pub const SERVICE_ENTITLEMENT_NAMES: &str = "SELECT e.name FROM service_entitlement se \
+     JOIN entitlement e ON e.id = se.entitlement_id \
+     WHERE se.service_id = ?1";
+
+pub const UPDATE_SERVICE_RISK: &str =
+    "UPDATE service SET risk_score = ?1, risk_factors = ?2 WHERE id = ?3";
+
+// Tag the services whose plist or binary lies below a folder (?1, ending with
+// a slash) as third-party
+pub const UPDATE_THIRD_PARTY_SERVICES: &str = "UPDATE service SET third_party = 1 \
+     WHERE substr(plist_path, 1, length(?1)) = ?1 OR substr(path, 1, length(?1)) = ?1";
+
+pub const UPDATE_SERVICE_WRITABLE_BY_NONROOT: &str =
+    "UPDATE service SET writable_by_nonroot = ?1 WHERE id = ?2";
+
+pub const UPDATE_SERVICE_BINARY_TYPE: &str =
+    "UPDATE service SET binary_type = ?1, interpreter = ?2 WHERE id = ?3";
+
+pub const UPDATE_LIBRARY_SIGNING_INFO: &str =
+    "UPDATE library SET identifier = ?1, team_id = ?2 WHERE id = ?3";
+
+pub const UPDATE_LIBRARY_SHARED_CACHE: &str =
+    "UPDATE library SET in_shared_cache = ?1, on_disk = ?2 WHERE id = ?3";
+
+pub const UPDATE_LIBRARY_WRITABLE_BY_NONROOT: &str =
+    "UPDATE library SET writable_by_nonroot = ?1 WHERE id = ?2";
+
+// Missing libraries that could be planted come first
+pub const PHANTOM_LIBRARIES: &str = "SELECT l.path, l.writable_by_nonroot, COUNT(sl.service_id) \
+     FROM library l \
+     LEFT JOIN service_library sl ON sl.library_id = l.id \
+     WHERE l.on_disk = 0 AND l.in_shared_cache = 0 \
+     GROUP BY l.id \
+     ORDER BY l.writable_by_nonroot = 1 DESC, l.path";
+
 pub const INSERT_SYMBOL: &str =
     "INSERT OR IGNORE INTO service_symbol (service_id, symbol_id) VALUES (?1, ?2)";
 
+// Delete queries, used to re-analyze a single service
+pub const DELETE_SERVICE_ENTITLEMENTS: &str =
+    "DELETE FROM service_entitlement WHERE service_id = ?1";
+
+pub const DELETE_SERVICE_LIBRARIES: &str = "DELETE FROM service_library WHERE service_id = ?1";
+
+pub const DELETE_SERVICE_SYMBOLS: &str = "DELETE FROM service_symbol WHERE service_id = ?1";
+
+pub const INSERT_SCAN_INFO: &str = "INSERT INTO scan_info \
+     (product_name, product_version, build_version, launch_paths, scan_dirs, dora_version, scanned_at, root) \
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), ?7)";
+
 // Select queries
+// Queries listing services as (label, path) have no ORDER BY: it is appended
+// according to the requested "SortOrder". Lists in a fixed order compare
+// labels regardless of case as well.
 pub const SERVICES_BY_ENTITLEMENT_AND_SYMBOL: &str = "SELECT DISTINCT s.label, s.path \
      FROM service s \
      JOIN service_entitlement se ON s.id = se.service_id \
      JOIN entitlement e ON se.entitlement_id = e.id \
      JOIN service_symbol ss ON s.id = ss.service_id \
      JOIN symbol sy ON ss.symbol_id = sy.id \
-     WHERE e.name LIKE ?1 COLLATE NOCASE AND sy.name GLOB ?2 ORDER BY s.label";
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name GLOB ?2 OR sy.demangled GLOB ?2)";
+
+pub const SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_entitlement se ON s.id = se.service_id \
+     JOIN entitlement e ON se.entitlement_id = e.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name = ?2 OR sy.demangled = ?2)";
+
+// Case-insensitive variants of the symbol searches, like the entitlement and
+// library ones; the GLOB/"=" queries above are the case-sensitive ones
+pub const SERVICES_BY_ENTITLEMENT_AND_SYMBOL_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_entitlement se ON s.id = se.service_id \
+     JOIN entitlement e ON se.entitlement_id = e.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name LIKE ?2 COLLATE NOCASE ESCAPE '\\' OR sy.demangled LIKE ?2 COLLATE NOCASE ESCAPE '\\')";
+
+pub const SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_entitlement se ON s.id = se.service_id \
+     JOIN entitlement e ON se.entitlement_id = e.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name = ?2 COLLATE NOCASE OR sy.demangled = ?2 COLLATE NOCASE)";
+
+// Services linking a library (LIKE ?1) and importing a symbol (?2), with the
+// same four symbol matching variants as the entitlement and symbol search
+pub const SERVICES_BY_SYMBOL_AND_LIBRARY: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_library sl ON s.id = sl.service_id \
+     JOIN library l ON sl.library_id = l.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE l.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name GLOB ?2 OR sy.demangled GLOB ?2)";
+
+pub const SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_library sl ON s.id = sl.service_id \
+     JOIN library l ON sl.library_id = l.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE l.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name = ?2 OR sy.demangled = ?2)";
+
+pub const SERVICES_BY_SYMBOL_AND_LIBRARY_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_library sl ON s.id = sl.service_id \
+     JOIN library l ON sl.library_id = l.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE l.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name LIKE ?2 COLLATE NOCASE ESCAPE '\\' OR sy.demangled LIKE ?2 COLLATE NOCASE ESCAPE '\\')";
+
+pub const SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_library sl ON s.id = sl.service_id \
+     JOIN library l ON sl.library_id = l.id \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE l.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' AND (sy.name = ?2 COLLATE NOCASE OR sy.demangled = ?2 COLLATE NOCASE)";
 
 pub const SERVICES_BY_LABEL_PATTERN: &str = "SELECT DISTINCT s.label, s.path \
      FROM service s \
-     WHERE s.label GLOB ?1 ORDER BY s.label";
+     WHERE s.label GLOB ?1";
+
+// Candidates of a fuzzy label search, ranked in Rust
+pub const SERVICE_LABELS_AND_PATHS: &str = "SELECT DISTINCT s.label, s.path FROM service s";
+
+// Number of best matches listed by a fuzzy label search
+pub const FUZZY_MAX_RESULTS: usize = 100;
+
+pub const SERVICES_BY_PATH_PATTERN: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     WHERE s.path GLOB ?1";
+
+// NULL parameters disable the corresponding filter
+pub const SERVICES_BY_FILTERS: &str = "SELECT s.label, s.path \
+     FROM service s \
+     WHERE (?1 IS NULL OR s.disabled = ?1) \
+     AND (?2 IS NULL OR s.run_at_load = ?2) \
+     AND (?3 IS NULL OR s.keep_alive = ?3) \
+     AND (?4 IS NULL OR EXISTS (SELECT 1 FROM env_var ev \
+          WHERE ev.service_id = s.id AND ev.name GLOB 'DYLD_*') = ?4) \
+     AND (?5 IS NULL OR (s.arch IS NOT NULL \
+          AND (',' || s.arch || ',' GLOB '*,arm64*') = ?5)) \
+     AND (?6 IS NULL OR ',' || s.session_types || ',' LIKE '%,' || ?6 || ',%') \
+     AND (?7 IS NULL OR s.third_party = ?7)";
+
+// ?1 is a flag name, ?2 whether the signature must have it (1) or not (0).
+// Services whose flags are unknown are left out either way.
+pub const SERVICES_BY_CODESIGN_FLAG: &str = "SELECT s.label, s.path \
+     FROM service s \
+     WHERE s.codesign_flags IS NOT NULL \
+     AND (instr(',' || s.codesign_flags || ',', ',' || ?1 || ',') > 0) = ?2";
+
+// Services signed by Apple as platform binaries (?1 = 1) or by a third-party
+// team (?1 = 0). Unsigned and ad-hoc signed binaries are neither.
+pub const SERVICES_BY_SIGNING_ORIGIN: &str = "SELECT s.label, s.path \
+     FROM service s \
+     WHERE CASE \
+         WHEN s.team_id = 'not set' AND s.platform_identifier IS NOT NULL THEN 1 \
+         WHEN s.team_id NOT IN ('not set', 'Unknown') THEN 0 \
+     END = ?1";
+
+pub const WRITABLE_SERVICES: &str = "SELECT s.label, s.path, s.run_as_user \
+     FROM service s \
+     WHERE s.writable_by_nonroot = 1 \
+     ORDER BY s.run_as_user = 'root' DESC, s.label COLLATE NOCASE";
+
+// Statuses a service can be given from its page, besides not reviewed
+pub const REVIEW_STATUSES: &[&str] = &["reviewed", "flagged"];
+
+pub const UPSERT_REVIEW: &str = "INSERT INTO review (service_id, status, note) \
+     SELECT id, ?3, ?4 FROM service WHERE label = ?1 AND source = ?2 \
+     ON CONFLICT (service_id) DO UPDATE SET status = excluded.status, note = excluded.note, \
+     updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+pub const DELETE_REVIEW: &str = "DELETE FROM review \
+     WHERE service_id IN (SELECT id FROM service WHERE label = ?1 AND source = ?2)";
+
+pub const SERVICE_EXISTS: &str =
+    "SELECT EXISTS (SELECT 1 FROM service WHERE label = ?1 AND source = ?2)";
+
+pub const REVIEW_BY_LABEL: &str = "SELECT r.status, r.note, r.updated_at FROM review r \
+     JOIN service s ON s.id = r.service_id \
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2";
+
+// Flagged last, so that it wins when a job and a binary sharing a label differ
+pub const REVIEW_STATUSES_BY_LABEL: &str = "SELECT s.label, r.status FROM review r \
+     JOIN service s ON s.id = r.service_id \
+     ORDER BY r.status = 'flagged'";
+
+// The executable was scanned as a binary service before XPC services are saved
+pub const INSERT_XPC_SERVICE: &str = "INSERT OR IGNORE INTO xpc_service \
+     (bundle_id, bundle_path, executable, container, service_type, service_id) \
+     VALUES (?1, ?2, ?3, ?4, ?5, (SELECT id FROM service WHERE path = ?3 AND source = 'binary'))";
+
+pub const XPC_SERVICES: &str = "SELECT x.bundle_id, x.bundle_path, x.executable, x.container, x.service_type, s.label \
+     FROM xpc_service x \
+     LEFT JOIN service s ON s.id = x.service_id \
+     ORDER BY x.bundle_id COLLATE NOCASE";
+
+// Without library validation, the code they load needn't be signed by Apple
+// or the same team: those come first after root services
+pub const DYNAMIC_LOADERS: &str = "SELECT s.label, s.path, s.run_as_user, s.codesign_flags \
+     FROM service s \
+     WHERE s.uses_dynamic_loading = 1 \
+     ORDER BY s.run_as_user = 'root' DESC, \
+     instr(',' || s.codesign_flags || ',', ',library-validation,') > 0, s.label COLLATE NOCASE";
 
-pub const SERVICE_BY_LABEL: &str = "SELECT s.label, s.path, s.run_as_user, s.run_at_load, s.keep_alive, s.plist_path \
+// Services with a risk score, highest first
+pub const TOP_RISK_SERVICES: &str = "SELECT s.label, s.path, s.run_as_user, s.risk_score, s.risk_factors \
      FROM service s \
-     WHERE s.label = ?1 COLLATE NOCASE";
+     WHERE s.risk_score > 0 \
+     ORDER BY s.risk_score DESC, s.label COLLATE NOCASE";
+
+// Services running as root whose binary dyld lets DYLD_INSERT_LIBRARIES into:
+// neither a "__RESTRICT" segment nor the "restrict" code signing flag
+pub const UNRESTRICTED_ROOT_SERVICES: &str = "SELECT s.label, s.path, s.codesign_flags \
+     FROM service s \
+     WHERE s.run_as_user = 'root' AND s.is_restricted = 0 \
+     ORDER BY s.label COLLATE NOCASE";
+
+// Binaries without a 64-bit slice, which current macOS can't run
+pub const THIRTY_TWO_BIT_SERVICES: &str = "SELECT s.label, s.path, s.run_as_user, s.arch \
+     FROM service s \
+     WHERE s.is_32bit_only = 1 \
+     ORDER BY s.label COLLATE NOCASE";
+
+pub const SCRIPT_SERVICES: &str = "SELECT s.label, s.path, s.run_as_user, s.interpreter, s.writable_by_nonroot \
+     FROM service s \
+     WHERE s.binary_type = 'script' \
+     ORDER BY s.run_as_user = 'root' DESC, s.writable_by_nonroot = 1 DESC, s.label COLLATE NOCASE";
+
+pub const SERVICES_BY_ENTITLEMENT_NAME: &str = "SELECT DISTINCT s.id, s.label, s.path \
+     FROM service s \
+     JOIN service_entitlement se ON s.id = se.service_id \
+     JOIN entitlement e ON se.entitlement_id = e.id \
+     WHERE e.name = ?1 AND se.value != 'false' \
+     AND EXISTS (SELECT 1 FROM service_symbol ss WHERE ss.service_id = s.id) \
+     ORDER BY s.label COLLATE NOCASE";
+
+pub const SERVICE_HAS_SYMBOL: &str = "SELECT EXISTS (SELECT 1 FROM service_symbol ss \
+     JOIN symbol sy ON ss.symbol_id = sy.id \
+     WHERE ss.service_id = ?1 AND sy.name GLOB ?2)";
+
+pub const SCAN_INFO: &str = "SELECT product_name, product_version, build_version, launch_paths, scan_dirs, dora_version, scanned_at \
+     FROM scan_info ORDER BY id DESC LIMIT 1";
+
+pub const SCAN_ROOT: &str = "SELECT root FROM scan_info ORDER BY id DESC LIMIT 1";
+
+pub const SCAN_COUNTS: &str = "SELECT (SELECT COUNT(*) FROM service), (SELECT COUNT(*) FROM entitlement), \
+     (SELECT COUNT(*) FROM library), (SELECT COUNT(*) FROM symbol)";
+
+// Every entitlement with the number of services holding it
+pub const ENTITLEMENT_SERVICE_COUNTS: &str = "SELECT e.name, COUNT(DISTINCT se.service_id) \
+     FROM entitlement e \
+     LEFT JOIN service_entitlement se ON se.entitlement_id = e.id \
+     GROUP BY e.id ORDER BY e.name";
+
+// Entitlements, libraries and symbols first seen at or after a date, with the
+// number of services using them. ?1 is a (prefix of a) UTC timestamp such as
+// "2025-06-01" or "2025-06-01T12:00:00Z", compared as text.
+pub const NEW_ENTITLEMENTS_SINCE: &str = "SELECT e.name, e.first_seen, COUNT(se.service_id) \
+     FROM entitlement e \
+     LEFT JOIN service_entitlement se ON se.entitlement_id = e.id \
+     WHERE e.first_seen >= ?1 \
+     GROUP BY e.id ORDER BY e.first_seen, e.name";
+
+pub const NEW_LIBRARIES_SINCE: &str = "SELECT l.path, l.first_seen, COUNT(sl.service_id) \
+     FROM library l \
+     LEFT JOIN service_library sl ON sl.library_id = l.id \
+     WHERE l.first_seen >= ?1 \
+     GROUP BY l.id ORDER BY l.first_seen, l.path";
+
+pub const NEW_SYMBOLS_SINCE: &str = "SELECT sy.name, sy.first_seen, COUNT(ss.service_id) \
+     FROM symbol sy \
+     LEFT JOIN service_symbol ss ON ss.symbol_id = sy.id \
+     WHERE sy.first_seen >= ?1 \
+     GROUP BY sy.id ORDER BY sy.first_seen, sy.name";
+
+// Export queries: every service, then its related rows by service id (?1)
+pub const EXPORT_SERVICES: &str = "SELECT s.id, s.label, s.source, s.path, s.run_as_user, s.run_at_load, \
+     s.keep_alive, s.keep_alive_conditions, s.plist_path, s.disabled, s.writable_by_nonroot, \
+     s.codesign_flags, s.platform_identifier, s.binary_type, s.interpreter, s.min_os, s.sdk, s.run_as_group, s.kind, s.arch, s.plist_target, s.program, s.uses_dynamic_loading, s.team_id, s.bundle_path, s.bundle_id, s.entitlements_status, s.session_types, s.is_32bit_only, s.third_party, s.risk_score, s.risk_factors, s.is_restricted \
+     FROM service s ORDER BY s.id";
+
+// Same columns as EXPORT_SERVICES, for the services of a label (?1)
+pub const EXPORT_SERVICES_BY_LABEL: &str = "SELECT s.id, s.label, s.source, s.path, s.run_as_user, s.run_at_load, \
+     s.keep_alive, s.keep_alive_conditions, s.plist_path, s.disabled, s.writable_by_nonroot, \
+     s.codesign_flags, s.platform_identifier, s.binary_type, s.interpreter, s.min_os, s.sdk, s.run_as_group, s.kind, s.arch, s.plist_target, s.program, s.uses_dynamic_loading, s.team_id, s.bundle_path, s.bundle_id, s.entitlements_status, s.session_types, s.is_32bit_only, s.third_party, s.risk_score, s.risk_factors, s.is_restricted \
+     FROM service s WHERE s.label = ?1 COLLATE NOCASE ORDER BY s.id";
+
+pub const EXPORT_MACH_SERVICES: &str = "SELECT ms.name, ms.value, ms.host_special_port \
+     FROM mach_service ms WHERE ms.service_id = ?1 ORDER BY ms.name";
+
+pub const EXPORT_ENTITLEMENTS: &str = "SELECT e.name, se.value FROM service_entitlement se \
+     JOIN entitlement e ON e.id = se.entitlement_id \
+     WHERE se.service_id = ?1 ORDER BY e.name";
+
+pub const EXPORT_LIBRARIES: &str = "SELECT l.path FROM service_library sl \
+     JOIN library l ON l.id = sl.library_id \
+     WHERE sl.service_id = ?1 ORDER BY l.path";
+
+pub const EXPORT_ENV_VARS: &str =
+    "SELECT ev.name, ev.value FROM env_var ev WHERE ev.service_id = ?1 ORDER BY ev.name";
+
+pub const UPDATE_SYMBOL_DEMANGLED: &str =
+    "UPDATE symbol SET demangled = ?1 WHERE id = ?2 AND demangled IS NULL";
+
+pub const EXPORT_SYMBOLS: &str = "SELECT sy.name, sy.demangled FROM service_symbol ss \
+     JOIN symbol sy ON sy.id = ss.symbol_id \
+     WHERE ss.service_id = ?1 ORDER BY sy.name";
+
+pub const SERVICE_ID_AND_PATH_BY_LABEL: &str =
+    "SELECT s.id, s.path FROM service s WHERE s.label = ?1 ORDER BY s.id";
+
+pub const SERVICES_ID_SOURCE_PATH_BY_LABEL: &str =
+    "SELECT s.id, s.source, s.path FROM service s WHERE s.label = ?1 COLLATE NOCASE ORDER BY s.id";
+
+pub const SERVICE_SOURCES_BY_LABEL: &str = "SELECT s.source FROM service s WHERE s.label = ?1";
+
+pub const SERVICE_BY_LABEL: &str = "SELECT s.label, s.path, s.run_as_user, s.run_at_load, s.keep_alive, s.plist_path, s.disabled, s.keep_alive_conditions, s.source, s.codesign_flags, s.platform_identifier, s.binary_type, s.interpreter, s.min_os, s.sdk, s.run_as_group, s.kind, s.arch, s.plist_target, s.program, s.uses_dynamic_loading, s.team_id, s.bundle_path, s.bundle_id, s.entitlements_status, s.session_types, s.is_32bit_only, s.third_party, s.risk_score, s.risk_factors, s.is_restricted \
+     FROM service s \
+     WHERE s.label = ?1 COLLATE NOCASE ORDER BY s.id";
 
 pub const SERVICES_BY_ENTITLEMENT: &str = "SELECT DISTINCT s.label, s.path \
      FROM service s \
      JOIN service_entitlement se ON s.id = se.service_id \
      JOIN entitlement e ON se.entitlement_id = e.id \
-     WHERE e.name LIKE ?1 COLLATE NOCASE ORDER BY s.label";
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\'";
+
+// An empty entitlement name pattern ("%%") matches any entitlement
+pub const SERVICES_BY_ENTITLEMENT_VALUE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_entitlement se ON s.id = se.service_id \
+     JOIN entitlement e ON se.entitlement_id = e.id \
+     WHERE e.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' \
+     AND se.value LIKE ?2 COLLATE NOCASE ESCAPE '\\'";
 
 pub const SERVICES_BY_LIBRARY: &str = "SELECT DISTINCT s.label, s.path \
      FROM service s \
      JOIN service_library sl ON s.id = sl.service_id \
      JOIN library l ON sl.library_id = l.id \
-     WHERE l.name LIKE ?1 COLLATE NOCASE ORDER BY s.label";
+     WHERE l.name LIKE ?1 COLLATE NOCASE ESCAPE '\\'";
 
+// Matching symbols are looked up first, then their services through the
+// "service_symbol" index: with a join, SQLite scans "service_symbol" instead
 pub const SERVICES_BY_SYMBOL: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     WHERE ss.symbol_id IN (SELECT sy.id FROM symbol sy WHERE sy.name GLOB ?1 OR sy.demangled GLOB ?1)";
+
+pub const SERVICES_BY_SYMBOL_EXACT: &str = "SELECT DISTINCT s.label, s.path \
      FROM service s \
      JOIN service_symbol ss ON s.id = ss.service_id \
      JOIN symbol sy ON ss.symbol_id = sy.id \
-     WHERE sy.name GLOB ?1 ORDER BY s.label";
+     WHERE (sy.name = ?1 OR sy.demangled = ?1)";
+
+pub const SERVICES_BY_SYMBOL_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     WHERE ss.symbol_id IN (SELECT sy.id FROM symbol sy WHERE sy.name LIKE ?1 COLLATE NOCASE ESCAPE '\\' OR sy.demangled LIKE ?1 COLLATE NOCASE ESCAPE '\\')";
+
+pub const SERVICES_BY_SYMBOL_EXACT_NOCASE: &str = "SELECT DISTINCT s.label, s.path \
+     FROM service s \
+     JOIN service_symbol ss ON s.id = ss.service_id \
+     WHERE ss.symbol_id IN (SELECT sy.id FROM symbol sy WHERE sy.name = ?1 COLLATE NOCASE OR sy.demangled = ?1 COLLATE NOCASE)";
 
-pub const MACH_SERVICES_BY_LABEL: &str = "SELECT ms.name FROM mach_service ms \
+pub const MACH_SERVICES_BY_LABEL: &str = "SELECT ms.name, ms.value, ms.host_special_port FROM mach_service ms \
      JOIN service s ON s.id = ms.service_id \
-     WHERE s.label = ?1 COLLATE NOCASE";
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2";
 
 pub const ENTITLEMENTS_VALUE_BY_SERVICE_LABEL: &str = "SELECT e.name AS entitlement_name, se.value AS entitlement_value \
      FROM service s \
      JOIN service_entitlement se ON s.id = se.service_id \
      JOIN entitlement e ON se.entitlement_id = e.id \
-     WHERE s.label = ?1 COLLATE NOCASE";
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2";
+
+pub const LIBRARY_ID_BY_PATH: &str = "SELECT id FROM library WHERE path = ?1";
 
-pub const LIBRARIES_BY_LABEL: &str = "SELECT l.name, l.path FROM library l \
+pub const LIBRARIES_BY_LABEL: &str = "SELECT l.name, l.path, l.identifier, l.team_id, l.in_shared_cache FROM library l \
      JOIN service_library sl ON l.id = sl.library_id \
      JOIN service s ON sl.service_id = s.id \
-     WHERE s.label = ?1 COLLATE NOCASE ORDER BY l.name";
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2 ORDER BY l.name";
+
+pub const ENV_VARS_BY_LABEL: &str = "SELECT ev.name, ev.value FROM env_var ev \
+     JOIN service s ON ev.service_id = s.id \
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2 ORDER BY ev.name";
+
+// Libraries reachable from a service (?1) through at most ?2 imports, with the
+// shortest number of imports needed to reach each of them
+pub const LIBRARY_CLOSURE: &str = "WITH RECURSIVE closure (library_id, depth) AS ( \
+         SELECT sl.library_id, 1 FROM service_library sl WHERE sl.service_id = ?1 \
+         UNION \
+         SELECT ld.dependency_id, c.depth + 1 FROM closure c \
+         JOIN library_dependency ld ON ld.library_id = c.library_id \
+         WHERE c.depth < ?2 \
+     ) \
+     SELECT l.name, l.path, l.in_shared_cache, MIN(c.depth) AS depth FROM closure c \
+     JOIN library l ON l.id = c.library_id \
+     GROUP BY l.id ORDER BY depth, l.name";
 
-pub const SYMBOLS_BY_LABEL: &str = "SELECT sy.name FROM symbol sy \
+pub const SYMBOLS_BY_LABEL: &str = "SELECT sy.name, sy.demangled FROM symbol sy \
      JOIN service_symbol ss ON sy.id = ss.symbol_id \
      JOIN service s ON ss.service_id = s.id \
-     WHERE s.label = ?1 COLLATE NOCASE ORDER BY sy.name";
+     WHERE s.label = ?1 COLLATE NOCASE AND s.source = ?2 ORDER BY sy.name";
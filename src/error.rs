@@ -0,0 +1,68 @@
+use thiserror::Error;
+
+// Errors raised while extracting data from binaries and plists and while
+// saving it to the SQLite database
+#[derive(Debug, Error)]
+pub enum DoraError {
+    // The binary to analyze doesn't exist on disk
+    #[error("binary not found: {0}")]
+    BinaryNotFound(String),
+
+    // The external tool couldn't be launched at all (e.g. not installed)
+    #[error("failed to execute {tool}: {source}")]
+    ToolUnavailable {
+        tool: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+
+    // The external tool ran but exited with a failure status
+    #[error("{tool} failed for binary: {binary}")]
+    ToolFailed { tool: &'static str, binary: String },
+
+    // The external tool ran for too long and was killed
+    #[error("{tool} timed out after {seconds}s")]
+    ToolTimeout { tool: &'static str, seconds: u64 },
+
+    // The binary's Mach-O header couldn't be parsed
+    #[error("invalid Mach-O header: {0}")]
+    InvalidMachO(String),
+
+    // The binary is signed but has no entitlements
+    #[error("no entitlements found for binary: {0}")]
+    NoEntitlements(String),
+
+    // No service with the given label in the database
+    #[error("service not found: {0}")]
+    ServiceNotFound(String),
+
+    #[error("plist error: {0}")]
+    Plist(#[from] plist::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl DoraError {
+    // Whether the error should abort the whole scan rather than only skip
+    // the current plist or binary
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, DoraError::Sqlite(_))
+    }
+
+    // The external tool the error comes from, if any
+    pub fn tool(&self) -> Option<&'static str> {
+        match self {
+            DoraError::ToolUnavailable { tool, .. }
+            | DoraError::ToolFailed { tool, .. }
+            | DoraError::ToolTimeout { tool, .. } => Some(tool),
+            _ => None,
+        }
+    }
+}
@@ -1,148 +1,659 @@
 use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::consts::{
+    SHARED_CACHE_FOLDERS, SHEBANG_MAX_LENGTH, TOOL_SPAWN_RETRIES, TOOL_TIMEOUT_SECS,
+};
+use crate::error::DoraError;
+
+// Path of an external tool: "DORA_<TOOL>" (e.g. "DORA_OTOOL", or
+// "DORA_SWIFT_DEMANGLE" for "swift-demangle") when set, e.g. to use a
+// specific toolchain, otherwise the bare name looked up in PATH
+fn tool_path(tool: &str) -> String {
+    std::env::var(format!("DORA_{}", tool.to_uppercase().replace('-', "_")))
+        .unwrap_or_else(|_| tool.to_string())
+}
+
+// Whether an external tool can be launched: an explicit path must be an
+// executable file, a bare name must be one in a folder of PATH
+fn is_tool_available(tool: &str) -> bool {
+    let program = PathBuf::from(tool_path(tool));
+    let is_executable = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+
+    if program.components().count() > 1 {
+        return is_executable(&program);
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|folder| is_executable(&folder.join(&program)))
+    })
+}
+
+// The tools, among "tools", that can't be launched
+pub fn missing_tools(tools: &[&'static str]) -> Vec<&'static str> {
+    tools
+        .iter()
+        .copied()
+        .filter(|tool| !is_tool_available(tool))
+        .collect()
+}
+
+// Seconds an external tool may run before it is killed, set by "--tool-timeout"
+static TOOL_TIMEOUT: AtomicU64 = AtomicU64::new(TOOL_TIMEOUT_SECS);
+
+pub fn set_tool_timeout(seconds: u64) {
+    TOOL_TIMEOUT.store(seconds, Ordering::Relaxed);
+}
+
+// Tool extracting the entitlements of binaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EntitlementsTool {
+    // "codesign -d --entitlements", falling back to "ldid -e" when it finds
+    // none in a signed binary and ldid is installed
+    Codesign,
+    // "ldid -e" only, e.g. for binaries not signed with Apple's toolchain
+    Ldid,
+}
+
+// Set by "--entitlements-tool"
+static ENTITLEMENTS_TOOL: OnceLock<EntitlementsTool> = OnceLock::new();
+
+pub fn set_entitlements_tool(tool: EntitlementsTool) {
+    let _ = ENTITLEMENTS_TOOL.set(tool);
+}
+
+// Launch an external tool with piped stdio.
+// Launching fails transiently (EAGAIN) when the system is out of processes:
+// retry a few times, waiting a little longer each time.
+fn spawn_tool(tool: &'static str, args: &[&str]) -> Result<Child, DoraError> {
+    let program = tool_path(tool);
+    let mut attempt = 0;
+
+    loop {
+        let spawned = Command::new(&program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        match spawned {
+            Ok(child) => return Ok(child),
+            Err(e) if e.kind() == ErrorKind::WouldBlock && attempt < TOOL_SPAWN_RETRIES => {
+                attempt += 1;
+                thread::sleep(Duration::from_millis(100 << attempt));
+            }
+            Err(source) => return Err(DoraError::ToolUnavailable { tool, source }),
+        }
+    }
+}
+
+// Read a pipe of a child to its end on a separate thread, so that a tool
+// filling one pipe while the other is being read can't block
+fn read_to_end_in_thread(mut pipe: impl Read + Send + 'static) -> Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        let _ = tx.send(buffer);
+    });
+
+    rx
+}
+
+// Run an external tool with the given arguments, optionally feeding "stdin",
+// and return its output.
+// Failing to launch the tool is reported as "ToolUnavailable", and a tool
+// still running after the timeout is killed and reported as "ToolTimeout".
+// The wall time of every run is added to the tool's timing.
+fn run_tool(tool: &'static str, args: &[&str], stdin: Option<&[u8]>) -> Result<Output, DoraError> {
+    let started = Instant::now();
+    let output = run_tool_untimed(tool, args, stdin);
+    record_timing(tool, started.elapsed());
+
+    output
+}
+
+// Text of a tool's output. Symbol names and paths may hold bytes that aren't
+// UTF-8: those are replaced with U+FFFD rather than failing the whole binary,
+// so that every other line is still parsed.
+fn output_text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+fn run_tool_untimed(
+    tool: &'static str,
+    args: &[&str],
+    stdin: Option<&[u8]>,
+) -> Result<Output, DoraError> {
+    let mut child = spawn_tool(tool, args)?;
+
+    // Dropping stdin right away closes it for tools that don't read it
+    let child_stdin = child.stdin.take().expect("Failed to open stdin");
+    if let Some(input) = stdin {
+        let input = input.to_vec();
+        let mut child_stdin = child_stdin;
+        thread::spawn(move || child_stdin.write_all(&input));
+    }
+
+    let stdout = read_to_end_in_thread(child.stdout.take().expect("Failed to open stdout"));
+    let stderr = read_to_end_in_thread(child.stderr.take().expect("Failed to open stderr"));
+
+    // Both pipes are closed once the tool exits
+    let seconds = TOOL_TIMEOUT.load(Ordering::Relaxed);
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let output = stdout
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .and_then(|stdout| {
+            stderr
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map(|stderr| (stdout, stderr))
+        });
+
+    match output {
+        Ok((stdout, stderr)) => Ok(Output {
+            status: child.wait()?,
+            stdout,
+            stderr,
+        }),
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(DoraError::ToolTimeout { tool, seconds })
+        }
+    }
+}
+
+// Make sure the binary exists before launching any tool on it
+fn ensure_binary_exists(binary_path: &str) -> Result<(), DoraError> {
+    if Path::new(binary_path).exists() {
+        Ok(())
+    } else {
+        Err(DoraError::BinaryNotFound(binary_path.to_string()))
+    }
+}
+
+// Binaries each external tool failed on during the run, as (tool, binary)
+static TOOL_FAILURES: LazyLock<Mutex<HashSet<(&'static str, String)>>> =
+    LazyLock::new(Default::default);
+
+// Remember the binary when "result" is a failure of an external tool
+fn record_failure<T>(binary_path: &str, result: Result<T, DoraError>) -> Result<T, DoraError> {
+    if let Err(e) = &result
+        && let Some(tool) = e.tool()
+    {
+        TOOL_FAILURES
+            .lock()
+            .unwrap()
+            .insert((tool, binary_path.to_string()));
+    }
+
+    result
+}
+
+// Number of binaries each external tool failed on so far
+pub fn tool_failure_counts() -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for (tool, _) in TOOL_FAILURES.lock().unwrap().iter() {
+        *counts.entry(*tool).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+// Number of runs of an external tool and the wall time they took altogether
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToolTiming {
+    pub runs: u32,
+    pub total: Duration,
+}
+
+impl ToolTiming {
+    pub fn average(&self) -> Duration {
+        self.total.checked_div(self.runs).unwrap_or_default()
+    }
+}
+
+// Time spent in each external tool during the run
+static TOOL_TIMINGS: LazyLock<Mutex<BTreeMap<&'static str, ToolTiming>>> =
+    LazyLock::new(Default::default);
+
+fn record_timing(tool: &'static str, elapsed: Duration) {
+    let mut timings = TOOL_TIMINGS.lock().unwrap();
+    let timing = timings.entry(tool).or_default();
+    timing.runs += 1;
+    timing.total += elapsed;
+}
+
+// Time spent in each external tool so far
+pub fn tool_timings() -> BTreeMap<&'static str, ToolTiming> {
+    TOOL_TIMINGS.lock().unwrap().clone()
+}
+
+// Forget the failures and timings recorded so far, so that a new scan in the
+// same process reports its own
+pub fn reset_tool_stats() {
+    TOOL_FAILURES.lock().unwrap().clear();
+    TOOL_TIMINGS.lock().unwrap().clear();
+}
+
+// Results of the extraction tools, cached in memory for the duration of the run
+// and keyed by binary path and modification time, so that a binary shared by
+// several services is only analyzed once and a modified binary is analyzed again.
+type CacheKey = (String, SystemTime);
+type ToolCache<T> = LazyLock<Mutex<HashMap<CacheKey, T>>>;
+
+static SIGNING_CACHE: ToolCache<SigningInfo> = LazyLock::new(Default::default);
+static ENTITLEMENTS_CACHE: ToolCache<JsonValue> = LazyLock::new(Default::default);
+static DEPENDENCIES_CACHE: ToolCache<Vec<String>> = LazyLock::new(Default::default);
+static SYMBOLS_CACHE: ToolCache<Vec<String>> = LazyLock::new(Default::default);
+static BUILD_VERSION_CACHE: ToolCache<BuildVersion> = LazyLock::new(Default::default);
+
+// Return the cached result of "extract" for the binary, running it on a cache miss.
+// Errors are not cached.
+fn cached<T: Clone>(
+    cache: &ToolCache<T>,
+    binary_path: &str,
+    extract: impl FnOnce(&str) -> Result<T, DoraError>,
+) -> Result<T, DoraError> {
+    let key = std::fs::metadata(binary_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(|mtime| (binary_path.to_string(), mtime));
+
+    if let Some(key) = &key
+        && let Some(value) = cache.lock().unwrap().get(key)
+    {
+        return Ok(value.clone());
+    }
+
+    let value = record_failure(binary_path, extract(binary_path))?;
+    if let Some(key) = key {
+        cache.lock().unwrap().insert(key, value.clone());
+    }
+
+    Ok(value)
+}
 
 // Get Identifier for a Mach-O binary
 // launching "codesign -dv <binary_path> 2>&1 | grep '^Identifier' | cut -d= -f2"
-pub fn get_macho_identifier(binary_path: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // Execute the codesign command to get the identifier
-    let output = std::process::Command::new("codesign")
-        .args(["-dv", binary_path])
-        .output()
-        .expect("Failed to execute codesign");
+pub fn get_macho_identifier(binary_path: &str) -> Result<String, DoraError> {
+    let signing_info = get_macho_signing_info(binary_path)?;
+
+    Ok(signing_info.identifier)
+}
+
+// Code signature of a Mach-O binary as reported by "codesign -dv"
+#[derive(Debug, Clone)]
+pub struct SigningInfo {
+    pub identifier: String,
+    // "not set" for Apple platform binaries
+    pub team_id: String,
+    // Names of the CodeDirectory flags, e.g. "runtime" (hardened runtime),
+    // "library-validation", "adhoc"
+    pub flags: Vec<String>,
+    // Only set for platform binaries
+    pub platform_identifier: Option<i64>,
+}
+
+// Get the code signature of a Mach-O binary
+// launching "codesign -dv <binary_path>" and parsing its stderr.
+pub fn get_macho_signing_info(binary_path: &str) -> Result<SigningInfo, DoraError> {
+    cached(&SIGNING_CACHE, binary_path, extract_macho_signing_info)
+}
+
+fn extract_macho_signing_info(binary_path: &str) -> Result<SigningInfo, DoraError> {
+    ensure_binary_exists(binary_path)?;
+
+    // Execute the codesign command to get the signing information
+    let output = run_tool("codesign", &["-dv", binary_path], None)?;
     if !output.status.success() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to get identifier for binary: {}", binary_path),
-        )));
+        return Err(DoraError::ToolFailed {
+            tool: "codesign",
+            binary: binary_path.to_string(),
+        });
     }
 
-    // Parse the output and extract the identifier
-    let output_str = String::from_utf8(output.stderr).expect("Failed to convert output to string");
-    let identifier = output_str
-        .lines()
-        .find(|line| line.starts_with("Identifier="))
-        .and_then(|line| line.split('=').nth(1))
-        .map(|s| s.trim())
-        .unwrap_or("Unknown");
+    // Parse the output and extract the identifier and the team identifier
+    let output_str = output_text(output.stderr);
+    let codesign_value = |key: &str| {
+        output_str
+            .lines()
+            .find(|line| line.starts_with(key))
+            .and_then(|line| line.split('=').nth(1))
+            .map(|s| s.trim())
+    };
 
-    Ok(identifier.to_string())
+    Ok(SigningInfo {
+        identifier: codesign_value("Identifier=")
+            .unwrap_or("Unknown")
+            .to_string(),
+        team_id: codesign_value("TeamIdentifier=")
+            .unwrap_or("Unknown")
+            .to_string(),
+        flags: parse_codesign_flags(&output_str),
+        platform_identifier: codesign_value("Platform identifier=")
+            .and_then(|value| value.parse().ok()),
+    })
+}
+
+// Parse the flag names of the CodeDirectory line of "codesign -dv", e.g.
+// "CodeDirectory v=20500 size=1234 flags=0x12000(library-validation,runtime) hashes=..."
+fn parse_codesign_flags(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.starts_with("CodeDirectory "))
+        .flat_map(|line| line.split_whitespace())
+        .find_map(|field| field.strip_prefix("flags="))
+        .and_then(|flags| flags.split_once('('))
+        .map(|(_, names)| {
+            names
+                .trim_end_matches(')')
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 // Get macho binary entitlements launching "codesign" command
-pub fn get_macho_entitlements(binary_path: &str) -> Result<JsonValue, Box<dyn std::error::Error>> {
-    // Execute the following command to get JSON formatted entitlements from a Mach-O binary
-    // "codesign --display --entitlements :- <binary_path> | plutil -convert json -o - -"
-    let codesign_output = std::process::Command::new("codesign")
-        .args(["-d", "--entitlements", ":-", binary_path])
-        .output()
-        .expect("Failed to execute codesign");
+pub fn get_macho_entitlements(binary_path: &str) -> Result<JsonValue, DoraError> {
+    cached(&ENTITLEMENTS_CACHE, binary_path, extract_macho_entitlements)
+}
 
-    if !codesign_output.status.success() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to get entitlements for binary: {}", binary_path),
-        )));
-    }
+fn extract_macho_entitlements(binary_path: &str) -> Result<JsonValue, DoraError> {
+    ensure_binary_exists(binary_path)?;
 
-    // Check if the output is empty
-    if codesign_output.stdout.is_empty() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("No entitlements found for binary: {}", binary_path),
-        )));
+    let tool = ENTITLEMENTS_TOOL
+        .get()
+        .copied()
+        .unwrap_or(EntitlementsTool::Codesign);
+    let plist = match tool {
+        EntitlementsTool::Ldid => extract_ldid_entitlements(binary_path)?,
+        EntitlementsTool::Codesign => match extract_codesign_entitlements(binary_path) {
+            // codesign may print nothing for binaries signed by other tools
+            Err(DoraError::NoEntitlements(_)) if is_tool_available("ldid") => {
+                extract_ldid_entitlements(binary_path)?
+            }
+            result => result?,
+        },
+    };
+
+    // Both tools print an XML plist: "... | plutil -convert json -o - -"
+    let output = run_tool(
+        "plutil",
+        &["-convert", "json", "-o", "-", "-"],
+        Some(&plist),
+    )?;
+
+    if !output.status.success() {
+        return Err(DoraError::ToolFailed {
+            tool: "plutil",
+            binary: binary_path.to_string(),
+        });
     }
 
-    let mut plutil = std::process::Command::new("plutil")
-        .args(["-convert", "json", "-o", "-", "-"])
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .expect("Failed to execute plutil");
+    let entitlements_json: JsonValue = serde_json::from_slice(&output.stdout)?;
+
+    Ok(entitlements_json)
+}
+
+// Get the entitlements plist of a binary with "ldid -e <binary_path>".
+// Universal binaries get a plist per slice: the first one is kept.
+fn extract_ldid_entitlements(binary_path: &str) -> Result<Vec<u8>, DoraError> {
+    let output = run_tool("ldid", &["-e", binary_path], None)?;
+
+    if !output.status.success() {
+        return Err(DoraError::ToolFailed {
+            tool: "ldid",
+            binary: binary_path.to_string(),
+        });
+    }
 
+    const PLIST_END: &[u8] = b"</plist>";
+    let mut plist = output.stdout;
+    if let Some(end) = plist
+        .windows(PLIST_END.len())
+        .position(|window| window == PLIST_END)
     {
-        use std::io::Write;
-        let stdin = plutil.stdin.as_mut().expect("Failed to open plutil stdin");
-        stdin
-            .write_all(&codesign_output.stdout)
-            .expect("Failed to write to plutil");
+        plist.truncate(end + PLIST_END.len());
     }
 
-    let output = plutil
-        .wait_with_output()
-        .expect("Failed to read plutil output");
+    if plist.trim_ascii().is_empty() {
+        return Err(DoraError::NoEntitlements(binary_path.to_string()));
+    }
 
-    if !output.status.success() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-                "Failed to convert entitlements to JSON for binary: {}",
-                binary_path
-            ),
-        )));
+    Ok(plist)
+}
+
+// Get the entitlements plist of a binary with
+// "codesign --display --entitlements :- <binary_path>"
+fn extract_codesign_entitlements(binary_path: &str) -> Result<Vec<u8>, DoraError> {
+    let codesign_output = run_tool(
+        "codesign",
+        &["-d", "--entitlements", ":-", binary_path],
+        None,
+    )?;
+
+    if !codesign_output.status.success() {
+        return Err(DoraError::ToolFailed {
+            tool: "codesign",
+            binary: binary_path.to_string(),
+        });
     }
 
-    let entitlements_json: JsonValue =
-        serde_json::from_slice(&output.stdout).expect("Failed to parse entitlements JSON");
+    // Check if the output is empty
+    if codesign_output.stdout.is_empty() {
+        return Err(DoraError::NoEntitlements(binary_path.to_string()));
+    }
 
-    Ok(entitlements_json)
+    Ok(codesign_output.stdout)
 }
 
 // Function that extracts external dependencies from a Mach-O binary
 // launching "otool -L <binary_path>" command
-pub fn get_macho_external_dependencies(
-    binary_path: &str,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub fn get_macho_external_dependencies(binary_path: &str) -> Result<Vec<String>, DoraError> {
+    cached(
+        &DEPENDENCIES_CACHE,
+        binary_path,
+        extract_macho_external_dependencies,
+    )
+}
+
+fn extract_macho_external_dependencies(binary_path: &str) -> Result<Vec<String>, DoraError> {
+    ensure_binary_exists(binary_path)?;
+
     // Execute the otool command to get external dependencies
-    let output = std::process::Command::new("otool")
-        .args(["-L", binary_path])
-        .output()
-        .expect("Failed to execute otool");
+    let output = run_tool("otool", &["-L", binary_path], None)?;
 
     if !output.status.success() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-                "Failed to get external dependencies for binary: {}",
-                binary_path
-            ),
-        )));
+        return Err(DoraError::ToolFailed {
+            tool: "otool",
+            binary: binary_path.to_string(),
+        });
     }
 
     // Parse the output and extract the dependencies
-    let dependencies: Vec<String> = String::from_utf8(output.stdout)
-        .expect("Failed to parse otool output")
-        .lines()
-        .skip(1) // Skip the first line which is the binary name
-        .map(|line| line.split_whitespace().next().unwrap_or("").to_string())
-        .filter(|dep| !dep.is_empty())
-        .collect();
+    let output_str = output_text(output.stdout);
+
+    Ok(parse_otool_dependencies(&output_str, binary_path))
+}
+
+// Parse "otool -L" output, e.g. for a universal binary:
+//
+//   /usr/bin/foo (architecture x86_64):
+//   	/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+//   /usr/bin/foo (architecture arm64e):
+//   	/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+//
+// Only the indented "<path> (compatibility version ...)" lines are dependencies:
+// file and per-architecture headers are skipped, libraries listed for several
+// architectures are kept once and a dylib's reference to itself is dropped.
+fn parse_otool_dependencies(output: &str, binary_path: &str) -> Vec<String> {
+    let mut dependencies: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let Some((path, _)) = line.trim().split_once(" (compatibility version ") else {
+            continue;
+        };
+
+        if path != binary_path && !dependencies.iter().any(|dep| dep == path) {
+            dependencies.push(path.to_string());
+        }
+    }
 
-    Ok(dependencies)
+    dependencies
+}
+
+// Deployment target of a Mach-O binary: the minimum OS version it supports and
+// the SDK it was built with. Both are None for binaries with neither a
+// LC_BUILD_VERSION nor a LC_VERSION_MIN_MACOSX load command.
+// "restrict_segment" comes from the same load commands: whether the binary
+// has a "__RESTRICT" segment.
+#[derive(Debug, Clone, Default)]
+pub struct BuildVersion {
+    pub min_os: Option<String>,
+    pub sdk: Option<String>,
+    pub restrict_segment: bool,
+}
+
+// Get the deployment target of a Mach-O binary
+// launching "otool -l <binary_path>" command
+pub fn get_macho_build_version(binary_path: &str) -> Result<BuildVersion, DoraError> {
+    cached(
+        &BUILD_VERSION_CACHE,
+        binary_path,
+        extract_macho_build_version,
+    )
+}
+
+fn extract_macho_build_version(binary_path: &str) -> Result<BuildVersion, DoraError> {
+    ensure_binary_exists(binary_path)?;
+
+    let output = run_tool("otool", &["-l", binary_path], None)?;
+
+    if !output.status.success() {
+        return Err(DoraError::ToolFailed {
+            tool: "otool",
+            binary: binary_path.to_string(),
+        });
+    }
+
+    let output_str = output_text(output.stdout);
+
+    Ok(BuildVersion {
+        restrict_segment: has_restrict_segment(&output_str),
+        ..parse_otool_build_version(&output_str)
+    })
+}
+
+// Whether "otool -l" output lists a "__RESTRICT" segment:
+//
+//         cmd LC_SEGMENT_64
+//     cmdsize 152
+//     segname __RESTRICT
+fn has_restrict_segment(output: &str) -> bool {
+    output.lines().any(|line| {
+        line.trim()
+            .strip_prefix("segname")
+            .is_some_and(|segname| segname.trim() == "__RESTRICT")
+    })
+}
+
+// Whether dyld ignores the DYLD_* environment variables of a binary, e.g.
+// DYLD_INSERT_LIBRARIES: it has a "__RESTRICT" segment or its signature has
+// the "restrict" flag. None when neither is known to apply but its load
+// commands couldn't be read.
+pub fn is_restricted(
+    restrict_segment: Option<bool>,
+    codesign_flags: Option<&[String]>,
+) -> Option<bool> {
+    let restrict_flag =
+        codesign_flags.is_some_and(|flags| flags.iter().any(|flag| flag == "restrict"));
+    match restrict_segment {
+        Some(restrict_segment) => Some(restrict_segment || restrict_flag),
+        None => restrict_flag.then_some(true),
+    }
+}
+
+// Parse the first version load command of "otool -l" output, either
+//
+//         cmd LC_BUILD_VERSION          or          cmd LC_VERSION_MIN_MACOSX
+//     cmdsize 32                                cmdsize 16
+//    platform 1                                 version 10.13
+//       minos 14.0                                  sdk 10.14
+//         sdk 14.2
+//
+// An SDK reported as "n/a" is unknown.
+fn parse_otool_build_version(output: &str) -> BuildVersion {
+    let mut version = BuildVersion::default();
+    let mut min_os_key = None;
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(char::is_whitespace) else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key {
+            "cmd" if version.min_os.is_some() => break,
+            "cmd" => {
+                min_os_key = match value {
+                    "LC_BUILD_VERSION" => Some("minos"),
+                    "LC_VERSION_MIN_MACOSX" => Some("version"),
+                    _ => None,
+                };
+            }
+            "sdk" if min_os_key.is_some() && value != "n/a" => {
+                version.sdk = Some(value.to_string())
+            }
+            _ if Some(key) == min_os_key => version.min_os = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    version
 }
 
 // Function that extracts binary imported symbols
 // launching "nm -u <binary_path>" command
-pub fn get_macho_imported_symbols(
-    binary_path: &str,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub fn get_macho_imported_symbols(binary_path: &str) -> Result<Vec<String>, DoraError> {
+    cached(&SYMBOLS_CACHE, binary_path, extract_macho_imported_symbols)
+}
+
+fn extract_macho_imported_symbols(binary_path: &str) -> Result<Vec<String>, DoraError> {
+    ensure_binary_exists(binary_path)?;
+
     // Execute the nm command to get imported symbols
-    let output = std::process::Command::new("nm")
-        .args(["-u", "--arch=arm64e", binary_path])
-        .output()
-        .expect("Failed to execute nm");
+    let output = run_tool("nm", &["-u", "--arch=arm64e", binary_path], None)?;
 
     if !output.status.success() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to get imported symbols for binary: {}", binary_path),
-        )));
+        return Err(DoraError::ToolFailed {
+            tool: "nm",
+            binary: binary_path.to_string(),
+        });
     }
 
     // Parse the output and extract the symbols
-    let symbols: Vec<String> = String::from_utf8(output.stdout)
-        .expect("Failed to parse nm output")
+    let symbols: Vec<String> = output_text(output.stdout)
         .lines()
         .map(|line| line.trim().to_string())
         .filter(|s| !s.is_empty())
@@ -151,6 +662,386 @@ pub fn get_macho_imported_symbols(
     Ok(symbols)
 }
 
+// Demangle symbol names as listed by nm, None for those that aren't mangled
+// or couldn't be demangled. Rust and C++ names are demangled in-process;
+// Swift ones go through "swift-demangle" in a single run, when it is
+// installed (xcrun ships it with Xcode).
+pub fn demangle_symbols(symbols: &[String]) -> Vec<Option<String>> {
+    // Mach-O symbols carry an extra leading underscore
+    let unprefixed = |symbol: &str| symbol.strip_prefix('_').unwrap_or(symbol).to_string();
+    let is_swift = |symbol: &str| {
+        let name = unprefixed(symbol);
+        ["$s", "$S", "$e", "_T0"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+    };
+
+    let mut demangled: Vec<Option<String>> = symbols
+        .iter()
+        .map(|symbol| {
+            let name = unprefixed(symbol);
+            if let Ok(rust) = rustc_demangle::try_demangle(&name) {
+                return Some(format!("{:#}", rust));
+            }
+            cpp_demangle::Symbol::new(name.as_bytes())
+                .ok()
+                .and_then(|cpp| cpp.demangle().ok())
+        })
+        .collect();
+
+    let swift: Vec<usize> = (0..symbols.len())
+        .filter(|&i| demangled[i].is_none() && is_swift(&symbols[i]))
+        .collect();
+    if swift.is_empty() || !is_tool_available("swift-demangle") {
+        return demangled;
+    }
+
+    // One name per line in, one per line out
+    let input: String = swift
+        .iter()
+        .map(|&i| format!("{}\n", unprefixed(&symbols[i])))
+        .collect();
+    match run_tool("swift-demangle", &["--compact"], Some(input.as_bytes())) {
+        Ok(output) if output.status.success() => {
+            for (&i, line) in swift.iter().zip(output_text(output.stdout).lines()) {
+                let line = line.trim();
+                if !line.is_empty() && line != unprefixed(&symbols[i]) {
+                    demangled[i] = Some(line.to_string());
+                }
+            }
+        }
+        Ok(_) => eprintln!("swift-demangle failed to demangle {} symbols", swift.len()),
+        Err(e) => eprintln!("Failed to run swift-demangle: {}", e),
+    }
+
+    demangled
+}
+
+// Run every extraction on a single binary and gather the results in a JSON object.
+// Failed extractions are reported on stderr and left as null.
+pub fn analyze_binary(binary_path: &str) -> JsonValue {
+    let or_null = |what: &str, result: Result<JsonValue, DoraError>| {
+        result.unwrap_or_else(|e| {
+            eprintln!("Failed to get {} for binary {}: {}", what, binary_path, e);
+            JsonValue::Null
+        })
+    };
+
+    let (identifier, team_id, flags, platform_identifier) =
+        match get_macho_signing_info(binary_path) {
+            Ok(info) => (
+                JsonValue::from(info.identifier),
+                JsonValue::from(info.team_id),
+                JsonValue::from(info.flags),
+                JsonValue::from(info.platform_identifier),
+            ),
+            Err(e) => {
+                eprintln!("Failed to get identifier for binary {}: {}", binary_path, e);
+                (
+                    JsonValue::Null,
+                    JsonValue::Null,
+                    JsonValue::Null,
+                    JsonValue::Null,
+                )
+            }
+        };
+
+    let (min_os, sdk, restrict_segment) = match get_macho_build_version(binary_path) {
+        Ok(version) => (
+            JsonValue::from(version.min_os),
+            JsonValue::from(version.sdk),
+            Some(version.restrict_segment),
+        ),
+        Err(e) => {
+            eprintln!(
+                "Failed to get build version for binary {}: {}",
+                binary_path, e
+            );
+            (JsonValue::Null, JsonValue::Null, None)
+        }
+    };
+    let codesign_flags: Option<Vec<String>> = flags.as_array().map(|flags| {
+        flags
+            .iter()
+            .filter_map(|flag| flag.as_str().map(String::from))
+            .collect()
+    });
+    let restricted = is_restricted(restrict_segment, codesign_flags.as_deref());
+
+    let (arch, thirty_two_bit_only) = match get_macho_archs(binary_path) {
+        Ok(archs) => (
+            JsonValue::from(
+                archs
+                    .iter()
+                    .map(|arch| arch.name.clone())
+                    .collect::<Vec<String>>(),
+            ),
+            JsonValue::from(is_32bit_only(&archs)),
+        ),
+        Err(e) => {
+            eprintln!(
+                "Failed to get architectures for binary {}: {}",
+                binary_path, e
+            );
+            (JsonValue::Null, JsonValue::Null)
+        }
+    };
+
+    serde_json::json!({
+        "binary": binary_path,
+        "identifier": identifier,
+        "team_id": team_id,
+        "codesign_flags": flags,
+        "platform_identifier": platform_identifier,
+        "min_os": min_os,
+        "sdk": sdk,
+        "arch": arch,
+        "is_32bit_only": thirty_two_bit_only,
+        "is_restricted": restricted,
+        "entitlements": match get_macho_entitlements(binary_path) {
+            Err(DoraError::NoEntitlements(_)) => JsonValue::Object(Default::default()),
+            result => or_null("entitlements", result),
+        },
+        "libraries": or_null(
+            "external dependencies",
+            get_macho_external_dependencies(binary_path).map(JsonValue::from),
+        ),
+        "symbols": or_null(
+            "imported symbols",
+            get_macho_imported_symbols(binary_path).map(JsonValue::from),
+        ),
+    })
+}
+
+// Mach-O magic numbers (<mach-o/loader.h> and <mach-o/fat.h>).
+// The "CIGAM" variants are the byte-swapped magics of files whose endianness
+// differs from the reader's.
+const MH_MAGIC: u32 = 0xFEEDFACE; // 32-bit
+const MH_CIGAM: u32 = 0xCEFAEDFE; // 32-bit, byte-swapped
+const MH_MAGIC_64: u32 = 0xFEEDFACF; // 64-bit
+const MH_CIGAM_64: u32 = 0xCFFAEDFE; // 64-bit, byte-swapped
+const FAT_MAGIC: u32 = 0xCAFEBABE; // universal binary
+const FAT_MAGIC_64: u32 = 0xCAFEBABF; // universal binary with 64-bit offsets
+
+// CPU types and subtypes of the Mach-O headers
+const CPU_ARCH_ABI64: u32 = 0x0100_0000;
+const CPU_ARCH_ABI64_32: u32 = 0x0200_0000;
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_ARM: u32 = 12;
+const CPU_TYPE_POWERPC: u32 = 18;
+const CPU_SUBTYPE_MASK: u32 = 0x00FF_FFFF; // drops the capability bits
+const CPU_SUBTYPE_X86_64_H: u32 = 8;
+const CPU_SUBTYPE_ARM64E: u32 = 2;
+
+// Size of the start of a binary read to list its architectures: room for the
+// universal header of any real binary
+const ARCH_HEADER_LENGTH: u64 = 4096;
+
+// Whether the first 4 bytes of a file are one of the Mach-O magic numbers,
+// the same ones "parse_macho_archs" reads. Universal headers are always
+// big-endian on disk: there is no byte-swapped variant to accept.
+fn is_macho_magic(bytes: [u8; 4]) -> bool {
+    matches!(
+        u32::from_be_bytes(bytes),
+        MH_MAGIC | MH_CIGAM | MH_MAGIC_64 | MH_CIGAM_64 | FAT_MAGIC | FAT_MAGIC_64
+    )
+}
+
+// An architecture slice of a Mach-O binary
+pub struct MachoArch {
+    // As "lipo -archs" prints it, e.g. "arm64e"
+    pub name: String,
+    // Whether the CPU type has the 64-bit ABI bit (arm64_32 doesn't)
+    pub is_64bit: bool,
+}
+
+impl MachoArch {
+    fn new(cpu_type: u32, cpu_subtype: u32) -> Self {
+        MachoArch {
+            name: arch_name(cpu_type, cpu_subtype),
+            is_64bit: cpu_type & CPU_ARCH_ABI64 != 0,
+        }
+    }
+}
+
+// Whether a binary lacks any 64-bit slice: macOS dropped 32-bit support, so
+// it can't run on a current system
+pub fn is_32bit_only(archs: &[MachoArch]) -> bool {
+    !archs.is_empty() && !archs.iter().any(|arch| arch.is_64bit)
+}
+
+// Name of an architecture, as "lipo -archs" prints it
+fn arch_name(cpu_type: u32, cpu_subtype: u32) -> String {
+    let subtype = cpu_subtype & CPU_SUBTYPE_MASK;
+    let name = match cpu_type {
+        CPU_TYPE_X86 => "i386",
+        t if t == CPU_TYPE_X86 | CPU_ARCH_ABI64 && subtype == CPU_SUBTYPE_X86_64_H => "x86_64h",
+        t if t == CPU_TYPE_X86 | CPU_ARCH_ABI64 => "x86_64",
+        CPU_TYPE_ARM => match subtype {
+            6 => "armv6",
+            9 => "armv7",
+            11 => "armv7s",
+            12 => "armv7k",
+            _ => "arm",
+        },
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64 && subtype == CPU_SUBTYPE_ARM64E => "arm64e",
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64 => "arm64",
+        t if t == CPU_TYPE_ARM | CPU_ARCH_ABI64_32 => "arm64_32",
+        CPU_TYPE_POWERPC => "ppc",
+        t if t == CPU_TYPE_POWERPC | CPU_ARCH_ABI64 => "ppc64",
+        _ => return format!("unknown({cpu_type},{cpu_subtype})"),
+    };
+    name.to_string()
+}
+
+// Parse the architectures out of the start of a Mach-O binary: the slices of
+// a universal binary (big-endian "fat" header), or the CPU type of a thin one
+fn parse_macho_archs(header: &[u8]) -> Option<Vec<MachoArch>> {
+    let word = |offset: usize, big_endian: bool| -> Option<u32> {
+        let bytes: [u8; 4] = header.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    match word(0, true)? {
+        magic @ (FAT_MAGIC | FAT_MAGIC_64) => {
+            // fat_arch entries are 20 bytes long, fat_arch_64 ones 32
+            let entry_length = if magic == FAT_MAGIC { 20 } else { 32 };
+            let count = word(4, true)? as usize;
+            (0..count)
+                .map(|i| {
+                    let entry = 8 + i * entry_length;
+                    Some(MachoArch::new(word(entry, true)?, word(entry + 4, true)?))
+                })
+                .collect()
+        }
+        MH_MAGIC | MH_MAGIC_64 => Some(vec![MachoArch::new(word(4, true)?, word(8, true)?)]),
+        MH_CIGAM | MH_CIGAM_64 => Some(vec![MachoArch::new(word(4, false)?, word(8, false)?)]),
+        _ => None,
+    }
+}
+
+// Get the architectures of a Mach-O binary (e.g. x86_64 and arm64e) from its
+// header, without any external tool
+pub fn get_macho_archs(binary_path: &str) -> Result<Vec<MachoArch>, DoraError> {
+    let mut header = Vec::new();
+    File::open(binary_path)?
+        .take(ARCH_HEADER_LENGTH)
+        .read_to_end(&mut header)?;
+
+    parse_macho_archs(&header).ok_or_else(|| DoraError::InvalidMachO(binary_path.to_string()))
+}
+
+// Read the install names of the images of a dyld shared cache file, from the
+// image list its header points to
+fn read_shared_cache_images(path: &Path) -> std::io::Result<Vec<String>> {
+    use std::io::{Seek, SeekFrom};
+
+    let invalid = || std::io::Error::new(ErrorKind::InvalidData, "not a dyld shared cache");
+    let mut file = File::open(path)?;
+    let mut header = Vec::new();
+    (&mut file).take(0x200).read_to_end(&mut header)?;
+    if !header.starts_with(b"dyld_v1") {
+        return Err(invalid());
+    }
+
+    let word = |offset: usize| -> std::io::Result<u32> {
+        let bytes: [u8; 4] = header
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        Ok(u32::from_le_bytes(bytes))
+    };
+
+    // Caches since macOS 12 moved the image list further down the header,
+    // which is known to be that long when the mappings start after it
+    let mapping_offset = word(0x10)?;
+    let (images_offset, images_count) = match (word(0x18)?, word(0x1c)?) {
+        (0, 0) if mapping_offset > 0x1c4 => (word(0x1c0)?, word(0x1c4)?),
+        old => old,
+    };
+
+    // dyld_cache_image_info entries are 32 bytes long, the path offset at 24.
+    // A truncated or corrupt cache can't list more images than it holds.
+    let images_len = u64::from(images_count)
+        .checked_mul(32)
+        .ok_or_else(invalid)?;
+    let images_end = u64::from(images_offset)
+        .checked_add(images_len)
+        .ok_or_else(invalid)?;
+    if images_end > file.metadata()?.len() {
+        return Err(invalid());
+    }
+
+    let mut images = vec![0; images_len as usize];
+    file.seek(SeekFrom::Start(images_offset as u64))?;
+    file.read_exact(&mut images)?;
+
+    let mut paths = Vec::with_capacity(images_count as usize);
+    let mut reader = std::io::BufReader::new(file);
+    for image in images.chunks_exact(32) {
+        let path_offset = u32::from_le_bytes(image[24..28].try_into().unwrap_or_default());
+        reader.seek(SeekFrom::Start(path_offset as u64))?;
+        let mut path = Vec::new();
+        std::io::BufRead::read_until(&mut reader, 0, &mut path)?;
+        path.pop_if(|byte| *byte == 0);
+        paths.push(String::from_utf8_lossy(&path).into_owned());
+    }
+
+    Ok(paths)
+}
+
+// Install names of the images of the dyld shared caches of the scanned
+// system, for every architecture, or None when no cache could be read.
+// A process only ever scans one system: the first "root" asked for is kept.
+static SHARED_CACHE_IMAGES: OnceLock<Option<HashSet<String>>> = OnceLock::new();
+
+// Whether a library is one of the images of the dyld shared cache, None when
+// the cache couldn't be read
+pub fn shared_cache_contains(root: Option<&Path>, library: &str) -> Option<bool> {
+    let images = SHARED_CACHE_IMAGES.get_or_init(|| {
+        let mut images = HashSet::new();
+        let mut found = false;
+        for folder in SHARED_CACHE_FOLDERS {
+            let folder = match root {
+                Some(root) => root.join(folder.trim_start_matches('/')),
+                None => PathBuf::from(folder),
+            };
+            let Ok(entries) = std::fs::read_dir(&folder) else {
+                continue;
+            };
+
+            // Main cache files only: subcaches (".01", ...) and maps have an extension
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                let is_main_cache = path.extension().is_none()
+                    && path.file_name().is_some_and(|name| {
+                        name.to_string_lossy().starts_with("dyld_shared_cache_")
+                    });
+                if !is_main_cache {
+                    continue;
+                }
+                match read_shared_cache_images(&path) {
+                    Ok(paths) => {
+                        found = true;
+                        images.extend(paths);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to read dyld shared cache {}: {}", path.display(), e)
+                    }
+                }
+            }
+        }
+
+        found.then_some(images)
+    });
+
+    images.as_ref().map(|images| images.contains(library))
+}
+
 pub trait FileType {
     fn is_macho(&self) -> bool;
 }
@@ -162,16 +1053,182 @@ impl FileType for PathBuf {
             Err(_) => return false,
         };
 
+        // Files too short to hold a magic number can't be Mach-O
+        match file.metadata() {
+            Ok(metadata) if metadata.len() >= 4 => {}
+            _ => return false,
+        }
+
         let mut buffer = [0; 4];
-        if let Ok(_) = file.take(4).read_exact(&mut buffer) {
-            // Check for Mach-O magic numbers
-            buffer == [0xFE, 0xED, 0xFA, 0xCE] || // Mach-O 32-bit
-            buffer == [0xCF, 0xFA, 0xED, 0xFE] || // Mach-O 32-bit (big-endian)
-            buffer == [0xFE, 0xED, 0xFA, 0xCF] || // Mach-O 64-bit
-            buffer == [0xCF, 0xFA, 0xED, 0xFE] || // Mach-O 64-bit (big-endian)
-            buffer == [0xCA, 0xFE, 0xBA, 0xBE] // Mach-O universal binary
-        } else {
-            false
+        match file.take(4).read_exact(&mut buffer) {
+            Ok(()) => is_macho_magic(buffer),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", self.display(), e);
+                false
+            }
+        }
+    }
+}
+
+// Get the interpreter of a script from its shebang line ("#!/bin/sh" gives
+// "/bin/sh"), or None when the file doesn't start with one
+pub fn get_script_interpreter(path: &Path) -> Option<String> {
+    let mut head = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SHEBANG_MAX_LENGTH)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    let line = head.strip_prefix(b"#!")?.split(|&b| b == b'\n').next()?;
+    let interpreter = String::from_utf8_lossy(line).trim().to_string();
+    (!interpreter.is_empty()).then_some(interpreter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_macho_magic_accepts_each_magic() {
+        for magic in [
+            MH_MAGIC,
+            MH_CIGAM,
+            MH_MAGIC_64,
+            MH_CIGAM_64,
+            FAT_MAGIC,
+            FAT_MAGIC_64,
+        ] {
+            assert!(is_macho_magic(magic.to_be_bytes()), "{magic:#X}");
+        }
+    }
+
+    #[test]
+    fn is_macho_magic_rejects_other_files() {
+        assert!(!is_macho_magic(*b"#!/b"));
+        assert!(!is_macho_magic([0x7F, b'E', b'L', b'F']));
+        assert!(!is_macho_magic([0xBE, 0xBA, 0xFE, 0xCA]));
+        assert!(!is_macho_magic([0; 4]));
+    }
+
+    #[test]
+    fn is_macho_magic_agrees_with_parse_macho_archs() {
+        // A thin arm64 header and a universal one with a single x86_64 slice,
+        // for each magic
+        let thin = |magic: u32, big_endian: bool| {
+            let word = |value: u32| {
+                if big_endian {
+                    value.to_be_bytes()
+                } else {
+                    value.to_le_bytes()
+                }
+            };
+            let mut header = magic.to_be_bytes().to_vec();
+            header.extend(word(CPU_TYPE_ARM | CPU_ARCH_ABI64));
+            header.extend(word(0));
+            header
+        };
+        let fat = |magic: u32, entry_length: usize| {
+            let mut header = magic.to_be_bytes().to_vec();
+            header.extend(1u32.to_be_bytes());
+            header.extend((CPU_TYPE_X86 | CPU_ARCH_ABI64).to_be_bytes());
+            header.extend(3u32.to_be_bytes());
+            header.resize(8 + entry_length, 0);
+            header
+        };
+
+        for header in [
+            thin(MH_MAGIC, true),
+            thin(MH_MAGIC_64, true),
+            thin(MH_CIGAM, false),
+            thin(MH_CIGAM_64, false),
+            fat(FAT_MAGIC, 20),
+            fat(FAT_MAGIC_64, 32),
+        ] {
+            assert!(is_macho_magic(header[..4].try_into().unwrap()));
+            assert!(parse_macho_archs(&header).is_some());
         }
     }
+
+    #[test]
+    fn parse_otool_dependencies_of_universal_binary() {
+        let output = "\
+/usr/bin/foo (architecture x86_64):
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+\t/System/Library/Frameworks/Foundation.framework/Versions/C/Foundation (compatibility version 300.0.0, current version 2420.0.0)
+/usr/bin/foo (architecture arm64e):
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+\t/usr/lib/libobjc.A.dylib (compatibility version 1.0.0, current version 228.0.0)
+";
+
+        assert_eq!(
+            parse_otool_dependencies(output, "/usr/bin/foo"),
+            [
+                "/usr/lib/libSystem.B.dylib",
+                "/System/Library/Frameworks/Foundation.framework/Versions/C/Foundation",
+                "/usr/lib/libobjc.A.dylib",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_otool_dependencies_skips_own_path() {
+        // An executable's output starts with its path, a dylib's also lists
+        // its own install name
+        let executable = "\
+/usr/libexec/bar:
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+";
+        assert_eq!(
+            parse_otool_dependencies(executable, "/usr/libexec/bar"),
+            ["/usr/lib/libSystem.B.dylib"]
+        );
+
+        let dylib = "\
+/usr/lib/libbaz.dylib:
+\t/usr/lib/libbaz.dylib (compatibility version 1.0.0, current version 1.0.0)
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+";
+        assert_eq!(
+            parse_otool_dependencies(dylib, "/usr/lib/libbaz.dylib"),
+            ["/usr/lib/libSystem.B.dylib"]
+        );
+    }
+
+    #[test]
+    fn output_text_keeps_lines_around_invalid_utf8() {
+        let mut bytes = b"/usr/bin/foo:\n\t/usr/lib/lib".to_vec();
+        bytes.push(0xff);
+        bytes.extend_from_slice(
+            b"bad.dylib (compatibility version 1.0.0, current version 1.0.0)\n\
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)\n",
+        );
+
+        assert_eq!(
+            parse_otool_dependencies(&output_text(bytes), "/usr/bin/foo"),
+            [
+                "/usr/lib/lib\u{fffd}bad.dylib",
+                "/usr/lib/libSystem.B.dylib"
+            ]
+        );
+    }
+
+    #[test]
+    fn read_shared_cache_images_rejects_images_past_end_of_file() {
+        // A header listing 0xffffffff images, right after it, in a file of
+        // only the header
+        let mut header = vec![0; 0x200];
+        header[..7].copy_from_slice(b"dyld_v1");
+        header[0x10..0x14].copy_from_slice(&0x200u32.to_le_bytes());
+        header[0x18..0x1c].copy_from_slice(&0x200u32.to_le_bytes());
+        header[0x1c..0x20].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let path =
+            std::env::temp_dir().join(format!("dora_truncated_cache_{}", std::process::id()));
+        std::fs::write(&path, &header).unwrap();
+        let result = read_shared_cache_images(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
 }
@@ -1,19 +1,438 @@
 use axum::{
-    Router,
+    Router, middleware,
     routing::{get, post},
 };
+use clap::{Parser, Subcommand};
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+use tower_http::compression::CompressionLayer;
 
 mod consts;
+mod error;
 mod macho;
 mod sqlite;
 mod utils;
 mod web;
 
-use crate::sqlite::populate_db;
-use crate::utils::generate_sqlite_filename;
-use consts::{LISTENING_ADDRESS, LISTENING_PORT};
+use crate::macho::{
+    EntitlementsTool, analyze_binary, missing_tools, set_entitlements_tool, set_tool_timeout,
+};
+use crate::sqlite::{
+    Collector, ScanOptions, ScanSummary, export_jsonl, migrate_database, populate_db,
+    populate_sample_db, refresh_service,
+};
+use crate::utils::{
+    generate_sqlite_filename, get_available_databases, get_os_version, info, is_valid_db,
+    load_context_map, load_dangerous_symbols, load_entitlement_symbols, read_os_version,
+    set_access_token, set_context_map, set_dangerous_symbols, set_entitlement_symbols,
+    set_no_style, set_quiet, set_served_database,
+};
+use consts::{EXIT_FATAL, EXIT_PARTIAL, LISTENING_ADDRESS, LISTENING_PORT, TOOL_TIMEOUT_SECS};
 use web::*;
 
+// Command line arguments
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Maximum directory depth to recurse into when scanning folders of binaries
+    #[arg(long, global = true)]
+    max_depth: Option<usize>,
+
+    /// Only scan the first N binaries found in each folder of binaries, for a
+    /// quick sample database
+    #[arg(long, value_name = "N", global = true)]
+    limit_per_dir: Option<usize>,
+
+    /// Don't draw a progress bar while scanning, log progress periodically instead
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// What to extract from plists and binaries, comma-separated (e.g.
+    /// "entitlements,machservices"); everything by default
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "COLLECTORS",
+        global = true
+    )]
+    collect: Option<Vec<Collector>>,
+
+    /// Don't extract imported symbols with nm, for much faster scans
+    #[arg(long, global = true)]
+    no_symbols: bool,
+
+    /// Don't extract library dependencies nor the minimum OS and SDK versions with otool
+    #[arg(long, global = true)]
+    no_deps: bool,
+
+    /// Also scan third-party software: the jobs of /Library/LaunchAgents and
+    /// /Library/LaunchDaemons, and the binaries below /usr/local
+    #[arg(long, global = true)]
+    third_party: bool,
+
+    /// Kill an external tool (codesign, otool, nm, plutil) still running after
+    /// this many seconds and skip what it was extracting
+    #[arg(long, value_name = "SECS", global = true, default_value_t = TOOL_TIMEOUT_SECS)]
+    tool_timeout: u64,
+
+    /// Tool extracting entitlements. codesign falls back to ldid, when
+    /// installed, for signed binaries it finds no entitlements in
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TOOL",
+        global = true,
+        default_value = "codesign"
+    )]
+    entitlements_tool: EntitlementsTool,
+
+    /// JSON file of {"entitlement": ["symbol pattern", ...]} extending the built-in
+    /// mapping used to flag over-privileged services
+    #[arg(long, value_name = "FILE", global = true)]
+    entitlement_symbols: Option<String>,
+
+    /// JSON array of symbol patterns (e.g. ["_task_for_pid", "_mach_vm_*"])
+    /// replacing the built-in list of dangerous symbols
+    #[arg(long, value_name = "FILE", global = true)]
+    dangerous_symbols: Option<String>,
+
+    /// JSON file of {"/folder": "root" | "user"} extending the built-in mapping of
+    /// launchd folders to the context their jobs run in
+    #[arg(long, value_name = "FILE", global = true)]
+    context_map: Option<String>,
+
+    /// Require this secret on every request to the web server, as an
+    /// "Authorization: Bearer" header or a "token" parameter
+    #[arg(long, value_name = "SECRET", global = true)]
+    token: Option<String>,
+
+    /// Serve bare HTML, without the stylesheet, e.g. for scraping
+    #[arg(long, global = true)]
+    no_style: bool,
+
+    /// Print how many times each external tool ran and the time it took at
+    /// the end of a scan
+    #[arg(long, global = true)]
+    profile: bool,
+
+    /// Don't print the banner nor any non-error message
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Analyze a single binary and print the results, without any database or server
+    Analyze {
+        /// Path of the Mach-O binary to analyze
+        binary: String,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Re-analyze the binary of a single service in an existing database
+    Refresh {
+        /// Database to update
+        #[arg(long)]
+        db: String,
+
+        /// Label of the service to refresh
+        #[arg(long)]
+        label: String,
+    },
+
+    /// Add the tables and indexes of the current schema to existing databases
+    Migrate {
+        /// Only migrate this database instead of every "dora_*.sqlite" of the current folder
+        #[arg(long)]
+        db: Option<String>,
+    },
+
+    /// Export every service of a database with its entitlements, libraries,
+    /// symbols, ... as JSON Lines (one JSON object per line and service)
+    Export {
+        /// Database to export
+        #[arg(long)]
+        db: String,
+
+        /// File to write the JSON Lines to
+        #[arg(long, value_name = "FILE")]
+        jsonl: PathBuf,
+    },
+
+    /// Start the web server on existing databases, without scanning
+    Serve {
+        /// Only serve this database (a "dora_*.sqlite" file of the current folder)
+        #[arg(long)]
+        db: Option<String>,
+    },
+
+    /// Build a database and exit, without starting the server
+    Scan {
+        /// Scan the system mounted at this folder (e.g. a backup or a forensic image)
+        /// instead of the host
+        #[arg(long, requires = "name")]
+        root: Option<PathBuf>,
+
+        /// Name the database "dora_<NAME>.sqlite" instead of after the host's OS version
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Print the scan summary as JSON (with --quiet, nothing else goes to stdout)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create a small database of fabricated services, e.g. to try the web
+    /// server away from macOS, without scanning anything
+    Sample {
+        /// Database to create; the server only lists "dora_*.sqlite" files
+        #[arg(long, value_name = "FILE", default_value = "dora_sample.sqlite")]
+        out: PathBuf,
+    },
+}
+
+// Build a database for "dora scan", refusing to touch an existing one.
+// Exits with 0 when the scan completed without errors, EXIT_PARTIAL when
+// errors were logged along the way and EXIT_FATAL when it couldn't run.
+fn scan(cli: &Cli, root: Option<&PathBuf>, name: Option<&str>, json: bool) {
+    let named_path = name.map(|name| format!("dora_{}.sqlite", name));
+    if let Some(path) = &named_path
+        && !is_valid_db(path)
+    {
+        eprintln!("Invalid database name: {}", path);
+        std::process::exit(EXIT_FATAL);
+    }
+
+    let mut tools = scan_tools(cli);
+    if root.is_none() {
+        tools.push("sw_vers");
+    }
+    check_tools(&tools);
+
+    let os_version = match root {
+        Some(root) => read_os_version(root),
+        None => get_os_version(),
+    };
+    let database_path = named_path.unwrap_or_else(|| generate_sqlite_filename(&os_version));
+
+    if Path::new(&database_path).exists() {
+        eprintln!("Database {} already exists", database_path);
+        std::process::exit(EXIT_FATAL);
+    }
+
+    info!("Creating SQLite database file: {}", database_path);
+    let summary = match populate_db(&database_path, &os_version, &scan_options(cli, root)) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Failed to populate the database {}: {}", database_path, e);
+            // Don't leave a half-built database behind: it would block the next scan
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{database_path}{suffix}"));
+            }
+            std::process::exit(EXIT_FATAL);
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&summary.to_json()).expect("Failed to serialize summary")
+        );
+    } else {
+        print_scan_summary(&summary, cli.profile);
+    }
+
+    if summary.is_partial() {
+        std::process::exit(EXIT_PARTIAL);
+    }
+}
+
+// Print the figures of a completed scan, and with "profile" the time spent
+// in each external tool
+fn print_scan_summary(summary: &ScanSummary, profile: bool) {
+    info!(
+        "Scan completed in {:.1}s: {} services, {} entitlements, {} libraries, {} symbols",
+        summary.elapsed.as_secs_f64(),
+        summary.services,
+        summary.entitlements,
+        summary.libraries,
+        summary.symbols
+    );
+    if summary.failed_files > 0 {
+        info!(
+            "\t{} files skipped or only partly processed, see the errors above",
+            summary.failed_files
+        );
+    }
+    for (tool, failures) in &summary.tool_failures {
+        info!("\t{} failed on {} binaries", tool, failures);
+    }
+    if profile {
+        print_tool_timings(summary);
+    }
+}
+
+// Print the time spent in each external tool, slowest first.
+// Printed even with "--quiet", as it was asked for explicitly.
+fn print_tool_timings(summary: &ScanSummary) {
+    let mut timings: Vec<_> = summary.tool_timings.iter().collect();
+    timings.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.total));
+
+    println!("Time spent in external tools (run concurrently, so it may exceed the scan time):");
+    for (tool, timing) in timings {
+        println!(
+            "\t{}: {} runs, {:.1}s total, {:.1}ms on average",
+            tool,
+            timing.runs,
+            timing.total.as_secs_f64(),
+            timing.average().as_secs_f64() * 1000.0
+        );
+    }
+}
+
+// External tools a scan runs, given what it skips
+fn scan_tools(cli: &Cli) -> Vec<&'static str> {
+    let options = scan_options(cli, None);
+    let mut tools = vec!["codesign", "plutil"];
+    if !options.skip_entitlements && cli.entitlements_tool == EntitlementsTool::Ldid {
+        tools.push("ldid");
+    }
+    if !options.skip_dependencies {
+        tools.push("otool");
+    }
+    if !options.skip_symbols {
+        tools.push("nm");
+    }
+    tools
+}
+
+// Exit before doing any work when one of "tools" can't be launched, rather
+// than failing on every binary
+fn check_tools(tools: &[&'static str]) {
+    let missing = missing_tools(tools);
+    if missing.is_empty() {
+        return;
+    }
+
+    eprintln!("Missing external tools: {}", missing.join(", "));
+    eprintln!(
+        "dora needs them in PATH: install the Xcode Command Line Tools (xcode-select --install), \
+         point DORA_<TOOL> (e.g. DORA_OTOOL) at another build, or skip otool and nm with --no-deps and --no-symbols"
+    );
+    std::process::exit(EXIT_FATAL);
+}
+
+// "--no-symbols" and "--no-deps" leave out symbols and libraries whatever
+// "--collect" says
+fn scan_options(cli: &Cli, root: Option<&PathBuf>) -> ScanOptions {
+    let collects = |collector| {
+        cli.collect
+            .as_ref()
+            .is_none_or(|collectors| collectors.contains(&collector))
+    };
+
+    ScanOptions {
+        max_depth: cli.max_depth,
+        limit_per_dir: cli.limit_per_dir,
+        progress: !cli.no_progress && !cli.quiet,
+        root: root.cloned(),
+        skip_symbols: cli.no_symbols || !collects(Collector::Symbols),
+        skip_dependencies: cli.no_deps || !collects(Collector::Libraries),
+        skip_entitlements: !collects(Collector::Entitlements),
+        skip_mach_services: !collects(Collector::Machservices),
+        third_party: cli.third_party,
+        observer: None,
+    }
+}
+
+// Print the results of "dora analyze" for a single binary
+fn print_analysis(binary: &str, json: bool) {
+    let analysis = analyze_binary(binary);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&analysis).expect("Failed to serialize analysis")
+        );
+        return;
+    }
+
+    let text = |key: &str| analysis[key].as_str().unwrap_or("NULL").to_string();
+    println!("Binary: {}", text("binary"));
+    println!("Identifier: {}", text("identifier"));
+    println!("Team identifier: {}", text("team_id"));
+
+    let flags = match &analysis["codesign_flags"] {
+        JsonValue::Array(flags) => flags
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .collect::<Vec<&str>>()
+            .join(", "),
+        _ => "NULL".to_string(),
+    };
+    println!("Code signing flags: {}", flags);
+    println!(
+        "Platform identifier: {}",
+        analysis["platform_identifier"]
+            .as_i64()
+            .map_or("NULL".to_string(), |platform| platform.to_string())
+    );
+    println!("Minimum OS version: {}", text("min_os"));
+    println!("SDK version: {}", text("sdk"));
+    println!(
+        "Architectures: {}",
+        match &analysis["arch"] {
+            JsonValue::Array(archs) => archs
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .collect::<Vec<&str>>()
+                .join(", "),
+            _ => "NULL".to_string(),
+        }
+    );
+    println!(
+        "32-bit only: {}",
+        analysis["is_32bit_only"]
+            .as_bool()
+            .map_or("NULL", |only| if only { "yes" } else { "no" })
+    );
+    println!(
+        "Restricted: {}",
+        analysis["is_restricted"]
+            .as_bool()
+            .map_or("NULL", |restricted| if restricted { "yes" } else { "no" })
+    );
+
+    match &analysis["entitlements"] {
+        JsonValue::Object(entitlements) => {
+            println!("Entitlements ({}):", entitlements.len());
+            for (name, value) in entitlements {
+                println!("\t{}: {}", name, value);
+            }
+        }
+        _ => println!("Entitlements: NULL"),
+    }
+
+    for (title, key) in [("Libraries", "libraries"), ("Symbols", "symbols")] {
+        match &analysis[key] {
+            JsonValue::Array(values) => {
+                println!("{} ({}):", title, values.len());
+                for value in values {
+                    println!("\t{}", value.as_str().unwrap_or_default());
+                }
+            }
+            _ => println!("{}: NULL", title),
+        }
+    }
+}
+
 // Print banner for "dora" tool
 fn print_banner() {
     println!(
@@ -36,30 +455,70 @@ fn print_banner() {
     println!();
 }
 
-// Main function that orchestrates the database creation, plist parsing, and data extraction
-#[tokio::main]
-async fn main() {
-    print_banner();
+// Print the banner and load what the server needs, before any scan so that a
+// bad "--entitlement-symbols" or "--dangerous-symbols" file is reported right away
+fn prepare_server(cli: &Cli) {
+    if !cli.quiet {
+        print_banner();
+    }
 
-    let database_path = generate_sqlite_filename();
-    if !std::path::Path::new(&database_path).exists() {
-        // Create the SQLite database file
-        println!("Creating SQLite database file: {}", database_path);
+    let entitlement_symbols = load_entitlement_symbols(cli.entitlement_symbols.as_deref())
+        .expect("Failed to load the entitlement to symbol mapping");
+    set_entitlement_symbols(entitlement_symbols);
 
-        populate_db(&database_path)
-            .expect("Failed to populate the database with services and their data");
+    let dangerous_symbols = load_dangerous_symbols(cli.dangerous_symbols.as_deref())
+        .expect("Failed to load the dangerous symbols list");
+    set_dangerous_symbols(dangerous_symbols);
+
+    if let Some(token) = &cli.token {
+        set_access_token(token.clone());
     }
+    set_no_style(cli.no_style);
+}
 
-    // Start the web server to serve the data
-    println!(
+// Start the web server on the databases of the current folder
+async fn serve() {
+    info!(
         "Dora is running at http://{}:{}",
         LISTENING_ADDRESS, LISTENING_PORT
     );
 
     let app = Router::new()
         .route("/", get(index))
-        .route("/query", post(query))
-        .route("/service", get(service));
+        .route("/health", get(health))
+        .route("/scan", get(scan_page).post(start_scan))
+        .route("/scan/progress", get(scan_progress))
+        .route("/about", get(about))
+        .route("/api/databases", get(api_databases))
+        .route("/download", get(download))
+        .route("/openapi.json", get(openapi))
+        .route("/api/services", get(api_services))
+        .route("/api/services/batch", post(api_services_batch))
+        .route("/query", get(query).post(query))
+        .route("/service", get(service))
+        .route("/service/export", get(service_export))
+        .route("/review", post(review))
+        .route("/closure", get(closure))
+        .route("/services", get(services))
+        .route("/by-flag", get(by_flag))
+        .route("/origin", get(origin))
+        .route("/top-risk", get(top_risk))
+        .route("/overprivileged", get(overprivileged))
+        .route("/dangerous-symbols", get(dangerous_symbols_services))
+        .route("/writable-daemons", get(writable_daemons))
+        .route("/scripts", get(scripts))
+        .route("/dynamic-loaders", get(dynamic_loaders))
+        .route("/thirtytwobit", get(thirty_two_bit))
+        .route("/unrestricted-root", get(unrestricted_root))
+        .route("/new-since", get(new_since))
+        .route("/entitlement-tree", get(entitlement_tree))
+        .route("/xpc-services", get(xpc_services))
+        .route("/phantom-libraries", get(phantom_libraries))
+        // Service pages of symbol-heavy binaries and JSON results can be large:
+        // compress responses when the client accepts gzip or brotli
+        .layer(CompressionLayer::new())
+        .with_state(AppState::default())
+        .layer(middleware::from_fn(require_token));
 
     let listener =
         tokio::net::TcpListener::bind(format!("{}:{}", LISTENING_ADDRESS, LISTENING_PORT))
@@ -67,3 +526,137 @@ async fn main() {
             .unwrap();
     axum::serve(listener, app).await.unwrap();
 }
+
+// Main function that orchestrates the database creation, plist parsing, and data extraction
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    set_quiet(cli.quiet);
+    set_tool_timeout(cli.tool_timeout);
+    set_entitlements_tool(cli.entitlements_tool);
+
+    match load_context_map(cli.context_map.as_deref()) {
+        Ok(context_map) => set_context_map(context_map),
+        Err(e) => {
+            eprintln!("Failed to load the execution context map: {}", e);
+            std::process::exit(EXIT_FATAL);
+        }
+    }
+
+    match &cli.command {
+        Some(Command::Analyze { binary, json }) => {
+            print_analysis(binary, *json);
+            return;
+        }
+        Some(Command::Refresh { db, label }) => {
+            // Refreshing always extracts libraries and symbols
+            let mut tools = vec!["codesign", "plutil", "otool", "nm"];
+            if cli.entitlements_tool == EntitlementsTool::Ldid {
+                tools.push("ldid");
+            }
+            check_tools(&tools);
+            if let Err(e) = refresh_service(db, label) {
+                eprintln!("Failed to refresh service {}: {}", label, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Scan { root, name, json }) => {
+            scan(&cli, root.as_ref(), name.as_deref(), *json);
+            return;
+        }
+        Some(Command::Sample { out }) => {
+            if out.exists() {
+                eprintln!("Database {} already exists", out.display());
+                std::process::exit(1);
+            }
+            match populate_sample_db(&out.to_string_lossy()) {
+                Ok(count) => info!(
+                    "Created sample database {} with {} services",
+                    out.display(),
+                    count
+                ),
+                Err(e) => {
+                    eprintln!("Failed to create sample database {}: {}", out.display(), e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Migrate { db }) => {
+            let databases = match db {
+                Some(db) if !is_valid_db(db) => {
+                    eprintln!("Invalid database name: {}", db);
+                    std::process::exit(1);
+                }
+                Some(db) => vec![db.clone()],
+                None => get_available_databases(),
+            };
+            for db in databases.iter().filter(|db| is_valid_db(db)) {
+                match migrate_database(db) {
+                    Ok(columns) => info!("Migrated database {} ({} columns added)", db, columns),
+                    Err(e) => {
+                        eprintln!("Failed to migrate database {}: {}", db, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            return;
+        }
+        Some(Command::Export { db, jsonl }) => {
+            if !is_valid_db(db) || !Path::new(db).is_file() {
+                eprintln!("Invalid database: {}", db);
+                std::process::exit(1);
+            }
+            match export_jsonl(db, jsonl) {
+                Ok(count) => info!("Exported {} services to {}", count, jsonl.display()),
+                Err(e) => {
+                    eprintln!("Failed to export database {}: {}", db, e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Serve { db }) => {
+            if let Some(db) = db {
+                if !is_valid_db(db) || !Path::new(db).is_file() {
+                    eprintln!("Invalid database: {}", db);
+                    std::process::exit(1);
+                }
+                set_served_database(db.clone());
+            }
+            prepare_server(&cli);
+            serve().await;
+            return;
+        }
+        None => {}
+    }
+
+    // Without a command, scan the host unless its database already exists,
+    // then serve
+    prepare_server(&cli);
+
+    check_tools(&["sw_vers"]);
+    let os_version = get_os_version();
+    let database_path = generate_sqlite_filename(&os_version);
+    if !Path::new(&database_path).exists() {
+        check_tools(&scan_tools(&cli));
+
+        // Create the SQLite database file
+        info!("Creating SQLite database file: {}", database_path);
+
+        let summary = populate_db(&database_path, &os_version, &scan_options(&cli, None))
+            .expect("Failed to populate the database with services and their data");
+        print_scan_summary(&summary, cli.profile);
+    }
+
+    // The host can be scanned again from the web interface
+    set_host_scan(HostScan {
+        database: database_path,
+        os_version,
+        options: scan_options(&cli, None),
+        tools: scan_tools(&cli),
+    });
+
+    serve().await;
+}
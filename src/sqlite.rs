@@ -1,31 +1,72 @@
-use rusqlite::Connection;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use rusqlite::OptionalExtension;
 use rusqlite::params;
-use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{Value as JsonValue, json};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
 
 use crate::consts::{
-    ENTITLEMENTS_VALUE_BY_SERVICE_LABEL, INSERT_LIBRARY, INSERT_MACH_SERVICE,
-    INSERT_SERVICE_ENTITLEMENT, INSERT_SYMBOL, LIBRARIES_BY_LABEL, MACH_SERVICES_BY_LABEL,
-    SERVICE_BY_LABEL, SERVICES_BY_ENTITLEMENT, SERVICES_BY_ENTITLEMENT_AND_SYMBOL,
-    SERVICES_BY_LABEL_PATTERN, SERVICES_BY_LIBRARY, SERVICES_BY_SYMBOL, SYMBOLS_BY_LABEL,
+    BINARY_TYPE_MACHO, BINARY_TYPE_SCRIPT, DEFAULT_PER_PAGE, DELETE_REVIEW,
+    DELETE_SERVICE_ENTITLEMENTS, DELETE_SERVICE_LIBRARIES, DELETE_SERVICE_SYMBOLS, DYNAMIC_LOADERS,
+    DYNAMIC_LOADING_SYMBOLS, ENTITLEMENT_SERVICE_COUNTS, ENTITLEMENTS_ERROR, ENTITLEMENTS_NONE,
+    ENTITLEMENTS_PRESENT, ENTITLEMENTS_VALUE_BY_SERVICE_LABEL, ENV_VARS_BY_LABEL,
+    EXPORT_ENTITLEMENTS, EXPORT_ENV_VARS, EXPORT_LIBRARIES, EXPORT_MACH_SERVICES, EXPORT_SERVICES,
+    EXPORT_SERVICES_BY_LABEL, EXPORT_SYMBOLS, FUZZY_MAX_RESULTS, INSERT_BINARY_SERVICE,
+    INSERT_ENV_VAR, INSERT_LIBRARY, INSERT_LIBRARY_DEPENDENCY, INSERT_MACH_SERVICE,
+    INSERT_SCAN_INFO, INSERT_SERVICE_ENTITLEMENT, INSERT_SYMBOL, INSERT_XPC_SERVICE, KIND_AGENT,
+    KIND_BINARY, KIND_DAEMON, KIND_DEXT, KIND_KEXT, LIBRARIES_BY_LABEL, LIBRARY_CLOSURE,
+    LIBRARY_ID_BY_PATH, MACH_SERVICES_BY_LABEL, MAX_PER_PAGE, MIGRATION_DEFAULTS,
+    NEW_ENTITLEMENTS_SINCE, NEW_LIBRARIES_SINCE, NEW_SYMBOLS_SINCE, PHANTOM_LIBRARIES,
+    REVIEW_BY_LABEL, REVIEW_STATUSES_BY_LABEL, SCAN_BATCH_SIZE, SCAN_COUNTS, SCAN_INFO,
+    SCAN_PRAGMAS, SCAN_ROOT, SCHEMA_TABLES, SCRIPT_SERVICES, SERVICE_BY_LABEL,
+    SERVICE_ENTITLEMENT_NAMES, SERVICE_EXISTS, SERVICE_HAS_SYMBOL, SERVICE_ID_AND_PATH_BY_LABEL,
+    SERVICE_LABELS_AND_PATHS, SERVICE_RISK_FACTS, SERVICE_SOURCES_BY_LABEL,
+    SERVICES_BY_CODESIGN_FLAG, SERVICES_BY_ENTITLEMENT, SERVICES_BY_ENTITLEMENT_AND_SYMBOL,
+    SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT, SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT_NOCASE,
+    SERVICES_BY_ENTITLEMENT_AND_SYMBOL_NOCASE, SERVICES_BY_ENTITLEMENT_NAME,
+    SERVICES_BY_ENTITLEMENT_VALUE, SERVICES_BY_FILTERS, SERVICES_BY_LABEL_PATTERN,
+    SERVICES_BY_LIBRARY, SERVICES_BY_PATH_PATTERN, SERVICES_BY_SIGNING_ORIGIN, SERVICES_BY_SYMBOL,
+    SERVICES_BY_SYMBOL_AND_LIBRARY, SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT,
+    SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT_NOCASE, SERVICES_BY_SYMBOL_AND_LIBRARY_NOCASE,
+    SERVICES_BY_SYMBOL_EXACT, SERVICES_BY_SYMBOL_EXACT_NOCASE, SERVICES_BY_SYMBOL_NOCASE,
+    SERVICES_ID_SOURCE_PATH_BY_LABEL, SOURCE_BINARY, SOURCE_PLIST, SYMBOLS_BY_LABEL,
+    THIRD_PARTY_BINARY_FOLDERS, THIRD_PARTY_FOLDERS, THIRD_PARTY_LAUNCH_PATHS,
+    THIRTY_TWO_BIT_SERVICES, TOP_RISK_SERVICES, UNRESTRICTED_ROOT_SERVICES,
+    UPDATE_KEEP_ALIVE_CONDITIONS, UPDATE_LIBRARY_SHARED_CACHE, UPDATE_LIBRARY_SIGNING_INFO,
+    UPDATE_LIBRARY_WRITABLE_BY_NONROOT, UPDATE_SERVICE_ARCH, UPDATE_SERVICE_BINARY_TYPE,
+    UPDATE_SERVICE_BUILD_VERSION, UPDATE_SERVICE_BUNDLE, UPDATE_SERVICE_DYNAMIC_LOADING,
+    UPDATE_SERVICE_ENTITLEMENTS_STATUS, UPDATE_SERVICE_PLIST_TARGET, UPDATE_SERVICE_PROGRAM,
+    UPDATE_SERVICE_RESTRICTED, UPDATE_SERVICE_RISK, UPDATE_SERVICE_RUN_AS_GROUP,
+    UPDATE_SERVICE_SESSION_TYPES, UPDATE_SERVICE_SIGNING_INFO, UPDATE_SERVICE_WRITABLE_BY_NONROOT,
+    UPDATE_SYMBOL_DEMANGLED, UPDATE_THIRD_PARTY_SERVICES, UPSERT_REVIEW, WRITABLE_SERVICES,
+    XPC_SERVICES,
 };
+use crate::error::DoraError;
 use crate::macho::*;
-use crate::utils::parse_service_plist;
+use crate::utils::{
+    EntitlementSymbols, ExecutionContext, OsVersion, ProgressObserver, ScanProgress,
+    dangerous_symbols, entitlement_symbols, execution_context, highlight, html_escape, info,
+    is_writable_by_nonroot, parse_service_plist,
+};
 
 // Function to read SQL queries from a file
 // This function takes a file name as input and reads the SQL queries from it
 // It returns the queries as a string
-fn read_sql_queries_from_file<P: AsRef<Path>>(
-    file_path: P,
-) -> Result<String, Box<dyn std::error::Error>> {
+fn read_sql_queries_from_file<P: AsRef<Path>>(file_path: P) -> Result<String, DoraError> {
     let mut file = File::open(&file_path)?;
     let mut sql = String::new();
 
     file.read_to_string(&mut sql)?;
-    println!(
+    info!(
         "Read SQL queries from file: {}",
         file_path.as_ref().display()
     );
@@ -33,39 +74,113 @@ fn read_sql_queries_from_file<P: AsRef<Path>>(
     Ok(sql)
 }
 
+// Bring an existing database up to the current schema: the columns added
+// since it was built are added to its tables, then the creation queries
+// create the missing tables and indexes. Constraints of existing columns are
+// kept as they were, and the new columns stay NULL for existing rows until a
+// rescan (or a refresh of their service).
+// Returns the number of columns added.
+pub fn migrate_database(db: &str) -> Result<usize, DoraError> {
+    let creation_queries = include_str!("../creation_query.sql");
+
+    // The current schema, to compare the tables of the database with
+    let current = Connection::open_in_memory()?;
+    current.execute_batch(creation_queries)?;
+    let tables: Vec<String> = current
+        .prepare(SCHEMA_TABLES)?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let mut added = 0;
+    for table in &tables {
+        let existing = table_columns(&conn, table)?;
+        // Missing tables are created by the creation queries below
+        if existing.is_empty() {
+            continue;
+        }
+
+        for (name, column_type, not_null, default) in table_columns(&current, table)? {
+            if existing.iter().any(|(existing, ..)| *existing == name) {
+                continue;
+            }
+
+            // SQLite only adds columns with a constant default, which NOT NULL
+            // ones can't do without
+            let default = MIGRATION_DEFAULTS
+                .iter()
+                .find(|(t, column, _)| t == table && *column == name)
+                .map(|(_, _, default)| default.to_string())
+                .or(default.filter(|default| is_constant(default)));
+            let constraints = match default {
+                Some(default) if not_null => format!(" NOT NULL DEFAULT {default}"),
+                Some(default) => format!(" DEFAULT {default}"),
+                None => String::new(),
+            };
+            conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN {name} {column_type}{constraints}"),
+                [],
+            )?;
+            added += 1;
+        }
+    }
+
+    conn.execute_batch(creation_queries)?;
+
+    Ok(added)
+}
+
+// Name, type, NOT NULL and default value of a column
+type ColumnDefinition = (String, String, bool, Option<String>);
+
+// Columns of a table, none when the table doesn't exist
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<ColumnDefinition>, rusqlite::Error> {
+    conn.prepare(&format!("PRAGMA table_info({table})"))?
+        .query_map([], |row| {
+            Ok((row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?
+        .collect()
+}
+
+// Whether a column default is a literal: a number, a string or NULL, unlike
+// e.g. the "strftime(...)" of the "first_seen" columns
+fn is_constant(default: &str) -> bool {
+    default.starts_with('\'')
+        || default.parse::<f64>().is_ok()
+        || default.eq_ignore_ascii_case("NULL")
+}
+
 ////////////////////////////////////////////////
 ///////// SAVE DATA TO SQLITE DATABASE /////////
 ////////////////////////////////////////////////
 
 // Function that takes the parsed JSON for a plist file and saves it to a SQLite database
 fn save_service(
-    plist_path: &String,
+    plist_path: &str,
     json: &JsonValue,
     conn: &rusqlite::Connection,
-) -> Result<i64, Box<dyn std::error::Error>> {
+) -> Result<i64, DoraError> {
     // Extract values from the JSON object
     let label: &str = json.get("Label").and_then(JsonValue::as_str).unwrap_or("");
-    let mut path: &str = json
-        .get("Program")
-        .and_then(JsonValue::as_str)
-        .unwrap_or("");
-
-    // If "Program" is not present, try "ProgramArguments"
-    if path.is_empty() {
-        path = json
-            .get("ProgramArguments")
-            .and_then(JsonValue::as_array)
-            .and_then(|args| args.get(0))
-            .and_then(JsonValue::as_str)
-            .unwrap_or("");
-    }
+    let program: &str = job_program(json).unwrap_or("");
+    let is_relative = !program.is_empty() && !program.starts_with('/');
+    let resolved = resolve_program(program, json);
+    let path: &str = resolved.as_deref().unwrap_or(program);
 
-    // if "plist_path" contains "LaunchAgents" the "run_as_user" is 501 else 0
-    let run_as_user: &str = if plist_path.contains("LaunchAgents") {
-        "standard" // User ID for the current user
+    // Agents run as the logged-in user: launchd only honors "UserName" and
+    // "GroupName" for jobs of the system domain, which otherwise run as root
+    let is_agent = execution_context(plist_path) == ExecutionContext::User;
+    let account = |key: &str| {
+        json.get(key)
+            .and_then(JsonValue::as_str)
+            .filter(|_| !is_agent)
+    };
+    let run_as_user: &str = if is_agent {
+        "standard" // The current user
     } else {
-        "root" // System service, run as root
+        account("UserName").unwrap_or("root")
     };
+    let run_as_group: Option<&str> = account("GroupName");
 
     // Get "RunAtLoad" and "KeepAlive" values from JSON that are Bool(true/false)
     let run_at_load: i32 = json
@@ -73,35 +188,134 @@ fn save_service(
         .and_then(JsonValue::as_bool)
         .unwrap_or(false) as i32;
 
-    let keep_alive: i32 = json
-        .get("KeepAlive")
+    // "KeepAlive" is either a boolean or a dictionary of conditions
+    // (SuccessfulExit, Crashed, NetworkState, PathState, ...) under which launchd
+    // keeps the service running. The latter is stored as keep_alive = 1 along with
+    // the conditions as JSON.
+    let (keep_alive, keep_alive_conditions): (i32, Option<String>) = match json.get("KeepAlive") {
+        Some(JsonValue::Bool(keep_alive)) => (*keep_alive as i32, None),
+        Some(conditions @ JsonValue::Object(_)) => (1, Some(conditions.to_string())),
+        _ => (0, None),
+    };
+
+    // "Disabled" services are not loaded by launchd unless an override enables them
+    let disabled: i32 = json
+        .get("Disabled")
         .and_then(JsonValue::as_bool)
         .unwrap_or(false) as i32;
 
-    let service_id = insert_and_get_id(
+    let session_types = job_session_types(json);
+
+    let service_id: i64 = insert_and_get_id(
         "service",
         &[
             "label",
+            "source",
             "path",
             "run_as_user",
             "run_at_load",
             "keep_alive",
             "plist_path",
+            "disabled",
+            "kind",
         ],
         &[
             label,
+            SOURCE_PLIST,
             path,
             run_as_user,
             &run_at_load.to_string(),
             &keep_alive.to_string(),
             plist_path,
+            &disabled.to_string(),
+            if is_agent { KIND_AGENT } else { KIND_DAEMON },
         ],
+        &["label", "source"],
         conn,
-    );
+    )?;
+
+    if let Some(conditions) = keep_alive_conditions {
+        conn.execute(
+            UPDATE_KEEP_ALIVE_CONDITIONS,
+            params![conditions, service_id],
+        )?;
+    }
+
+    if let Some(group) = run_as_group {
+        conn.execute(UPDATE_SERVICE_RUN_AS_GROUP, params![group, service_id])?;
+    }
+
+    if is_relative {
+        conn.execute(UPDATE_SERVICE_PROGRAM, params![program, service_id])?;
+    }
+
+    if let Some(session_types) = session_types {
+        conn.execute(
+            UPDATE_SERVICE_SESSION_TYPES,
+            params![session_types, service_id],
+        )?;
+    }
 
     //println!("Inserted service data for label: {}", label);
 
-    service_id
+    Ok(service_id)
+}
+
+// Get the program a launchd job runs: "Program", or else the first of
+// "ProgramArguments"
+fn job_program(json: &JsonValue) -> Option<&str> {
+    json.get("Program")
+        .and_then(JsonValue::as_str)
+        .filter(|program| !program.is_empty())
+        .or_else(|| {
+            json.get("ProgramArguments")
+                .and_then(JsonValue::as_array)
+                .and_then(|args| args.first())
+                .and_then(JsonValue::as_str)
+        })
+}
+
+// Get the sessions a job is loaded in ("Aqua", "LoginWindow", "Background",
+// "System", ...), comma-separated. "LimitLoadToSessionType" is either a
+// string or an array of them.
+fn job_session_types(json: &JsonValue) -> Option<String> {
+    let session_types: Vec<&str> = match json.get("LimitLoadToSessionType")? {
+        JsonValue::String(session_type) => vec![session_type.as_str()],
+        JsonValue::Array(session_types) => {
+            session_types.iter().filter_map(JsonValue::as_str).collect()
+        }
+        _ => return None,
+    };
+    let session_types: Vec<&str> = session_types
+        .into_iter()
+        .filter(|session_type| !session_type.is_empty())
+        .collect();
+
+    (!session_types.is_empty()).then(|| session_types.join(","))
+}
+
+// Get the absolute path of a job's program. A relative one is resolved
+// against the job's "WorkingDirectory", and is None when the job has none.
+fn resolve_program(program: &str, json: &JsonValue) -> Option<String> {
+    if program.starts_with('/') {
+        return Some(program.to_string());
+    }
+
+    let working_directory = json
+        .get("WorkingDirectory")
+        .and_then(JsonValue::as_str)
+        .filter(|directory| directory.starts_with('/'))?;
+    // Drop the "." components of "./program"
+    let resolved: PathBuf = Path::new(working_directory)
+        .components()
+        .chain(
+            Path::new(program)
+                .components()
+                .filter(|component| *component != Component::CurDir),
+        )
+        .collect();
+
+    Some(resolved.to_string_lossy().into_owned())
 }
 
 // Function that takes service id, JSON formatted entitlements and saves them
@@ -110,12 +324,13 @@ fn save_service_entitlements(
     service_id: i64,
     entitlements: &JsonValue,
     conn: &rusqlite::Connection,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), DoraError> {
     // Iterate over the entitlements JSON object and insert each entitlement
     if let JsonValue::Object(entitlements_map) = entitlements {
         for (key, value) in entitlements_map {
             // Insert the entitlement into the entitlement table and get its id
-            let entitlement_id: i64 = insert_and_get_id("entitlement", &["name"], &[key], conn)?;
+            let entitlement_id: i64 =
+                insert_and_get_id("entitlement", &["name"], &[key], &["name"], conn)?;
 
             // The value could be a string, a boolean, a number, an array or a dictionary
             let value_str = match value {
@@ -146,46 +361,179 @@ fn save_service_entitlements(
     Ok(())
 }
 
+// Function to save the "EnvironmentVariables" dictionary of a service.
+// Anything else than a dictionary (or no dictionary at all) is ignored.
+fn save_environment_variables(
+    service_id: i64,
+    json: &JsonValue,
+    conn: &rusqlite::Connection,
+) -> Result<(), DoraError> {
+    let Some(JsonValue::Object(variables)) = json.get("EnvironmentVariables") else {
+        return Ok(());
+    };
+
+    for (name, value) in variables {
+        // Values should be strings, keep anything else as JSON
+        let value_str = match value {
+            JsonValue::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+
+        conn.execute(INSERT_ENV_VAR, params![service_id, name, value_str])?;
+    }
+
+    Ok(())
+}
+
 // Function to save mach services data to the database
 fn save_mach_services(
     service_id: i64,
     json: &JsonValue,
     conn: &rusqlite::Connection,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), DoraError> {
     // Iterate over the JSON object and insert each mach service
-    if let Some(mach_services) = json.get("MachServices") {
-        if let JsonValue::Object(services) = mach_services {
-            for (name, value) in services {
-                let value_str: &str = value.as_str().unwrap_or("");
-                conn.execute(
-                    INSERT_MACH_SERVICE,
-                    rusqlite::params![name, value_str, service_id],
-                )?;
-            }
+    if let Some(mach_services) = json.get("MachServices")
+        && let JsonValue::Object(services) = mach_services
+    {
+        for (name, value) in services {
+            // The value is usually "true", but can be a dictionary of options
+            // such as "HostSpecialPort" or "ResetAtClose": keep it as JSON.
+            let value_str = match value {
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+
+            // Host special ports are high-value targets, keep them queryable
+            let host_special_port: Option<i64> =
+                value.get("HostSpecialPort").and_then(JsonValue::as_i64);
+
+            conn.execute(
+                INSERT_MACH_SERVICE,
+                rusqlite::params![name, value_str, service_id, host_special_port],
+            )?;
         }
     }
 
     Ok(())
 }
 
+// Get the id of the library at "dep", saving it on first sight.
+// Libraries are shared by many services: codesign only runs the first time a
+// library path is seen, afterwards the stored row is reused.
+// Newly saved libraries that are on disk are also returned with their host
+// path, so that their own dependencies can be recorded.
+fn save_library(
+    dep: &str,
+    root: Option<&Path>,
+    conn: &rusqlite::Connection,
+) -> Result<(i64, Option<PathBuf>), DoraError> {
+    if let Some(id) = conn
+        .query_row(LIBRARY_ID_BY_PATH, params![dep], |row| row.get(0))
+        .optional()?
+    {
+        return Ok((id, None));
+    }
+
+    // Get dependency name
+    let library_name = dep.split('/').next_back().unwrap_or(dep).to_string();
+    let id = insert_and_get_id(
+        "library",
+        &["name", "path"],
+        &[&library_name, dep],
+        &["path"],
+        conn,
+    )?;
+
+    // A library is looked up in the image list of the dyld shared cache. When
+    // no cache could be read, one missing from disk is assumed to live in it.
+    // "@rpath/..."-like paths are left unknown.
+    let host_path = rooted(root, dep);
+    let on_disk = host_path.exists();
+    if dep.starts_with('/') {
+        let in_shared_cache = shared_cache_contains(root, dep).unwrap_or(!on_disk);
+        conn.execute(
+            UPDATE_LIBRARY_SHARED_CACHE,
+            params![in_shared_cache as i32, on_disk as i32, id],
+        )?;
+
+        // Nowhere to be found: could someone plant it?
+        if !on_disk && !in_shared_cache {
+            let writable = host_path
+                .ancestors()
+                .skip(1)
+                .find(|folder| folder.is_dir())
+                .and_then(is_writable_by_nonroot);
+            conn.execute(UPDATE_LIBRARY_WRITABLE_BY_NONROOT, params![writable, id])?;
+        }
+    }
+
+    if !on_disk {
+        return Ok((id, None));
+    }
+
+    match get_macho_signing_info(&host_path.to_string_lossy()) {
+        Ok(signing_info) => {
+            conn.execute(
+                UPDATE_LIBRARY_SIGNING_INFO,
+                params![signing_info.identifier, signing_info.team_id, id],
+            )?;
+        }
+        Err(e) => eprintln!("Failed to get signing info for library {}: {}", dep, e),
+    }
+
+    Ok((id, Some(host_path)))
+}
+
 // Function that saves to SQLite database the dependencies and
-// the relationship between the Mach service and the dependencies
+// the relationship between the Mach service and the dependencies.
+// The libraries a new library imports are followed as well, recording
+// library to library edges for the dependency closure of services.
 fn save_services_dependencies(
     service_id: i64,
     dependencies: Vec<String>,
+    root: Option<&Path>,
     conn: &rusqlite::Connection,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Insert each dependency into the mach_service table
+) -> Result<(), DoraError> {
+    // Libraries saved for the first time, whose dependencies are still unknown
+    let mut to_follow: Vec<(i64, PathBuf)> = Vec::new();
+
+    // Insert each dependency into the library table
     for dep in dependencies {
-        // Get dependency name
-        let library_name = dep.split('/').last().unwrap_or(&dep).to_string();
-        let library_id: i64 =
-            insert_and_get_id("library", &["name", "path"], &[&library_name, &dep], conn)?;
+        let (library_id, host_path) = save_library(&dep, root, conn)?;
+        to_follow.extend(host_path.map(|path| (library_id, path)));
 
         // Insert the relationship between the mach service and the library
         conn.execute(INSERT_LIBRARY, rusqlite::params![service_id, library_id])?;
     }
 
+    while let Some((library_id, host_path)) = to_follow.pop() {
+        let library = host_path.to_string_lossy();
+        let dependencies = match get_macho_external_dependencies(&library) {
+            Ok(dependencies) => dependencies,
+            Err(e) => {
+                eprintln!(
+                    "Failed to get external dependencies for library {:?}: {}",
+                    library, e
+                );
+                continue;
+            }
+        };
+
+        for dep in dependencies {
+            let (dependency_id, host_path) = save_library(&dep, root, conn)?;
+            to_follow.extend(host_path.map(|path| (dependency_id, path)));
+
+            // A library lists its own install name, which under a root
+            // differs from the path it was read from
+            if dependency_id != library_id {
+                conn.execute(
+                    INSERT_LIBRARY_DEPENDENCY,
+                    params![library_id, dependency_id],
+                )?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -194,10 +542,16 @@ fn save_service_imported_symbols(
     service_id: i64,
     symbols: Vec<String>,
     conn: &rusqlite::Connection,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Insert each symbol into the binary_imported_symbol table
-    for symbol in symbols {
-        let symbol_id: i64 = insert_and_get_id("symbol", &["name"], &[&symbol], conn)?;
+) -> Result<(), DoraError> {
+    let demangled = demangle_symbols(&symbols);
+
+    // Insert each symbol into the binary_imported_symbol table, with its
+    // demangled name when it has one
+    for (symbol, demangled) in symbols.into_iter().zip(demangled) {
+        let symbol_id: i64 = insert_and_get_id("symbol", &["name"], &[&symbol], &["name"], conn)?;
+        if let Some(demangled) = demangled {
+            conn.execute(UPDATE_SYMBOL_DEMANGLED, params![demangled, symbol_id])?;
+        }
 
         // Insert the relationship between the service and the symbol
         conn.execute(INSERT_SYMBOL, rusqlite::params![service_id, symbol_id])?;
@@ -208,61 +562,207 @@ fn save_service_imported_symbols(
 
 // Function that takes a Mach-O binary file path and extract all the information from it
 // and saves it to the SQLite database
+// "binary" is the path on the host; libraries are looked for below "options.root"
+// and dependencies/symbols are only extracted unless skipped by "options".
 pub fn process_and_save_macho_information(
     binary: &str,
     service_id: i64,
+    options: &ScanOptions,
     conn: &rusqlite::Connection,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Get entitlements for the binary or go on.
-    match get_macho_entitlements(binary) {
-        Ok(entitlements_json) => {
-            save_service_entitlements(service_id, &entitlements_json, &conn)
-                .expect("Failed to save service entitlements to database");
-        }
-        Err(e) => eprintln!("Failed to get entitlements for binary {:?}: {}", binary, e),
-    };
+) -> Result<(), DoraError> {
+    // Nothing can be extracted from a binary which is not on disk
+    if !Path::new(binary).exists() {
+        return Err(DoraError::BinaryNotFound(binary.to_string()));
+    }
 
-    // Get binary external dependencies
-    match get_macho_external_dependencies(binary) {
-        Ok(dependencies) => {
-            // Print the external dependencies
-            if !dependencies.is_empty() {
-                let _ = save_services_dependencies(service_id, dependencies.clone(), &conn);
-            } else {
-                println!("No external dependencies found for binary {:?}", binary);
-            }
+    // Flag binaries that a non-root user could replace
+    if let Some(writable) = is_writable_by_nonroot(Path::new(binary)) {
+        conn.execute(
+            UPDATE_SERVICE_WRITABLE_BY_NONROOT,
+            params![writable as i32, service_id],
+        )?;
+    }
+
+    // Launchd may start a script rather than a Mach-O binary: the tools have
+    // nothing to extract from it, only its interpreter is recorded
+    if !PathBuf::from(binary).is_macho() {
+        conn.execute(
+            UPDATE_SERVICE_BINARY_TYPE,
+            params![
+                BINARY_TYPE_SCRIPT,
+                get_script_interpreter(Path::new(binary)),
+                service_id
+            ],
+        )?;
+        return Ok(());
+    }
+    conn.execute(
+        UPDATE_SERVICE_BINARY_TYPE,
+        params![BINARY_TYPE_MACHO, None::<String>, service_id],
+    )?;
+
+    // The architectures come from the header: no tool is involved
+    match get_macho_archs(binary) {
+        Ok(archs) => {
+            let names: Vec<String> = archs.iter().map(|arch| arch.name.clone()).collect();
+            conn.execute(
+                UPDATE_SERVICE_ARCH,
+                params![names.join(","), is_32bit_only(&archs), service_id],
+            )?;
         }
-        Err(e) => eprintln!(
-            "Failed to get external dependencies for binary {:?}: {}",
-            binary, e
-        ),
+        Err(e) => eprintln!("Failed to get architectures for binary {:?}: {}", binary, e),
     }
 
-    // Get binary imported symbols
-    match get_macho_imported_symbols(&binary) {
-        Ok(symbols) => {
-            if !symbols.is_empty() {
-                let _ = save_service_imported_symbols(service_id, symbols, &conn);
-            } else {
-                println!("No imported symbols found for binary: {}", binary);
+    // codesign, otool and nm don't depend on each other: run them on their own
+    // threads so that a binary takes as long as the slowest of them, then save
+    // their results in order
+    let (codesign, otool, nm) = thread::scope(|scope| {
+        let codesign = scope.spawn(|| {
+            (
+                get_macho_signing_info(binary),
+                (!options.skip_entitlements).then(|| get_macho_entitlements(binary)),
+            )
+        });
+        let otool = (!options.skip_dependencies).then(|| {
+            scope.spawn(|| {
+                (
+                    get_macho_external_dependencies(binary),
+                    get_macho_build_version(binary),
+                )
+            })
+        });
+        let nm =
+            (!options.skip_symbols).then(|| scope.spawn(|| get_macho_imported_symbols(binary)));
+
+        (
+            join_tool_thread(codesign),
+            otool.map(join_tool_thread),
+            nm.map(join_tool_thread),
+        )
+    });
+    let (signing_info, entitlements) = codesign;
+    let codesign_flags = signing_info.as_ref().ok().map(|info| info.flags.clone());
+
+    // Record the code signing flags, which tell whether the binary can load
+    // unsigned libraries, and who signed it
+    match signing_info {
+        Ok(signing_info) => {
+            conn.execute(
+                UPDATE_SERVICE_SIGNING_INFO,
+                params![
+                    signing_info.flags.join(","),
+                    signing_info.platform_identifier,
+                    signing_info.team_id,
+                    service_id
+                ],
+            )?;
+        }
+        Err(e) => eprintln!("Failed to get signing info for binary {:?}: {}", binary, e),
+    }
+
+    // Save the entitlements of the binary, if any, telling a binary signed
+    // without entitlements apart from one whose entitlements couldn't be read.
+    // Their status stays NULL when they weren't collected.
+    if let Some(entitlements) = entitlements {
+        let entitlements_status = match entitlements {
+            Ok(entitlements_json) => {
+                save_service_entitlements(service_id, &entitlements_json, conn)?;
+                match entitlements_json.as_object() {
+                    Some(entitlements) if !entitlements.is_empty() => ENTITLEMENTS_PRESENT,
+                    _ => ENTITLEMENTS_NONE,
+                }
+            }
+            Err(DoraError::NoEntitlements(_)) => ENTITLEMENTS_NONE,
+            Err(e) => {
+                eprintln!("Failed to get entitlements for binary {:?}: {}", binary, e);
+                ENTITLEMENTS_ERROR
+            }
+        };
+        conn.execute(
+            UPDATE_SERVICE_ENTITLEMENTS_STATUS,
+            params![entitlements_status, service_id],
+        )?;
+    }
+
+    // Save binary external dependencies and deployment target, both from otool
+    let mut restrict_segment = None;
+    if let Some((dependencies, build_version)) = otool {
+        match dependencies {
+            Ok(dependencies) if !dependencies.is_empty() => {
+                save_services_dependencies(
+                    service_id,
+                    dependencies,
+                    options.root.as_deref(),
+                    conn,
+                )?;
+            }
+            Ok(_) => info!("No external dependencies found for binary {:?}", binary),
+            Err(e) => eprintln!(
+                "Failed to get external dependencies for binary {:?}: {}",
+                binary, e
+            ),
+        }
+
+        match build_version {
+            Ok(version) => {
+                conn.execute(
+                    UPDATE_SERVICE_BUILD_VERSION,
+                    params![version.min_os, version.sdk, service_id],
+                )?;
+                restrict_segment = Some(version.restrict_segment);
             }
+            Err(e) => eprintln!("Failed to get build version for binary {:?}: {}", binary, e),
+        }
+    }
+
+    // Record whether dyld ignores DYLD_INSERT_LIBRARIES for the binary
+    if let Some(restricted) = is_restricted(restrict_segment, codesign_flags.as_deref()) {
+        conn.execute(UPDATE_SERVICE_RESTRICTED, params![restricted, service_id])?;
+    }
+
+    // Save binary imported symbols, flagging the binary when it loads code at
+    // runtime so that it needn't be found among them at query time
+    if let Some(Ok(symbols)) = &nm {
+        let uses_dynamic_loading = symbols
+            .iter()
+            .any(|symbol| DYNAMIC_LOADING_SYMBOLS.contains(&symbol.as_str()));
+        conn.execute(
+            UPDATE_SERVICE_DYNAMIC_LOADING,
+            params![uses_dynamic_loading as i32, service_id],
+        )?;
+    }
+    match nm {
+        Some(Ok(symbols)) if !symbols.is_empty() => {
+            save_service_imported_symbols(service_id, symbols, conn)?;
         }
-        Err(e) => eprintln!(
+        Some(Ok(_)) => info!("No imported symbols found for binary: {}", binary),
+        Some(Err(e)) => eprintln!(
             "Failed to get imported symbols for binary {:?}: {}",
             binary, e
         ),
+        None => {}
     }
 
     Ok(())
 }
 
-// Insert new item into column(s) and retrieve its id
+// Wait for a thread running external tools, passing on its panic if any
+fn join_tool_thread<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+// Insert new item into column(s) and retrieve its id.
+// "key" lists the columns of a UNIQUE constraint of the table, among "columns":
+// when the item already exists, its row is looked up by these columns only.
 pub fn insert_and_get_id(
     table: &str,
     columns: &[&str],
     values: &[&str],
+    key: &[&str],
     conn: &rusqlite::Connection,
-) -> Result<i64, Box<dyn std::error::Error>> {
+) -> Result<i64, DoraError> {
     // Construct the SQL query dynamically based on the table and columns
     let placeholders: String = (1..=columns.len())
         .map(|i| format!("?{}", i))
@@ -277,413 +777,2974 @@ pub fn insert_and_get_id(
 
     // Execute the SQL statement to insert the data
     let res = conn.execute(&insert_sql, rusqlite::params_from_iter(values.iter()))?;
-    let id: i64;
-    if res == 0 {
-        let get_id_sql = format!("SELECT id FROM {} WHERE {} = ?1", table, columns[0]);
-        id = conn
-            .query_row(&get_id_sql, rusqlite::params![values[0]], |row| row.get(0))
-            .expect("Failed to get id from database");
+    let id: i64 = if res == 0 {
+        let key_values: Vec<&str> = key
+            .iter()
+            .map(|column| {
+                let index = columns
+                    .iter()
+                    .position(|c| c == column)
+                    .expect("Key column must be inserted");
+                values[index]
+            })
+            .collect();
+        let conditions = key
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+            .collect::<Vec<String>>()
+            .join(" AND ");
+        let get_id_sql = format!("SELECT id FROM {} WHERE {}", table, conditions);
+        conn.query_row(&get_id_sql, rusqlite::params_from_iter(key_values), |row| {
+            row.get(0)
+        })?
     } else {
         // If the insert was successful, get the last inserted row id
-        id = conn.last_insert_rowid();
-    }
+        conn.last_insert_rowid()
+    };
 
     Ok(id)
 }
 
-pub fn populate_db(sqlite_filename: &String) -> Result<(), Box<dyn std::error::Error>> {
-    // Read SQL queries from a file
-    let creation_queries = read_sql_queries_from_file("creation_query.sql")
-        .expect("Failed to read SQL queries from file");
-
-    let conn = Connection::open(sqlite_filename).expect("Failed to open SQLite database");
-    // Execute the SQL queries to create the database
-    conn.execute_batch(&creation_queries)
-        .expect("Failed to execute SQL queries to create the database");
+// List the plist files directly inside each of the launchd directories
+fn discover_plists(launch_paths: &[String]) -> Vec<PathBuf> {
+    let mut plist_paths = Vec::new();
 
-    println!("Database created successfully at {}", sqlite_filename);
+    for launch_path in launch_paths {
+        // A scanned root may lack some of the folders
+        let paths = match std::fs::read_dir(launch_path) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("Failed to read {} directory: {}", launch_path, e);
+                continue;
+            }
+        };
 
-    let launch_paths = [
-        //"/Library/LaunchAgents",
-        //"/Library/LaunchDaemons",
-        "/System/Library/LaunchAgents",
-        "/System/Library/LaunchDaemons",
-    ];
+        for entry in paths {
+            plist_paths.push(entry.expect("Failed to read entry").path());
+        }
+    }
 
-    // Iterate over launch_paths and process each directory
-
-    launch_paths.iter().for_each(|&launch_path| {
-        let paths = std::fs::read_dir(launch_path)
-            .expect(format!("Failed to read {} directory", launch_path).as_str());
-
-        paths.for_each(|entry| {
-            let path = entry.expect("Failed to read entry").path();
-
-            println!("Processing plist file: {:?}", path);
-
-            match parse_service_plist(&path) {
-                Ok(plist_json) => {
-                    // Save service data to SQLite database
-                    let service_id: i64 =
-                        save_service(&path.to_string_lossy().to_string(), &plist_json, &conn)
-                            .expect("Failed to save parsed plist data to database");
-
-                    // Save mach services data to SQLite database
-                    save_mach_services(service_id, &plist_json, &conn)
-                        .expect("Failed to save mach services data to database");
-
-                    // Now analyze the binary
-                    // Get the binary path from the JSON object
-                    // The binary path can be found in "Program" or "ProgramArguments" fields
-                    let binary_path = plist_json
-                        .get("Program")
-                        .and_then(JsonValue::as_str)
-                        .or_else(|| {
-                            plist_json
-                                .get("ProgramArguments")
-                                .and_then(JsonValue::as_array)
-                                .and_then(|args| args.get(0))
-                                .and_then(JsonValue::as_str)
-                        });
-
-                    // Save entitlements for the binary if it exists
-                    if let Some(binary) = binary_path {
-                        match process_and_save_macho_information(binary, service_id, &conn) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                eprintln!("Failed to process Mach-O binary {}: {}", binary, e)
-                            }
-                        }
-                    }
-                }
-                Err(e) => eprintln!("Failed to parse plist file {:?}: {}", path, e),
-            }
-        });
-    });
+    plist_paths
+}
 
-    // Iterate over all mach-o binaries under /System/Library/PrivateFrameworks, /usr/bin, /sbin, /usr/sbin
-    // and all of its subdirectories
-    let folders_to_scan = [
-        "/System/Library/PrivateFrameworks",
-        "/usr/bin",
-        "/sbin",
-        "/usr/sbin",
-    ];
+// List the Mach-O binaries below each of the folders to scan
+fn discover_binaries(
+    folders_to_scan: &[String],
+    max_depth: Option<usize>,
+    limit_per_dir: Option<usize>,
+) -> Vec<PathBuf> {
+    let mut binary_paths = Vec::new();
 
-    folders_to_scan.iter().for_each(|&folder| {
-        let entries = std::fs::read_dir(folder)
-            .expect(format!("Failed to read {} directory", folder).as_str());
-
-        entries.for_each(|entry| {
-            let entry = entry.expect("Failed to read entry");
-            let path = entry.path();
-
-            if path.is_file() && path.is_macho() {
-                // Process the Mach-O binary
-                println!("Processing Mach-O binary: {:?}", path);
-
-                let identifier = match get_macho_identifier(path.to_str().unwrap()) {
-                    Ok(id) => id,
-                    Err(e) => {
-                        eprintln!(
-                            "Failed to get identifier for binary {}: {}",
-                            path.display(),
-                            e
-                        );
-                        return;
-                    }
-                };
+    for folder in folders_to_scan {
+        let mut found = 0;
+        // Walk the folder recursively without following symlinks, so that
+        // framework "Versions/Current" links are neither scanned twice nor looped on
+        let mut walker = WalkDir::new(folder).follow_links(false);
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth);
+        }
 
-                let service_id: i64 = insert_and_get_id(
-                    "service",
-                    &["label", "path"],
-                    &[identifier.as_str(), path.to_str().unwrap()],
-                    &conn,
-                )
-                .expect("Failed to insert service data");
+        for entry in walker {
+            if limit_per_dir.is_some_and(|limit| found >= limit) {
+                break;
+            }
 
-                match process_and_save_macho_information(path.to_str().unwrap(), service_id, &conn)
-                {
-                    Ok(_) => {}
-                    Err(e) => {
-                        eprintln!("Failed to process Mach-O binary {}: {}", path.display(), e)
-                    }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("Failed to read entry under {}: {}", folder, e);
+                    continue;
                 }
+            };
+            let path = entry.path().to_path_buf();
+
+            if entry.file_type().is_file() && path.is_macho() {
+                binary_paths.push(path);
+                found += 1;
             }
-        });
-    });
+        }
+    }
 
-    // SQLite database connection is automatically closed when it goes out of scope
+    binary_paths
+}
 
-    Ok(())
+// Get the Info.plist of a bundle and the folder of its executable. Kexts and
+// XPC services use the macOS bundle layout ("Contents/Info.plist" and
+// "Contents/MacOS"), dexts may use the flat one.
+fn bundle_layout(bundle: &Path) -> (PathBuf, PathBuf) {
+    let contents = bundle.join("Contents");
+    if contents.is_dir() {
+        (contents.join("Info.plist"), contents.join("MacOS"))
+    } else {
+        (bundle.join("Info.plist"), bundle.to_path_buf())
+    }
 }
 
-//////////////////////////////////////////////////////////
-//////////////////////////////////////////////////////////
-//////////////////////////////////////////////////////////
+// Get the application bundle a path of the scanned system lies in: the
+// outermost ".app", so that a helper app nested in another one is tied back
+// to the application that ships it
+fn owning_app_bundle(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .skip(1)
+        .filter(|ancestor| ancestor.extension().is_some_and(|ext| ext == "app"))
+        .last()
+}
 
-//////////////////////////////////////////////////////////
-//////// LOOK FOR SERVICES FROM SQLITE DATABASE //////////
-//////////////////////////////////////////////////////////
+// Record the application bundle the program of a service lies in, if any,
+// with its "CFBundleIdentifier". "program" is a path of the scanned system.
+fn save_owning_app(
+    service_id: i64,
+    program: &Path,
+    root: Option<&Path>,
+    conn: &rusqlite::Connection,
+) -> Result<(), DoraError> {
+    let Some(bundle) = owning_app_bundle(program) else {
+        return Ok(());
+    };
 
-// Get services from SQLite database that have a sepcified entitlement AND
-// a specified symbol
-pub fn get_services_by_entitlement_and_symbol(
-    db: &String,
-    entitlement: &str,
-    symbol: &str,
-) -> Result<Vec<String>, rusqlite::Error> {
-    let conn = match rusqlite::Connection::open(db) {
-        Ok(conn) => conn,
-        Err(e) => return Err(e),
+    let (info_plist, _) = bundle_layout(&rooted(root, &bundle.to_string_lossy()));
+    let bundle_id = match parse_service_plist(&info_plist) {
+        Ok(info) => info
+            .get("CFBundleIdentifier")
+            .and_then(JsonValue::as_str)
+            .map(String::from),
+        Err(e) => {
+            eprintln!(
+                "Failed to read the Info.plist of {}: {}",
+                bundle.display(),
+                e
+            );
+            None
+        }
     };
 
-    let mut stmt = conn.prepare(SERVICES_BY_ENTITLEMENT_AND_SYMBOL)?;
-    let result_set = stmt.query_map(
-        params![format!("%{}%", entitlement), format!("*{}*", symbol)],
-        |row| {
-            Ok((
-                row.get::<_, String>(0)?, // label
-                row.get::<_, String>(1)?, // path
-            ))
-        },
+    conn.execute(
+        UPDATE_SERVICE_BUNDLE,
+        params![bundle.to_string_lossy(), bundle_id, service_id],
     )?;
 
-    let mut services = Vec::new();
-    for service in result_set {
-        match service {
-            Ok((label, path)) => {
-                services.push(format!(
-                    "<li><strong>Label:</strong> <a href=\"/service?db={db}&label={label}\">{label}</a> ({path})<br>"
-                ));
+    Ok(())
+}
+
+// Get the Mach-O executable of a bundle, named by the "CFBundleExecutable"
+// of its Info.plist
+fn bundle_executable(bundle: &Path) -> Option<PathBuf> {
+    let (info_plist, executable_dir) = bundle_layout(bundle);
+
+    let info = parse_service_plist(&info_plist).ok()?;
+    let executable = executable_dir.join(info.get("CFBundleExecutable")?.as_str()?);
+
+    (executable.is_file() && executable.is_macho()).then_some(executable)
+}
+
+// List the executables of the kext and dext bundles below each of the
+// extension folders, with their kind. Kexts may embed others in "PlugIns":
+// those are listed as well.
+fn discover_extensions(
+    extension_folders: &[String],
+    max_depth: Option<usize>,
+    limit_per_dir: Option<usize>,
+) -> Vec<(PathBuf, &'static str)> {
+    let mut executables = Vec::new();
+
+    for folder in extension_folders {
+        let mut found = 0;
+        let mut walker = WalkDir::new(folder).follow_links(false);
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+
+        for entry in walker {
+            if limit_per_dir.is_some_and(|limit| found >= limit) {
+                break;
             }
-            Err(e) => {
-                eprintln!("Error retrieving services by entitlement and symbol: {}", e);
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("Failed to read entry under {}: {}", folder, e);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+
+            let kind = match entry.path().extension().and_then(|ext| ext.to_str()) {
+                Some("kext") => KIND_KEXT,
+                Some("dext") => KIND_DEXT,
+                _ => continue,
+            };
+            match bundle_executable(entry.path()) {
+                Some(executable) => {
+                    executables.push((executable, kind));
+                    found += 1;
+                }
+                None => eprintln!(
+                    "Failed to find the executable of bundle {}",
+                    entry.path().display()
+                ),
             }
         }
     }
 
-    if services.is_empty() {
-        return Ok(vec![format!(
-            "<p>No services found with entitlement: {entitlement} and symbol: {symbol}</p>"
-        )]);
-    }
+    executables
+}
 
-    let mut html = String::new();
-    html.push_str(
-        format!(
-            "<h2>Found {} services with entitlement: {entitlement} and symbol: {symbol}</h2>",
-            services.len()
-        )
-        .as_str(),
-    );
-    for service in services {
-        html.push_str(&service);
+// List the XPC service bundles below each of the folders to scan, e.g.
+// "Foo.framework/Versions/A/XPCServices/Bar.xpc"
+fn discover_xpc_bundles(folders_to_scan: &[String], max_depth: Option<usize>) -> Vec<PathBuf> {
+    let mut bundles = Vec::new();
+
+    for folder in folders_to_scan {
+        let mut walker = WalkDir::new(folder).follow_links(false);
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+
+        // Read errors were already reported when looking for binaries
+        for entry in walker.into_iter().filter_map(Result::ok) {
+            if entry.file_type().is_dir()
+                && entry.path().extension().is_some_and(|ext| ext == "xpc")
+            {
+                bundles.push(entry.into_path());
+            }
+        }
     }
 
-    Ok(vec![html])
+    bundles
 }
 
-//
+// Save an XPC service bundle with its bundle identifier, executable, the
+// bundle embedding it and its ServiceType. Binaries must be saved first, so
+// that the service can be linked to the one analyzed from its executable.
+fn save_xpc_service(
+    bundle: &Path,
+    root: Option<&Path>,
+    conn: &rusqlite::Connection,
+) -> Result<(), DoraError> {
+    let (info_plist, _) = bundle_layout(bundle);
+    let info = parse_service_plist(&info_plist)?;
 
-// Get all services from SQLite database having a specific symbol.
-// Handle multiple services retrieved by symbol.
-pub fn get_services_by_symbol(db: &String, symbol: &str) -> Result<Vec<String>, rusqlite::Error> {
-    let conn = match rusqlite::Connection::open(db) {
-        Ok(conn) => conn,
-        Err(e) => return Err(e),
+    let Some(bundle_id) = info.get("CFBundleIdentifier").and_then(JsonValue::as_str) else {
+        eprintln!(
+            "Skipping XPC service {}: no CFBundleIdentifier",
+            bundle.display()
+        );
+        return Ok(());
     };
+    let executable =
+        bundle_executable(bundle).map(|path| unrooted(root, &path).to_string_lossy().into_owned());
+    // Services are launched in the app's context unless told otherwise
+    let service_type = info
+        .get("XPCService")
+        .and_then(|xpc| xpc.get("ServiceType"))
+        .and_then(JsonValue::as_str)
+        .unwrap_or("Application");
+    // The closest enclosing bundle, e.g. "Foo.framework" or "Foo.app"
+    let container = bundle
+        .ancestors()
+        .skip(1)
+        .find(|path| path.extension().is_some())
+        .map(|path| unrooted(root, path).to_string_lossy().into_owned());
+
+    conn.execute(
+        INSERT_XPC_SERVICE,
+        params![
+            bundle_id,
+            unrooted(root, bundle).to_string_lossy(),
+            executable,
+            container,
+            service_type
+        ],
+    )?;
+
+    Ok(())
+}
+
+// What a scan extracts besides the services themselves, chosen with
+// "--collect": leaving some out makes scans faster and databases smaller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Collector {
+    // Entitlements of the binaries (codesign or ldid)
+    Entitlements,
+    // Libraries imported by the binaries and their deployment target (otool)
+    Libraries,
+    // Symbols imported by the binaries (nm)
+    Symbols,
+    // Mach services declared by the plists
+    Machservices,
+}
+
+// Options controlling how "populate_db" scans the system.
+// The default scans the host and extracts everything.
+#[derive(Debug, Default, Clone)]
+pub struct ScanOptions {
+    // Maximum recursion depth below each folder of binaries (unbounded if None)
+    pub max_depth: Option<usize>,
+    // Maximum number of binaries taken from each folder of binaries (all if None),
+    // for quick sample scans
+    pub limit_per_dir: Option<usize>,
+    // Draw a progress bar instead of logging progress periodically
+    pub progress: bool,
+    // Root of the system to scan (e.g. a mounted backup) instead of the host's "/"
+    pub root: Option<PathBuf>,
+    // Don't run "nm": no imported symbols, but a much faster scan
+    pub skip_symbols: bool,
+    // Don't run "otool": no libraries nor deployment target
+    pub skip_dependencies: bool,
+    // Don't extract the entitlements of binaries
+    pub skip_entitlements: bool,
+    // Don't save the Mach services declared by plists
+    pub skip_mach_services: bool,
+    // Also scan the launchd folders and folders of binaries of third-party
+    // software
+    pub third_party: bool,
+    // Told about every file processed, besides the progress bar or log
+    pub observer: Option<ProgressObserver>,
+}
+
+// Figures reported once "populate_db" completes
+pub struct ScanSummary {
+    pub services: i64,
+    pub entitlements: i64,
+    pub libraries: i64,
+    pub symbols: i64,
+    // Number of plists, binaries and bundles skipped or only partly processed
+    // because of an error
+    pub failed_files: usize,
+    // Number of binaries each external tool failed on
+    pub tool_failures: BTreeMap<&'static str, usize>,
+    // Runs of each external tool and the time they took. Tools run
+    // concurrently, so the times may add up to more than "elapsed".
+    pub tool_timings: BTreeMap<&'static str, ToolTiming>,
+    pub elapsed: Duration,
+}
+
+impl ScanSummary {
+    // Whether errors were logged along the way: the database is usable, but
+    // some files or binaries are missing from it or incomplete
+    pub fn is_partial(&self) -> bool {
+        self.failed_files > 0 || !self.tool_failures.is_empty()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "services": self.services,
+            "entitlements": self.entitlements,
+            "libraries": self.libraries,
+            "symbols": self.symbols,
+            "failed_files": self.failed_files,
+            "tool_failures": self.tool_failures,
+            "tool_timings": self
+                .tool_timings
+                .iter()
+                .map(|(tool, timing)| {
+                    (
+                        tool.to_string(),
+                        json!({
+                            "runs": timing.runs,
+                            "total_seconds": timing.total.as_secs_f64(),
+                            "average_seconds": timing.average().as_secs_f64(),
+                        }),
+                    )
+                })
+                .collect::<serde_json::Map<String, JsonValue>>(),
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+        })
+    }
+}
+
+// Map an absolute path of the scanned system to the host path below "root", if set
+fn rooted(root: Option<&Path>, path: &str) -> PathBuf {
+    match root {
+        Some(root) => root.join(path.trim_start_matches('/')),
+        None => PathBuf::from(path),
+    }
+}
+
+// Get the file a symlink resolves to, None when "path" isn't a symlink.
+// Below a scanned root, absolute link targets are relative to the scanned
+// system rather than the host, so links are followed one at a time.
+// Broken links and loops are errors.
+fn resolve_symlink(root: Option<&Path>, path: &Path) -> std::io::Result<Option<PathBuf>> {
+    if !path.symlink_metadata()?.file_type().is_symlink() {
+        return Ok(None);
+    }
+    if root.is_none() {
+        return std::fs::canonicalize(path).map(Some);
+    }
+
+    // As many links as the kernel follows before giving up with ELOOP
+    const MAX_LINKS: usize = 40;
+
+    let mut resolved = path.to_path_buf();
+    for _ in 0..MAX_LINKS {
+        let target = std::fs::read_link(&resolved)?;
+        resolved = if target.is_absolute() {
+            rooted(root, &target.to_string_lossy())
+        } else {
+            resolved.parent().unwrap_or(Path::new("/")).join(target)
+        };
+        if !resolved.symlink_metadata()?.file_type().is_symlink() {
+            return Ok(Some(resolved));
+        }
+    }
+
+    Err(std::io::Error::other("too many levels of symbolic links"))
+}
+
+// Map a host path below "root", if set, back to the absolute path of the scanned system
+fn unrooted(root: Option<&Path>, path: &Path) -> PathBuf {
+    match root.and_then(|root| path.strip_prefix(root).ok()) {
+        Some(relative) => Path::new("/").join(relative),
+        None => path.to_path_buf(),
+    }
+}
+
+// Record where the database comes from: OS version, scanned paths, dora version,
+// scan time and the root of the scanned system
+fn save_scan_info(
+    os_version: &OsVersion,
+    launch_paths: &[String],
+    scan_dirs: &[String],
+    root: Option<&Path>,
+    conn: &Connection,
+) -> Result<(), DoraError> {
+    conn.execute(
+        INSERT_SCAN_INFO,
+        params![
+            os_version.product_name,
+            os_version.product_version,
+            os_version.build_version,
+            serde_json::to_string(launch_paths)?,
+            serde_json::to_string(scan_dirs)?,
+            env!("CARGO_PKG_VERSION"),
+            root.map(|root| root.to_string_lossy().into_owned()),
+        ],
+    )?;
+
+    Ok(())
+}
+
+// Transaction of a scan, committed and begun again every SCAN_BATCH_SIZE files
+struct ScanBatch<'conn> {
+    conn: &'conn Connection,
+    files: usize,
+}
+
+impl<'conn> ScanBatch<'conn> {
+    fn begin(conn: &'conn Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch("BEGIN")?;
+        Ok(ScanBatch { conn, files: 0 })
+    }
+
+    // Count a file about to be processed, committing the batch when it's full
+    fn next(&mut self) -> Result<(), rusqlite::Error> {
+        self.files += 1;
+        if self.files > SCAN_BATCH_SIZE {
+            self.conn.execute_batch("COMMIT; BEGIN")?;
+            self.files = 1;
+        }
+        Ok(())
+    }
+
+    fn commit(self) -> Result<(), rusqlite::Error> {
+        self.conn.execute_batch("COMMIT")
+    }
+}
+
+// Tag the services of third-party software, found below THIRD_PARTY_FOLDERS.
+// Paths are stored as seen from the scanned system, so this holds for "--root"
+// scans too.
+fn tag_third_party_services(conn: &Connection) -> Result<(), rusqlite::Error> {
+    for folder in THIRD_PARTY_FOLDERS {
+        conn.execute(UPDATE_THIRD_PARTY_SERVICES, params![format!("{}/", folder)])?;
+    }
+
+    Ok(())
+}
+
+// What the risk score of a service is computed from
+struct RiskFacts {
+    runs_as_root: bool,
+    run_at_load: bool,
+    keep_alive: bool,
+    // Holds a private entitlement ("com.apple.private.*") or one of the
+    // entitlement to symbol mapping
+    powerful_entitlements: bool,
+    // Imports a library a non-root user could replace or create
+    hijackable_library: bool,
+    // A non-root user could replace the binary
+    writable_binary: bool,
+    // Imports one of the dangerous symbols
+    dangerous_symbols: bool,
+}
+
+// Rules of the risk score: the factors that apply to a service, with their
+// weight. The score is the sum of the weights; it only orders services for
+// triage and doesn't claim anything is exploitable.
+// What an attacker can plant (a writable binary or library) weighs most, then
+// what makes a compromise worth more (root, powerful entitlements), then what
+// makes the code reachable (started at load, kept alive) or handy (dangerous
+// symbols).
+fn risk_factors(facts: &RiskFacts) -> Vec<(&'static str, i64)> {
+    [
+        (facts.writable_binary, "writable_binary", 3),
+        (facts.hijackable_library, "hijackable_library", 3),
+        (facts.runs_as_root, "root", 2),
+        (facts.powerful_entitlements, "powerful_entitlements", 2),
+        (facts.run_at_load, "run_at_load", 1),
+        (facts.keep_alive, "keep_alive", 1),
+        (facts.dangerous_symbols, "dangerous_symbols", 1),
+    ]
+    .into_iter()
+    .filter(|(applies, _, _)| *applies)
+    .map(|(_, factor, weight)| (factor, weight))
+    .collect()
+}
+
+// Compute and save the risk score of every service, once everything else
+// about them is saved
+fn save_risk_scores(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let powerful = entitlement_symbols();
+    let dangerous = dangerous_symbols();
+
+    let mut facts_stmt = conn.prepare(SERVICE_RISK_FACTS)?;
+    let mut entitlements_stmt = conn.prepare(SERVICE_ENTITLEMENT_NAMES)?;
+    let mut has_symbol = conn.prepare(SERVICE_HAS_SYMBOL)?;
+    let mut update_stmt = conn.prepare(UPDATE_SERVICE_RISK)?;
+
+    let services: Vec<(i64, [Option<bool>; 5])> = facts_stmt
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                [
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ],
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    for (service_id, [root, run_at_load, keep_alive, writable, hijackable]) in services {
+        let entitlements: Vec<String> = entitlements_stmt
+            .query_map(params![service_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let mut dangerous_symbols = false;
+        for symbol in dangerous {
+            if has_symbol.query_row(params![service_id, symbol], |row| row.get::<_, bool>(0))? {
+                dangerous_symbols = true;
+                break;
+            }
+        }
+
+        let factors = risk_factors(&RiskFacts {
+            runs_as_root: root.unwrap_or(false),
+            run_at_load: run_at_load.unwrap_or(false),
+            keep_alive: keep_alive.unwrap_or(false),
+            powerful_entitlements: entitlements
+                .iter()
+                .any(|name| name.starts_with("com.apple.private.") || powerful.contains_key(name)),
+            hijackable_library: hijackable.unwrap_or(false),
+            writable_binary: writable.unwrap_or(false),
+            dangerous_symbols,
+        });
+        let score: i64 = factors.iter().map(|(_, weight)| weight).sum();
+        let names: Vec<&str> = factors.iter().map(|(factor, _)| *factor).collect();
+        update_stmt.execute(params![score, names.join(","), service_id])?;
+    }
+
+    Ok(())
+}
+
+// Save a Mach-O binary found while scanning folders as a service labelled with
+// its code-signing identifier.
+// A launchd job may already use the same label: both are kept, told apart by
+// their "source". Another binary with the same identifier is skipped instead,
+// as its data would otherwise be merged into the first one.
+fn save_binary_service(
+    identifier: &str,
+    path: &Path,
+    kind: &str,
+    conn: &rusqlite::Connection,
+) -> Result<Option<i64>, DoraError> {
+    let mut stmt = conn.prepare_cached(SERVICE_SOURCES_BY_LABEL)?;
+    let sources = stmt
+        .query_map(params![identifier], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    if sources.iter().any(|source| source == SOURCE_BINARY) {
+        eprintln!(
+            "Skipping binary {}: identifier {} already used by another binary",
+            path.display(),
+            identifier
+        );
+        return Ok(None);
+    }
+    if sources.iter().any(|source| source == SOURCE_PLIST) {
+        info!(
+            "Binary {} shares the label {} with a launchd service, keeping both",
+            path.display(),
+            identifier
+        );
+    }
+
+    conn.execute(
+        INSERT_BINARY_SERVICE,
+        params![identifier, SOURCE_BINARY, path.to_string_lossy(), kind],
+    )?;
+
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+pub fn populate_db(
+    sqlite_filename: &String,
+    os_version: &OsVersion,
+    options: &ScanOptions,
+) -> Result<ScanSummary, DoraError> {
+    let started = Instant::now();
+    reset_tool_stats();
+
+    // Read SQL queries from a file
+    let creation_queries = read_sql_queries_from_file("creation_query.sql")?;
+
+    let conn = Connection::open(sqlite_filename)?;
+    // Execute the SQL queries to create the database
+    conn.execute_batch(&creation_queries)?;
+    conn.execute_batch(SCAN_PRAGMAS)?;
+
+    info!("Database created successfully at {}", sqlite_filename);
+
+    let mut launch_paths = vec![
+        "/System/Library/LaunchAgents",
+        "/System/Library/LaunchDaemons",
+    ];
+
+    // Iterate over all mach-o binaries under /System/Library/PrivateFrameworks, /usr/bin, /sbin, /usr/sbin
+    // and all of its subdirectories, down to "max_depth" levels if set
+    let mut folders_to_scan = vec![
+        "/System/Library/PrivateFrameworks",
+        "/usr/bin",
+        "/sbin",
+        "/usr/sbin",
+    ];
+
+    // Third-party jobs and tools, on request
+    if options.third_party {
+        launch_paths.extend_from_slice(THIRD_PARTY_LAUNCH_PATHS);
+        folders_to_scan.extend_from_slice(THIRD_PARTY_BINARY_FOLDERS);
+    }
+
+    // Kernel and driver extension bundles, whose executable is analyzed like
+    // the binaries above, down to "max_depth" levels as well
+    let extension_folders = [
+        "/System/Library/Extensions",
+        "/Library/Extensions",
+        "/System/Library/DriverExtensions",
+        "/Library/DriverExtensions",
+    ];
+
+    let root = options.root.as_deref();
+    let host_paths = |paths: &[&str]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| rooted(root, path).to_string_lossy().into_owned())
+            .collect()
+    };
+    let launch_paths = host_paths(&launch_paths);
+    let folders_to_scan = host_paths(&folders_to_scan);
+    let extension_folders = host_paths(&extension_folders);
+
+    let scan_dirs = [folders_to_scan.as_slice(), extension_folders.as_slice()].concat();
+    save_scan_info(os_version, &launch_paths, &scan_dirs, root, &conn)?;
+
+    // Discover everything to process first, so that progress can be reported
+    // against a known total
+    let plist_paths = discover_plists(&launch_paths);
+    let mut binary_paths: Vec<(PathBuf, &str)> =
+        discover_binaries(&folders_to_scan, options.max_depth, options.limit_per_dir)
+            .into_iter()
+            .map(|path| (path, KIND_BINARY))
+            .collect();
+    let extension_paths =
+        discover_extensions(&extension_folders, options.max_depth, options.limit_per_dir);
+    info!(
+        "Found {} plist files, {} Mach-O binaries and {} extension bundles to process",
+        plist_paths.len(),
+        binary_paths.len(),
+        extension_paths.len()
+    );
+    binary_paths.extend(extension_paths);
+
+    // Files skipped or only partly processed because of an error
+    let mut failed_files = 0;
+
+    let mut progress = ScanProgress::new(
+        (plist_paths.len() + binary_paths.len()) as u64,
+        options.progress,
+        options.observer.clone(),
+    );
+
+    // Rows are committed every SCAN_BATCH_SIZE files, whether they could be
+    // processed or not. A database error aborts the scan, losing the current
+    // batch only.
+    let mut batch = ScanBatch::begin(&conn)?;
+
+    // Iterate over plist files and process each of them.
+    // Database errors abort the scan, anything else only skips the current file.
+    for path in &plist_paths {
+        progress.inc(path);
+        batch.next()?;
+
+        // A symlinked plist is read from its target, but saved under its own path
+        let target = match resolve_symlink(root, path) {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("Skipping broken symlink {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        match parse_service_plist(target.as_deref().unwrap_or(path)) {
+            Ok(plist_json) => {
+                // Save service data to SQLite database
+                let plist_path = unrooted(root, path);
+                let service_id: i64 =
+                    save_service(&plist_path.to_string_lossy(), &plist_json, &conn)?;
+                if let Some(target) = &target {
+                    conn.execute(
+                        UPDATE_SERVICE_PLIST_TARGET,
+                        params![unrooted(root, target).to_string_lossy(), service_id],
+                    )?;
+                }
+
+                // Save mach services data to SQLite database
+                if !options.skip_mach_services {
+                    save_mach_services(service_id, &plist_json, &conn)?;
+                }
+
+                // Save environment variables data to SQLite database
+                save_environment_variables(service_id, &plist_json, &conn)?;
+
+                // Now analyze the binary
+                // Get the binary path from the JSON object
+                // The binary path can be found in "Program" or "ProgramArguments" fields
+                let binary_path = match job_program(&plist_json) {
+                    Some(program) => {
+                        let resolved = resolve_program(program, &plist_json);
+                        if resolved.is_none() {
+                            eprintln!(
+                                "Relative program path {} of {:?} has no WorkingDirectory to resolve it against",
+                                program, path
+                            );
+                        }
+                        resolved
+                    }
+                    None => None,
+                };
+
+                // Save entitlements for the binary if it exists
+                if let Some(binary) = binary_path {
+                    save_owning_app(service_id, Path::new(&binary), root, &conn)?;
+                    let binary = rooted(root, &binary).to_string_lossy().into_owned();
+                    match process_and_save_macho_information(&binary, service_id, options, &conn) {
+                        Ok(_) => {}
+                        Err(e) if e.is_fatal() => return Err(e),
+                        Err(e) => {
+                            eprintln!("Failed to process Mach-O binary {}: {}", binary, e);
+                            failed_files += 1;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to parse plist file {:?}: {}", path, e);
+                failed_files += 1;
+            }
+        }
+    }
+
+    // Iterate over the discovered Mach-O binaries and process each of them
+    for (path, kind) in &binary_paths {
+        progress.inc(path);
+        batch.next()?;
+
+        let identifier = match get_macho_identifier(path.to_str().unwrap()) {
+            Ok(id) => id,
+            Err(e) => {
+                eprintln!(
+                    "Failed to get identifier for binary {}: {}",
+                    path.display(),
+                    e
+                );
+                failed_files += 1;
+                continue;
+            }
+        };
+
+        // Store the path as seen from the scanned system
+        let system_path = unrooted(root, path);
+
+        let service_id: i64 = match save_binary_service(&identifier, &system_path, kind, &conn)? {
+            Some(id) => id,
+            None => continue,
+        };
+        save_owning_app(service_id, &system_path, root, &conn)?;
+
+        match process_and_save_macho_information(path.to_str().unwrap(), service_id, options, &conn)
+        {
+            Ok(_) => {}
+            Err(e) if e.is_fatal() => return Err(e),
+            Err(e) => {
+                eprintln!("Failed to process Mach-O binary {}: {}", path.display(), e);
+                failed_files += 1;
+            }
+        }
+    }
+
+    progress.finish();
+
+    // XPC services, linked to the binaries saved above
+    let xpc_bundles = discover_xpc_bundles(&folders_to_scan, options.max_depth);
+    info!("Found {} XPC services", xpc_bundles.len());
+    for bundle in &xpc_bundles {
+        batch.next()?;
+        match save_xpc_service(bundle, root, &conn) {
+            Ok(_) => {}
+            Err(e) if e.is_fatal() => return Err(e),
+            Err(e) => {
+                eprintln!("Failed to save XPC service {}: {}", bundle.display(), e);
+                failed_files += 1;
+            }
+        }
+    }
+
+    tag_third_party_services(&conn)?;
+    save_risk_scores(&conn)?;
+    batch.commit()?;
+
+    let (services, entitlements, libraries, symbols) = conn.query_row(SCAN_COUNTS, [], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+
+    // SQLite database connection is automatically closed when it goes out of scope
+
+    Ok(ScanSummary {
+        services,
+        entitlements,
+        libraries,
+        symbols,
+        failed_files,
+        tool_failures: tool_failure_counts(),
+        tool_timings: tool_timings(),
+        elapsed: started.elapsed(),
+    })
+}
+
+// Fabricated services of the sample database: launchd jobs as their plist
+// would be parsed ("plist_path" and "plist"), or plain binaries ("identifier"),
+// along with what the analysis of their binary would have found.
+fn sample_services() -> JsonValue {
+    let lib_system = "/usr/lib/libSystem.B.dylib";
+    let foundation = "/System/Library/Frameworks/Foundation.framework/Versions/C/Foundation";
+
+    json!([
+        {
+            "plist_path": "/System/Library/LaunchDaemons/com.example.sampled.plist",
+            "plist": {
+                "Label": "com.example.sampled",
+                "Program": "/usr/libexec/sampled",
+                "RunAtLoad": true,
+                "KeepAlive": { "SuccessfulExit": false },
+                "MachServices": {
+                    "com.example.sampled.xpc": true,
+                    "com.example.sampled.port": { "HostSpecialPort": 29 },
+                },
+            },
+            "binary_type": BINARY_TYPE_MACHO,
+            "codesign_flags": "runtime",
+            "platform_identifier": 15,
+            "team_id": "not set",
+            "arch": "arm64e,x86_64",
+            "restrict_segment": false,
+            "min_os": "14.0",
+            "sdk": "15.0",
+            "entitlements": {
+                "com.apple.private.tcc.allow": ["kTCCServiceSystemPolicyAllFiles"],
+                "com.apple.security.cs.allow-unsigned-executable-memory": true,
+                "com.apple.security.application-groups": ["group.com.example.shared"],
+            },
+            "libraries": [lib_system, foundation],
+            "symbols": ["_dlopen", "_task_for_pid", "_xpc_connection_create_mach_service"],
+        },
+        {
+            "plist_path": "/System/Library/LaunchAgents/com.example.agent.plist",
+            "plist": {
+                "Label": "com.example.agent",
+                "ProgramArguments": ["/Applications/Example.app/Contents/MacOS/ExampleAgent", "--background"],
+                "RunAtLoad": true,
+                "LimitLoadToSessionType": ["Aqua", "LoginWindow"],
+                "EnvironmentVariables": { "DYLD_INSERT_LIBRARIES": "/tmp/libexample.dylib" },
+            },
+            "binary_type": BINARY_TYPE_MACHO,
+            "codesign_flags": "",
+            "team_id": "ABCDE12345",
+            "arch": "x86_64",
+            "min_os": "10.13",
+            "sdk": "13.0",
+            "writable_by_nonroot": true,
+            "bundle": ["/Applications/Example.app", "com.example.app"],
+            "entitlements": {
+                "com.apple.security.cs.disable-library-validation": true,
+            },
+            "libraries": [lib_system, "/Library/Frameworks/ExampleKit.framework/ExampleKit"],
+            "symbols": ["_AXIsProcessTrusted", "_CGEventTapCreate"],
+        },
+        {
+            "plist_path": "/System/Library/LaunchDaemons/com.example.script.plist",
+            "plist": {
+                "Label": "com.example.script",
+                "ProgramArguments": ["/usr/local/bin/example.sh"],
+                "UserName": "_example",
+                "GroupName": "_example",
+                "Disabled": true,
+            },
+            "binary_type": BINARY_TYPE_SCRIPT,
+            "interpreter": "/bin/sh",
+            "writable_by_nonroot": false,
+        },
+        {
+            "identifier": "com.example.tool",
+            "path": "/usr/bin/exampletool",
+            "binary_type": BINARY_TYPE_MACHO,
+            "codesign_flags": "library-validation,runtime",
+            "platform_identifier": 15,
+            "team_id": "not set",
+            "arch": "arm64e",
+            "restrict_segment": true,
+            "min_os": "15.0",
+            "sdk": "15.0",
+            "entitlements": {},
+            "libraries": [lib_system],
+            "symbols": ["_sandbox_init", "_open", "__ZNSt3__14coutE"],
+        },
+        {
+            "identifier": "com.example.legacy",
+            "path": "/usr/bin/legacytool",
+            "binary_type": BINARY_TYPE_MACHO,
+            "codesign_flags": "",
+            "team_id": "ABCDE12345",
+            "arch": "i386",
+            "is_32bit_only": true,
+            "min_os": "10.6",
+            "sdk": "10.13",
+            "entitlements": {},
+            "libraries": [lib_system],
+            "symbols": ["_system"],
+        },
+    ])
+}
+
+// Create a small database of fabricated services at "sqlite_filename", to try
+// the web server or test its pages without scanning anything: the system
+// isn't looked at and no external tool is run.
+// Returns the number of services saved.
+pub fn populate_sample_db(sqlite_filename: &str) -> Result<usize, DoraError> {
+    let creation_queries = read_sql_queries_from_file("creation_query.sql")?;
+
+    let mut conn = Connection::open(sqlite_filename)?;
+    conn.execute_batch(&creation_queries)?;
+
+    let tx = conn.transaction()?;
+    let os_version = OsVersion {
+        product_name: "macOS".to_string(),
+        product_version: "sample".to_string(),
+        build_version: "sample".to_string(),
+    };
+    save_scan_info(&os_version, &[], &[], None, &tx)?;
+
+    let services = sample_services();
+    let mut saved = 0;
+    for service in services.as_array().into_iter().flatten() {
+        let text = |key: &str| service[key].as_str();
+
+        let service_id = match text("identifier") {
+            Some(identifier) => {
+                let path = Path::new(text("path").unwrap_or_default());
+                match save_binary_service(identifier, path, KIND_BINARY, &tx)? {
+                    Some(service_id) => service_id,
+                    None => continue,
+                }
+            }
+            None => {
+                let plist = &service["plist"];
+                let service_id = save_service(text("plist_path").unwrap_or_default(), plist, &tx)?;
+                save_mach_services(service_id, plist, &tx)?;
+                save_environment_variables(service_id, plist, &tx)?;
+                service_id
+            }
+        };
+        saved += 1;
+
+        tx.execute(
+            UPDATE_SERVICE_BINARY_TYPE,
+            params![text("binary_type"), text("interpreter"), service_id],
+        )?;
+        if let Some(writable) = service["writable_by_nonroot"].as_bool() {
+            tx.execute(
+                UPDATE_SERVICE_WRITABLE_BY_NONROOT,
+                params![writable, service_id],
+            )?;
+        }
+        if let [JsonValue::String(bundle_path), JsonValue::String(bundle_id)] = service["bundle"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            tx.execute(
+                UPDATE_SERVICE_BUNDLE,
+                params![bundle_path, bundle_id, service_id],
+            )?;
+        }
+
+        // Scripts have no signature, architectures nor imports
+        if text("binary_type") != Some(BINARY_TYPE_MACHO) {
+            continue;
+        }
+
+        tx.execute(
+            UPDATE_SERVICE_SIGNING_INFO,
+            params![
+                text("codesign_flags"),
+                service["platform_identifier"].as_i64(),
+                text("team_id"),
+                service_id
+            ],
+        )?;
+        tx.execute(
+            UPDATE_SERVICE_ARCH,
+            params![
+                text("arch"),
+                service["is_32bit_only"].as_bool().unwrap_or(false),
+                service_id
+            ],
+        )?;
+        tx.execute(
+            UPDATE_SERVICE_BUILD_VERSION,
+            params![text("min_os"), text("sdk"), service_id],
+        )?;
+        let codesign_flags: Vec<String> = text("codesign_flags")
+            .unwrap_or_default()
+            .split(',')
+            .map(String::from)
+            .collect();
+        tx.execute(
+            UPDATE_SERVICE_RESTRICTED,
+            params![
+                is_restricted(
+                    Some(service["restrict_segment"].as_bool().unwrap_or(false)),
+                    Some(&codesign_flags)
+                ),
+                service_id
+            ],
+        )?;
+
+        let entitlements = &service["entitlements"];
+        save_service_entitlements(service_id, entitlements, &tx)?;
+        let entitlements_status = match entitlements.as_object() {
+            Some(entitlements) if !entitlements.is_empty() => ENTITLEMENTS_PRESENT,
+            _ => ENTITLEMENTS_NONE,
+        };
+        tx.execute(
+            UPDATE_SERVICE_ENTITLEMENTS_STATUS,
+            params![entitlements_status, service_id],
+        )?;
+
+        // Libraries are recorded as save_library would find them on a host
+        // where only the system ones are, in the dyld shared cache
+        let mut library_ids = Vec::new();
+        for library in service["libraries"].as_array().into_iter().flatten() {
+            let path = library.as_str().unwrap_or_default();
+            let name = path.split('/').next_back().unwrap_or(path);
+            let library_id =
+                insert_and_get_id("library", &["name", "path"], &[name, path], &["path"], &tx)?;
+            let in_shared_cache = path.starts_with("/usr/lib/") || path.starts_with("/System/");
+            tx.execute(
+                UPDATE_LIBRARY_SHARED_CACHE,
+                params![in_shared_cache as i32, 0, library_id],
+            )?;
+            if !in_shared_cache {
+                tx.execute(UPDATE_LIBRARY_WRITABLE_BY_NONROOT, params![1, library_id])?;
+            }
+            tx.execute(INSERT_LIBRARY, params![service_id, library_id])?;
+            library_ids.push(library_id);
+        }
+        // The first library of a sample service is libSystem, which the
+        // others import as real ones do
+        if let Some((lib_system_id, libraries)) = library_ids.split_first() {
+            for library_id in libraries {
+                tx.execute(
+                    INSERT_LIBRARY_DEPENDENCY,
+                    params![library_id, lib_system_id],
+                )?;
+            }
+        }
+
+        let symbols: Vec<String> = service["symbols"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|symbol| symbol.as_str().map(String::from))
+            .collect();
+        tx.execute(
+            UPDATE_SERVICE_DYNAMIC_LOADING,
+            params![
+                symbols
+                    .iter()
+                    .any(|symbol| DYNAMIC_LOADING_SYMBOLS.contains(&symbol.as_str())),
+                service_id
+            ],
+        )?;
+        save_service_imported_symbols(service_id, symbols, &tx)?;
+    }
+    tag_third_party_services(&tx)?;
+    save_risk_scores(&tx)?;
+    tx.commit()?;
+
+    Ok(saved)
+}
+
+//////////////////////////////////////////////////////////
+//////////////////////////////////////////////////////////
+//////////////////////////////////////////////////////////
+
+//////////////////////////////////////////////////////////
+//////// LOOK FOR SERVICES FROM SQLITE DATABASE //////////
+//////////////////////////////////////////////////////////
+
+// Re-analyze the binary of a single service in an existing database, e.g. after
+// it was patched or replaced: its entitlements, libraries and symbols are
+// deleted and extracted again. When a launchd job and a binary share the
+// label, both are refreshed. Everything happens in a transaction, so a
+// database error leaves the previous data in place.
+pub fn refresh_service(db: &str, label: &str) -> Result<(), DoraError> {
+    let mut conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+
+    let services: Vec<(i64, String)> = conn
+        .prepare(SERVICE_ID_AND_PATH_BY_LABEL)?
+        .query_map(params![label], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    if services.is_empty() {
+        return Err(DoraError::ServiceNotFound(label.to_string()));
+    }
+
+    // Libraries are looked for below the root the database was scanned from.
+    // Databases predating "scan_info.root" were taken as scans of the host.
+    let options = ScanOptions {
+        root: conn
+            .query_row(SCAN_ROOT, [], |row| row.get::<_, Option<String>>(0))
+            .optional()
+            .unwrap_or_default()
+            .flatten()
+            .map(PathBuf::from),
+        ..ScanOptions::default()
+    };
+
+    let tx = conn.transaction()?;
+    for (service_id, binary) in &services {
+        for delete in [
+            DELETE_SERVICE_ENTITLEMENTS,
+            DELETE_SERVICE_LIBRARIES,
+            DELETE_SERVICE_SYMBOLS,
+        ] {
+            tx.execute(delete, params![service_id])?;
+        }
+
+        process_and_save_macho_information(binary, *service_id, &options, &tx)?;
+    }
+    save_risk_scores(&tx)?;
+    tx.commit()?;
+
+    for (_, binary) in &services {
+        info!("Refreshed service {} ({})", label, binary);
+    }
+
+    Ok(())
+}
+
+// Write every service of a database to "output" as JSON Lines: one
+// self-contained object per service, with its Mach services, entitlements,
+// environment variables, libraries and symbols. Services are written one at a
+// time, so memory use doesn't grow with the database.
+// Returns the number of services written.
+pub fn export_jsonl(db: &str, output: &Path) -> Result<usize, DoraError> {
+    let conn = open_database(db)?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut exporter = ServiceExporter::new(&conn)?;
+
+    let mut services_stmt = conn.prepare(EXPORT_SERVICES)?;
+    let mut services = services_stmt.query([])?;
+    let mut count = 0;
+    while let Some(row) = services.next()? {
+        let service = exporter.service_json(row)?;
+
+        serde_json::to_writer(&mut writer, &service)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+
+    Ok(count)
+}
+
+// Get every detail of the services of each label, as "export_jsonl" writes
+// them, in a JSON object keyed by label. A label maps to the array of its
+// services (a launchd job and a binary may share it), or null when unknown.
+// All labels are looked up through the same connection and statements.
+pub fn get_services_json_by_labels(
+    db: &str,
+    labels: &[String],
+) -> Result<serde_json::Map<String, JsonValue>, DoraError> {
+    let conn = open_database(db)?;
+    let mut exporter = ServiceExporter::new(&conn)?;
+    let mut services_stmt = conn.prepare(EXPORT_SERVICES_BY_LABEL)?;
+
+    let mut result = serde_json::Map::new();
+    for label in labels {
+        let mut services = Vec::new();
+        let mut rows = services_stmt.query(params![label])?;
+        while let Some(row) = rows.next()? {
+            services.push(exporter.service_json(row)?);
+        }
+
+        let value = if services.is_empty() {
+            JsonValue::Null
+        } else {
+            JsonValue::Array(services)
+        };
+        result.insert(label.clone(), value);
+    }
+
+    Ok(result)
+}
+
+// Get a self-contained description of the services of a label, to attach to a
+// bug report or share a finding: every service as "export_jsonl" writes it,
+// along with where the database comes from.
+// Returns None when no service has the label.
+pub fn get_service_bundle(db: &str, label: &str) -> Result<Option<JsonValue>, DoraError> {
+    let services = match get_services_json_by_labels(db, &[label.to_string()])?.remove(label) {
+        Some(services @ JsonValue::Array(_)) => services,
+        _ => return Ok(None),
+    };
+
+    let conn = open_database(db)?;
+    let scan = get_scan_info(&conn).map(|info| {
+        json!({
+            "product_name": info.product_name,
+            "product_version": info.product_version,
+            "build_version": info.build_version,
+            "launch_paths": serde_json::from_str::<JsonValue>(&info.launch_paths).ok(),
+            "scan_dirs": serde_json::from_str::<JsonValue>(&info.scan_dirs).ok(),
+            "dora_version": info.dora_version,
+            "scanned_at": info.scanned_at,
+        })
+    });
+
+    Ok(Some(json!({
+        "label": label,
+        "database": db,
+        "scan": scan,
+        "exported_by": format!("dora {}", env!("CARGO_PKG_VERSION")),
+        "services": services,
+    })))
+}
+
+// Statements gathering the rows related to a service, prepared once to turn
+// many service rows into JSON
+struct ServiceExporter<'conn> {
+    mach_services: rusqlite::Statement<'conn>,
+    entitlements: rusqlite::Statement<'conn>,
+    env_vars: rusqlite::Statement<'conn>,
+    libraries: rusqlite::Statement<'conn>,
+    symbols: rusqlite::Statement<'conn>,
+}
+
+impl<'conn> ServiceExporter<'conn> {
+    fn new(conn: &'conn Connection) -> Result<Self, rusqlite::Error> {
+        Ok(ServiceExporter {
+            mach_services: conn.prepare(EXPORT_MACH_SERVICES)?,
+            entitlements: conn.prepare(EXPORT_ENTITLEMENTS)?,
+            env_vars: conn.prepare(EXPORT_ENV_VARS)?,
+            libraries: conn.prepare(EXPORT_LIBRARIES)?,
+            symbols: conn.prepare(EXPORT_SYMBOLS)?,
+        })
+    }
+
+    // Self-contained JSON object of a service row of EXPORT_SERVICES, with its
+    // Mach services, entitlements, environment variables, libraries and symbols
+    fn service_json(&mut self, row: &rusqlite::Row) -> Result<JsonValue, rusqlite::Error> {
+        let service_id: i64 = row.get(0)?;
+
+        let mach_services: serde_json::Map<String, JsonValue> = self
+            .mach_services
+            .query_map(params![service_id], |row| {
+                let value: String = row.get(1)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    json!({
+                        "value": serde_json::from_str::<JsonValue>(&value).unwrap_or(JsonValue::String(value)),
+                        "host_special_port": row.get::<_, Option<i64>>(2)?,
+                    }),
+                ))
+            })?
+            .collect::<Result<_, _>>()?;
+        let entitlements: serde_json::Map<String, JsonValue> = self
+            .entitlements
+            .query_map(params![service_id], |row| {
+                Ok((row.get::<_, String>(0)?, JsonValue::String(row.get(1)?)))
+            })?
+            .collect::<Result<_, _>>()?;
+        let env_vars: serde_json::Map<String, JsonValue> = self
+            .env_vars
+            .query_map(params![service_id], |row| {
+                Ok((row.get::<_, String>(0)?, JsonValue::String(row.get(1)?)))
+            })?
+            .collect::<Result<_, _>>()?;
+        let libraries: Vec<String> = self
+            .libraries
+            .query_map(params![service_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let symbols: Vec<(String, Option<String>)> = self
+            .symbols
+            .query_map(params![service_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        let demangled_symbols: serde_json::Map<String, JsonValue> = symbols
+            .iter()
+            .filter_map(|(name, demangled)| {
+                Some((name.clone(), JsonValue::String(demangled.clone()?)))
+            })
+            .collect();
+        let symbols: Vec<String> = symbols.into_iter().map(|(name, _)| name).collect();
+
+        Ok(json!({
+            "label": row.get::<_, String>(1)?,
+            "source": row.get::<_, String>(2)?,
+            "path": row.get::<_, String>(3)?,
+            "run_as_user": row.get::<_, Option<String>>(4)?,
+            "run_as_group": row.get::<_, Option<String>>(17)?,
+            "kind": row.get::<_, Option<String>>(18)?,
+            "arch": row.get::<_, Option<String>>(19)?,
+            "run_at_load": row.get::<_, Option<String>>(5)?,
+            "keep_alive": row.get::<_, Option<String>>(6)?,
+            "keep_alive_conditions": row
+                .get::<_, Option<String>>(7)?
+                .and_then(|conditions| serde_json::from_str::<JsonValue>(&conditions).ok()),
+            "plist_path": row.get::<_, Option<String>>(8)?,
+            "plist_target": row.get::<_, Option<String>>(20)?,
+            "program": row.get::<_, Option<String>>(21)?,
+            "uses_dynamic_loading": row.get::<_, Option<bool>>(22)?,
+            "team_id": row.get::<_, Option<String>>(23)?,
+            "bundle_path": row.get::<_, Option<String>>(24)?,
+            "bundle_id": row.get::<_, Option<String>>(25)?,
+            "entitlements_status": row.get::<_, Option<String>>(26)?,
+            "session_types": row.get::<_, Option<String>>(27)?.map(|session_types| {
+                session_types
+                    .split(',')
+                    .map(String::from)
+                    .collect::<Vec<String>>()
+            }),
+            "is_32bit_only": row.get::<_, Option<bool>>(28)?,
+            "third_party": row.get::<_, Option<bool>>(29)?,
+            "risk_score": row.get::<_, Option<i64>>(30)?,
+            "is_restricted": row.get::<_, Option<bool>>(32)?,
+            "risk_factors": row.get::<_, Option<String>>(31)?.map(|factors| {
+                factors
+                    .split(',')
+                    .filter(|factor| !factor.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<String>>()
+            }),
+            "disabled": row.get::<_, Option<String>>(9)?,
+            "writable_by_nonroot": row.get::<_, Option<bool>>(10)?,
+            "codesign_flags": row.get::<_, Option<String>>(11)?.map(|flags| {
+                flags
+                    .split(',')
+                    .filter(|flag| !flag.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<String>>()
+            }),
+            "platform_identifier": row.get::<_, Option<i64>>(12)?,
+            "binary_type": row.get::<_, Option<String>>(13)?,
+            "interpreter": row.get::<_, Option<String>>(14)?,
+            "min_os": row.get::<_, Option<String>>(15)?,
+            "sdk": row.get::<_, Option<String>>(16)?,
+            "mach_services": mach_services,
+            "entitlements": entitlements,
+            "environment_variables": env_vars,
+            "libraries": libraries,
+            "symbols": symbols,
+            "demangled_symbols": demangled_symbols,
+        }))
+    }
+}
+
+// Open a database for browsing.
+// Browsing never writes, so the file is opened read-only: no write locks are
+// taken and databases on read-only media can be browsed too. Only "set_review"
+// opens it for writing.
+pub fn open_database(db: &str) -> Result<Connection, rusqlite::Error> {
+    Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+// Build a "contains" LIKE pattern from a user-supplied search term.
+// Unless "wildcards" is set, "%" and "_" in the term match literally
+// (the queries declare "\" as their ESCAPE character).
+fn like_pattern(term: &str, wildcards: bool) -> String {
+    if wildcards {
+        return format!("%{}%", term);
+    }
+
+    let escaped: String = term
+        .chars()
+        .flat_map(|c| match c {
+            '%' | '_' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect();
+
+    format!("%{}%", escaped)
+}
+
+// Build a "contains" GLOB pattern from a user-supplied search term.
+// Unless "wildcards" is set, "*", "?" and "[" in the term match literally
+// by wrapping each of them in a bracket expression.
+fn glob_pattern(term: &str, wildcards: bool) -> String {
+    if wildcards {
+        return format!("*{}*", term);
+    }
+
+    let escaped: String = term
+        .chars()
+        .map(|c| match c {
+            '*' | '?' | '[' => format!("[{}]", c),
+            _ => c.to_string(),
+        })
+        .collect();
+
+    format!("*{}*", escaped)
+}
+
+// How a searched symbol name is matched against imported symbols
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolMatch {
+    // The whole name must match, instead of a substring
+    pub exact: bool,
+    // Honor "*", "?" and "[...]" (case-sensitive) or "%" and "_" (case-insensitive)
+    pub wildcards: bool,
+    // Match case like GLOB does; by default symbols are matched regardless of
+    // case, as entitlements and libraries are
+    pub case_sensitive: bool,
+}
+
+impl SymbolMatch {
+    // Pick the query among its [pattern, exact, pattern NOCASE, exact NOCASE]
+    // variants, with the value to bind to the symbol parameter
+    fn query<'a>(self, symbol: &str, variants: [&'a str; 4]) -> (&'a str, String) {
+        let [glob, exact, like, exact_nocase] = variants;
+        match (self.exact, self.case_sensitive) {
+            (true, true) => (exact, symbol.to_string()),
+            (true, false) => (exact_nocase, symbol.to_string()),
+            (false, true) => (glob, glob_pattern(symbol, self.wildcards)),
+            (false, false) => (like, like_pattern(symbol, self.wildcards)),
+        }
+    }
+}
+
+// Order of the services listed by the search and browse queries.
+// Labels and paths are compared regardless of case, so that "com.apple.foo"
+// and "com.Apple.bar" sit together; exact ties are then broken bytewise.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SortOrder {
+    #[default]
+    Label,
+    Path,
+    // By label, numbers compared by value: "foo2" comes before "foo10"
+    Natural,
+}
+
+impl SortOrder {
+    // Parse the "sort" parameter, defaulting to the label order
+    pub fn from_param(sort: Option<&str>) -> Self {
+        match sort {
+            Some("path") => SortOrder::Path,
+            Some("natural") => SortOrder::Natural,
+            _ => SortOrder::Label,
+        }
+    }
+
+    // Append the ORDER BY clause to one of the (label, path) service queries.
+    // SQLite can't sort naturally: those are sorted by label, then again by
+    // "arrange" once fetched.
+    fn sql(self, query: &str) -> String {
+        let order_by = match self {
+            SortOrder::Label | SortOrder::Natural => "s.label COLLATE NOCASE, s.label",
+            SortOrder::Path => "s.path COLLATE NOCASE, s.path, s.label COLLATE NOCASE, s.label",
+        };
+
+        format!("{} ORDER BY {}", query, order_by)
+    }
+
+    // Finish ordering (label, path) services fetched with the query of "sql"
+    fn arrange(self, services: &mut [(String, String)]) {
+        if let SortOrder::Natural = self {
+            services.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+        }
+    }
+}
+
+// Page of a browse list, from the "page" (from 1) and "per_page" parameters:
+// a whole database would otherwise be thousands of rows in one page.
+// "url" links to the list with all its parameters but "page".
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub page: usize,
+    pub per_page: usize,
+    pub url: String,
+}
+
+impl Pagination {
+    // Parse the "page" and "per_page" parameters, defaulting to the first
+    // page of DEFAULT_PER_PAGE services
+    pub fn from_params(page: Option<&str>, per_page: Option<&str>, url: String) -> Self {
+        let page = page
+            .and_then(|page| page.parse().ok())
+            .filter(|&page| page > 0)
+            .unwrap_or(1);
+        let per_page = per_page
+            .and_then(|per_page| per_page.parse::<usize>().ok())
+            .filter(|&per_page| per_page > 0)
+            .map_or(DEFAULT_PER_PAGE, |per_page| per_page.min(MAX_PER_PAGE));
+
+        Pagination {
+            page,
+            per_page,
+            url,
+        }
+    }
+
+    // Items of the current page, none past the last one
+    fn slice<'a, T>(&self, items: &'a [T]) -> &'a [T] {
+        let start = (self.page - 1)
+            .saturating_mul(self.per_page)
+            .min(items.len());
+        let end = start.saturating_add(self.per_page).min(items.len());
+        &items[start..end]
+    }
+
+    // "Services 101-200 of 1234, page 2 of 13" between links to the previous
+    // and next pages. Lists fitting in a single page have none.
+    fn controls(&self, total: usize) -> String {
+        if total <= self.per_page && self.page == 1 {
+            return String::new();
+        }
+
+        let pages = total.div_ceil(self.per_page);
+        let link = |page: usize, text: &str| {
+            format!(
+                "<a href=\"{}\">{text}</a>",
+                html_escape(&format!("{}&page={page}", self.url))
+            )
+        };
+        let previous = match self.page {
+            1 => "&laquo; Previous".to_string(),
+            page => link((page - 1).min(pages), "&laquo; Previous"),
+        };
+        let next = if self.page < pages {
+            link(self.page + 1, "Next &raquo;")
+        } else {
+            "Next &raquo;".to_string()
+        };
+        let offset = (self.page - 1).saturating_mul(self.per_page);
+        let shown = total.saturating_sub(offset).min(self.per_page);
+        let position = if shown == 0 {
+            format!("No services on page {} of {pages}", self.page)
+        } else {
+            format!(
+                "Services {}-{} of {total}, page {} of {pages}",
+                offset + 1,
+                offset + shown,
+                self.page
+            )
+        };
+
+        format!("<p class=\"pagination\">{previous} | {position} | {next}</p>")
+    }
+}
+
+// Compare two strings regardless of case, runs of digits by their value
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (a_number, b_number) = (number(&mut a_chars), number(&mut b_chars));
+                a_number
+                    .len()
+                    .cmp(&b_number.len())
+                    .then_with(|| a_number.cmp(&b_number))
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+                a_chars.next();
+                b_chars.next();
+                ordering
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Characters percent-encoded in query parameter values: all but the unreserved ones
+pub const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// Link to the service page of a service.
+// Labels may contain spaces, "&" and the like: both parameters are percent-encoded,
+// and decoded back by the "service" handler's query extractor.
+fn service_url(db: &str, label: &str) -> String {
+    format!(
+        "/service?db={}&label={}",
+        utf8_percent_encode(db, QUERY_VALUE),
+        utf8_percent_encode(label, QUERY_VALUE)
+    )
+}
+
+// Render services as a table whose columns can be sorted client side.
+// Occurrences of the searched "term" are marked in the labels and paths, and
+// passed on to the service pages to be marked in their details.
+// Services given a review status are tagged with it.
+fn services_table(db: &str, services: &[(String, String)], term: Option<&str>) -> String {
+    let reviews = review_statuses(db);
+    let rows: String = services
+        .iter()
+        .map(|(label, path)| {
+            let mut url = service_url(db, label);
+            if let Some(term) = term.filter(|term| !term.is_empty()) {
+                url.push_str(&format!(
+                    "&highlight={}",
+                    utf8_percent_encode(term, QUERY_VALUE)
+                ));
+            }
+            format!(
+                "<tr><td><a href=\"{}\">{}</a>{}</td><td>{}</td></tr>",
+                html_escape(&url),
+                highlight(label, term),
+                reviews
+                    .get(label)
+                    .map_or(String::new(), |status| review_badge(status)),
+                highlight(path, term)
+            )
+        })
+        .collect();
+
+    format!(
+        "<table class=\"sortable\"><thead><tr><th>Label</th><th>Path</th></tr></thead><tbody>{rows}</tbody></table>"
+    )
+}
+
+// Render the current page of services as a table, between the controls
+// moving to the other pages
+fn paginated_services_table(
+    db: &str,
+    services: &[(String, String)],
+    pagination: &Pagination,
+) -> String {
+    let controls = pagination.controls(services.len());
+    format!(
+        "{controls}{}{controls}",
+        services_table(db, pagination.slice(services), None)
+    )
+}
+
+// Render the current page of "rows" as a table below the "head" column
+// headers, between the controls moving to the other pages
+fn paginated_table(head: &str, rows: &[String], pagination: &Pagination) -> String {
+    let controls = pagination.controls(rows.len());
+    format!(
+        "{controls}<table class=\"sortable\"><thead><tr>{head}</tr></thead><tbody>{}</tbody></table>{controls}",
+        pagination.slice(rows).concat()
+    )
+}
+
+// Render the current page of grouped services, each group as a table under
+// its heading and count, between the controls moving to the other pages.
+// "groups" are in display order; a group split across pages shows its part.
+fn paginated_grouped_services(
+    db: &str,
+    groups: &[(String, Vec<(String, String)>)],
+    pagination: &Pagination,
+) -> String {
+    let services: Vec<(usize, &(String, String))> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, services))| services.iter().map(move |service| (i, service)))
+        .collect();
+
+    let controls = pagination.controls(services.len());
+    let mut html = controls.clone();
+    for page in pagination.slice(&services).chunk_by(|a, b| a.0 == b.0) {
+        let (title, group) = &groups[page[0].0];
+        let page: Vec<(String, String)> =
+            page.iter().map(|(_, service)| (*service).clone()).collect();
+        html.push_str(&format!(
+            "<h3>{} ({})</h3>{}",
+            html_escape(title),
+            group.len(),
+            services_table(db, &page, None)
+        ));
+    }
+    html.push_str(&controls);
+
+    html
+}
+
+// Review status of each reviewed label. Databases predating the review table
+// have none.
+fn review_statuses(db: &str) -> HashMap<String, String> {
+    let statuses = || -> Result<HashMap<String, String>, rusqlite::Error> {
+        let conn = open_database(db)?;
+        let mut stmt = conn.prepare(REVIEW_STATUSES_BY_LABEL)?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    };
+    statuses().unwrap_or_default()
+}
+
+// Small colored tag showing a review status next to a label
+pub fn review_badge(status: &str) -> String {
+    let color = if status == "flagged" {
+        "#f8d7da"
+    } else {
+        "#d4edda"
+    };
+    format!(
+        " <span style=\"background: {color}; border-radius: 3px; padding: 0 0.3em\">{}</span>",
+        html_escape(status)
+    )
+}
+
+// Review of a service: its status, note and when it was last changed
+pub fn get_review_by_label(
+    conn: &rusqlite::Connection,
+    label: &str,
+    source: &str,
+) -> Option<(String, Option<String>, String)> {
+    conn.query_row(REVIEW_BY_LABEL, params![label, source], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })
+    .ok()
+}
+
+// Set the review status and note of a service, or forget its review when
+// "status" is None. This is the only write the web server makes.
+pub fn set_review(
+    db: &str,
+    label: &str,
+    source: &str,
+    status: Option<&str>,
+    note: &str,
+) -> Result<(), DoraError> {
+    // Never create a database: only existing ones can be reviewed
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+
+    let exists: bool = conn.query_row(SERVICE_EXISTS, params![label, source], |row| row.get(0))?;
+    if !exists {
+        return Err(DoraError::ServiceNotFound(label.to_string()));
+    }
+
+    match status {
+        Some(status) => {
+            let note = (!note.is_empty()).then_some(note);
+            conn.execute(UPSERT_REVIEW, params![label, source, status, note])?;
+        }
+        None => {
+            conn.execute(DELETE_REVIEW, params![label, source])?;
+        }
+    }
+
+    Ok(())
+}
+
+// Services found by a search, gathered before being rendered as HTML or JSON
+pub struct SearchResults {
+    // What the services match, e.g. "symbol: fork"
+    pub criteria: String,
+    // (label, path) of the services listed
+    pub services: Vec<(String, String)>,
+    // Number of services found, more than those listed when only the best
+    // ones were kept
+    pub total: usize,
+    // Searched term, marked in the rendered results
+    pub term: Option<String>,
+}
+
+impl SearchResults {
+    fn new(criteria: String, services: Vec<(String, String)>, term: Option<&str>) -> Self {
+        SearchResults {
+            criteria,
+            total: services.len(),
+            services,
+            term: term.map(String::from),
+        }
+    }
 
-    let mut stmt = conn.prepare(SERVICES_BY_SYMBOL)?;
-    let result_set = stmt.query_map(params![format!("*{}*", symbol)], |row| {
+    // Heading and table of the services found in "db"
+    pub fn html(&self, db: &str) -> String {
+        if self.services.is_empty() {
+            return format!(
+                "<p>No services found with {}</p>",
+                html_escape(&self.criteria)
+            );
+        }
+
+        format!(
+            "<h2>Found {} services with {}{}</h2>{}",
+            self.total,
+            html_escape(&self.criteria),
+            if self.total > self.services.len() {
+                format!(" (best {} shown)", self.services.len())
+            } else {
+                String::new()
+            },
+            services_table(db, &self.services, self.term.as_deref())
+        )
+    }
+
+    pub fn json(&self, db: &str) -> JsonValue {
+        json!({
+            "db": db,
+            "criteria": self.criteria,
+            "total": self.total,
+            "services": self
+                .services
+                .iter()
+                .map(|(label, path)| json!({ "label": label, "path": path }))
+                .collect::<Vec<JsonValue>>(),
+        })
+    }
+}
+
+// Run one of the (label, path) service queries, ordered by "sort". Rows that
+// can't be read are logged and skipped.
+fn query_services<P: rusqlite::Params>(
+    conn: &Connection,
+    sql: &str,
+    sort: SortOrder,
+    params: P,
+) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&sort.sql(sql))?;
+    let result_set = stmt.query_map(params, |row| {
         Ok((
             row.get::<_, String>(0)?, // label
             row.get::<_, String>(1)?, // path
         ))
     })?;
+
     let mut services = Vec::new();
     for service in result_set {
         match service {
             Ok((label, path)) => {
-                services.push(format!(
-                    "<li><strong>Label:</strong> <a href=\"/service?db={db}&label={label}\">{label}</a> ({path})<br>"
-                ));
+                services.push((label, path));
             }
             Err(e) => {
-                eprintln!("Error retrieving service by symbol: {}", e);
+                eprintln!("Error retrieving services: {}", e);
             }
         }
     }
+    sort.arrange(&mut services);
+
+    Ok(services)
+}
+
+// Get services from SQLite database that have a sepcified entitlement AND
+// a specified symbol
+pub fn get_services_by_entitlement_and_symbol(
+    db: &str,
+    entitlement: &str,
+    symbol: &str,
+    symbol_match: SymbolMatch,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let (sql, symbol_param) = symbol_match.query(
+        symbol,
+        [
+            SERVICES_BY_ENTITLEMENT_AND_SYMBOL,
+            SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT,
+            SERVICES_BY_ENTITLEMENT_AND_SYMBOL_NOCASE,
+            SERVICES_BY_ENTITLEMENT_AND_SYMBOL_EXACT_NOCASE,
+        ],
+    );
+    let services = query_services(
+        &conn,
+        sql,
+        sort,
+        params![
+            like_pattern(entitlement, symbol_match.wildcards),
+            symbol_param
+        ],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("entitlement: {entitlement} and symbol: {symbol}"),
+        services,
+        Some(symbol),
+    ))
+}
+
+// Get services from SQLite database that import a specified symbol AND
+// link a specified library
+pub fn get_services_by_symbol_and_library(
+    db: &str,
+    symbol: &str,
+    library: &str,
+    symbol_match: SymbolMatch,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let (sql, symbol_param) = symbol_match.query(
+        symbol,
+        [
+            SERVICES_BY_SYMBOL_AND_LIBRARY,
+            SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT,
+            SERVICES_BY_SYMBOL_AND_LIBRARY_NOCASE,
+            SERVICES_BY_SYMBOL_AND_LIBRARY_EXACT_NOCASE,
+        ],
+    );
+    let services = query_services(
+        &conn,
+        sql,
+        sort,
+        params![like_pattern(library, symbol_match.wildcards), symbol_param],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("symbol: {symbol} and library: {library}"),
+        services,
+        Some(symbol),
+    ))
+}
+
+// Get all services from SQLite database having a specific symbol.
+pub fn get_services_by_symbol(
+    db: &str,
+    symbol: &str,
+    symbol_match: SymbolMatch,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let (sql, symbol_param) = symbol_match.query(
+        symbol,
+        [
+            SERVICES_BY_SYMBOL,
+            SERVICES_BY_SYMBOL_EXACT,
+            SERVICES_BY_SYMBOL_NOCASE,
+            SERVICES_BY_SYMBOL_EXACT_NOCASE,
+        ],
+    );
+    let services = query_services(&conn, sql, sort, params![symbol_param])?;
+
+    Ok(SearchResults::new(
+        format!("symbol: {symbol}"),
+        services,
+        Some(symbol),
+    ))
+}
+
+// Get all services from SQLite database importing a specific library.
+pub fn get_services_by_library(
+    db: &str,
+    library: &str,
+    wildcards: bool,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_LIBRARY,
+        sort,
+        params![like_pattern(library, wildcards)],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("library: {library}"),
+        services,
+        Some(library),
+    ))
+}
+
+pub fn get_services_by_entitlement(
+    db: &str,
+    entitlement: &str,
+    wildcards: bool,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_ENTITLEMENT,
+        sort,
+        params![like_pattern(entitlement, wildcards)],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("entitlement: {entitlement}"),
+        services,
+        Some(entitlement),
+    ))
+}
+
+// Get all services from SQLite database holding an entitlement whose value
+// contains "value", e.g. an application group among
+// "com.apple.security.application-groups".
+// "entitlement" restricts the entitlements searched when not empty.
+pub fn get_services_by_entitlement_value(
+    db: &str,
+    entitlement: &str,
+    value: &str,
+    wildcards: bool,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_ENTITLEMENT_VALUE,
+        sort,
+        params![
+            like_pattern(entitlement, wildcards),
+            like_pattern(value, wildcards)
+        ],
+    )?;
+
+    let criteria = if entitlement.is_empty() {
+        format!("an entitlement value containing: {}", value)
+    } else {
+        format!("entitlement {} containing: {}", entitlement, value)
+    };
+    Ok(SearchResults::new(criteria, services, Some(value)))
+}
+
+// Get service from SQLite database by label case insensitive.
+pub fn get_services_by_label_pattern(
+    db: &str,
+    label_pattern: &str,
+    wildcards: bool,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_LABEL_PATTERN,
+        sort,
+        params![glob_pattern(label_pattern, wildcards)],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("label pattern: {label_pattern}"),
+        services,
+        Some(label_pattern),
+    ))
+}
+
+// Get the services whose label fuzzily matches "query": its characters appear
+// in the label in order, not necessarily next to each other. The closest
+// labels (consecutive characters, word starts, ...) come first, and only the
+// best FUZZY_MAX_RESULTS are kept.
+pub fn get_services_by_label_fuzzy(
+    db: &str,
+    query: &str,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut ranked: Vec<(i64, String, String)> =
+        query_services(&conn, SERVICE_LABELS_AND_PATHS, SortOrder::Label, [])?
+            .into_iter()
+            .filter_map(|(label, path)| {
+                matcher
+                    .fuzzy_match(&label, query)
+                    .map(|score| (score, label, path))
+            })
+            .collect();
+
+    ranked.sort_by(|(a_score, a_label, _), (b_score, b_label, _)| {
+        b_score.cmp(a_score).then_with(|| a_label.cmp(b_label))
+    });
+    let total = ranked.len();
+    let services: Vec<(String, String)> = ranked
+        .into_iter()
+        .take(FUZZY_MAX_RESULTS)
+        .map(|(_, label, path)| (label, path))
+        .collect();
+
+    // The characters of a fuzzy query are scattered in the labels: nothing to mark
+    Ok(SearchResults {
+        total,
+        ..SearchResults::new(format!("a label like: {query}"), services, None)
+    })
+}
+
+// Get services from SQLite database whose binary path matches a pattern.
+pub fn get_services_by_path_pattern(
+    db: &str,
+    path_pattern: &str,
+    wildcards: bool,
+    sort: SortOrder,
+) -> Result<SearchResults, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_PATH_PATTERN,
+        sort,
+        params![glob_pattern(path_pattern, wildcards)],
+    )?;
+
+    Ok(SearchResults::new(
+        format!("path pattern: {path_pattern}"),
+        services,
+        Some(path_pattern),
+    ))
+}
+
+// Filters of the "/services" browse route. Unset filters match every service.
+#[derive(Debug, Default)]
+pub struct ServiceFilters {
+    pub disabled: Option<bool>,
+    pub run_at_load: Option<bool>,
+    pub keep_alive: Option<bool>,
+    // Whether the service sets any "DYLD_*" environment variable
+    pub dyld_env: Option<bool>,
+    // Whether the binary has an arm64 slice (arm64, arm64e, ...); services
+    // whose architectures are unknown only match when unset
+    pub arm64: Option<bool>,
+    // A session type the job is limited to (e.g. "LoginWindow"), regardless
+    // of case; services loaded in any session only match when unset
+    pub session_type: Option<String>,
+    // Whether the service is third-party software (see THIRD_PARTY_FOLDERS)
+    pub third_party: Option<bool>,
+}
+
+// Get all services from SQLite database matching the given filters
+pub fn get_services(
+    db: &str,
+    filters: &ServiceFilters,
+    sort: SortOrder,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let services = list_services(db, filters, sort)?;
     if services.is_empty() {
-        return Ok(vec![format!(
-            "<p>No services found with symbol: {}</p>",
-            symbol
-        )]);
+        return Ok("<p>No services found.</p>".to_string());
     }
+
     let mut html = String::new();
-    html.push_str(
-        format!(
-            "<h2>Found {} services with symbol: {}</h2>",
-            services.len(),
-            symbol
-        )
-        .as_str(),
+    html.push_str(format!("<h2>Found {} services</h2>", services.len()).as_str());
+    html.push_str(&paginated_services_table(db, &services, pagination));
+
+    Ok(html)
+}
+
+// Get the (label, path) of all services matching the given filters
+pub fn list_services(
+    db: &str,
+    filters: &ServiceFilters,
+    sort: SortOrder,
+) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    query_services(
+        &conn,
+        SERVICES_BY_FILTERS,
+        sort,
+        params![
+            filters.disabled.map(|d| d as i32),
+            filters.run_at_load.map(|r| r as i32),
+            filters.keep_alive.map(|k| k as i32),
+            filters.dyld_env.map(|d| d as i32),
+            filters.arm64.map(|a| a as i32),
+            filters.session_type,
+            filters.third_party.map(|t| t as i32),
+        ],
+    )
+}
+
+// Get services whose code signature has (or lacks, unless "present") a flag,
+// e.g. those without "library-validation" that may load unsigned libraries
+pub fn get_services_by_codesign_flag(
+    db: &str,
+    flag: &str,
+    present: bool,
+    sort: SortOrder,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_CODESIGN_FLAG,
+        sort,
+        params![flag, present],
+    )?;
+
+    let description = format!(
+        "{} the {} flag",
+        if present { "with" } else { "without" },
+        html_escape(flag)
     );
-    for service in services {
-        html.push_str(&service);
+    if services.is_empty() {
+        return Ok(format!("<p>No services found {}</p>", description));
     }
 
-    Ok(vec![html])
+    Ok(format!(
+        "<h2>Found {} services {}</h2>{}",
+        services.len(),
+        description,
+        paginated_services_table(db, &services, pagination)
+    ))
 }
 
-// Get all services from SQLite database importing a specific library.
-// Handle multiple services retrieved by library.
-pub fn get_services_by_library(db: &String, library: &str) -> Result<Vec<String>, rusqlite::Error> {
-    let conn = match rusqlite::Connection::open(db) {
-        Ok(conn) => conn,
-        Err(e) => return Err(e),
+// Who signed the binary of a service
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningOrigin {
+    // Apple platform binaries: no team, a platform identifier
+    Apple,
+    // Binaries signed with a developer's team identifier
+    ThirdParty,
+}
+
+impl SigningOrigin {
+    // Parse the "origin" parameter
+    pub fn from_param(origin: &str) -> Option<Self> {
+        match origin {
+            "apple" => Some(SigningOrigin::Apple),
+            "thirdparty" => Some(SigningOrigin::ThirdParty),
+            _ => None,
+        }
+    }
+}
+
+// Get services by who signed their binary: Apple, which tells the baseline OS
+// apart, or a third party, i.e. anything added to it
+pub fn get_services_by_signing_origin(
+    db: &str,
+    origin: SigningOrigin,
+    sort: SortOrder,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services = query_services(
+        &conn,
+        SERVICES_BY_SIGNING_ORIGIN,
+        sort,
+        params![origin == SigningOrigin::Apple],
+    )?;
+
+    let description = match origin {
+        SigningOrigin::Apple => "signed by Apple as platform binaries",
+        SigningOrigin::ThirdParty => "signed by a third-party team",
     };
+    if services.is_empty() {
+        return Ok(format!("<p>No services found {}</p>", description));
+    }
 
-    let mut stmt = conn.prepare(SERVICES_BY_LIBRARY)?;
-    let result_set = stmt.query_map(params![format!("%{}%", library)], |row| {
+    Ok(format!(
+        "<h2>Found {} services {}</h2>{}",
+        services.len(),
+        description,
+        paginated_services_table(db, &services, pagination)
+    ))
+}
+
+// Get services whose binary, or one of its parent directories, is writable
+// by a non-root user. Root services come first as they are the riskiest.
+pub fn get_writable_services(db: &str, pagination: &Pagination) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(WRITABLE_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
         Ok((
-            row.get::<_, String>(0)?, // label
-            row.get::<_, String>(1)?, // path
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
         ))
     })?;
-    let mut services = Vec::new();
+
+    let mut root_services = Vec::new();
+    let mut other_services = Vec::new();
     for service in result_set {
         match service {
-            Ok((label, path)) => {
-                services.push(format!(
-                    "<li><strong>Label:</strong> <a href=\"/service?db={db}&label={label}\">{label}</a> ({path})<br>"
+            Ok((label, path, run_as_user)) if run_as_user == "root" => {
+                root_services.push((label, path))
+            }
+            Ok((label, path, _)) => other_services.push((label, path)),
+            Err(e) => {
+                eprintln!("Error retrieving writable services: {}", e);
+            }
+        }
+    }
+
+    let total = root_services.len() + other_services.len();
+    if total == 0 {
+        return Ok(
+            "<p>No services with a binary writable by non-root users found.</p>".to_string(),
+        );
+    }
+
+    let groups = [
+        ("Running as root".to_string(), root_services),
+        ("Running as user".to_string(), other_services),
+    ];
+    Ok(format!(
+        "<h2>Found {} services with a binary writable by non-root users</h2>{}",
+        total,
+        paginated_grouped_services(db, &groups, pagination)
+    ))
+}
+
+// Get the XPC services found in bundles, linked to the service of their
+// executable when it was analyzed
+pub fn get_xpc_services(db: &str, pagination: &Pagination) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(XPC_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                                // bundle_id
+            row.get::<_, String>(1).unwrap_or(String::from("NULL")), // bundle_path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // executable
+            row.get::<_, String>(3).unwrap_or(String::from("NULL")), // container
+            row.get::<_, String>(4).unwrap_or(String::from("NULL")), // service_type
+            row.get::<_, Option<String>>(5).unwrap_or(None),         // label
+        ))
+    })?;
+
+    let mut rows = Vec::new();
+    for xpc_service in result_set {
+        match xpc_service {
+            Ok((bundle_id, bundle_path, executable, container, service_type, label)) => {
+                let executable = match label {
+                    Some(label) => format!(
+                        "<a href=\"{}\">{}</a>",
+                        html_escape(&service_url(db, &label)),
+                        html_escape(&executable)
+                    ),
+                    None => html_escape(&executable),
+                };
+                rows.push(format!(
+                    "<tr><td>{}</td><td>{}</td><td>{executable}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&bundle_id),
+                    html_escape(&service_type),
+                    html_escape(&container),
+                    html_escape(&bundle_path)
                 ));
             }
             Err(e) => {
-                eprintln!("Error retrieving service by library: {}", e);
+                eprintln!("Error retrieving XPC services: {}", e);
             }
         }
     }
-    if services.is_empty() {
-        return Ok(vec![format!(
-            "<p>No services found with library: {}</p>",
-            library
-        )]);
+
+    if rows.is_empty() {
+        return Ok("<p>No XPC services found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} XPC services</h2>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Bundle identifier</th><th>Service type</th><th>Executable</th><th>Container</th><th>Bundle</th>",
+            &rows,
+            pagination
+        )
+    ))
+}
+
+// Get the libraries imported by services that are neither on disk nor in the
+// dyld shared cache, with the number of services importing each
+pub fn get_phantom_libraries(db: &str) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(PHANTOM_LIBRARIES)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                      // path
+            row.get::<_, Option<bool>>(1).unwrap_or(None), // writable_by_nonroot
+            row.get::<_, i64>(2)?,                         // services
+        ))
+    })?;
+
+    let mut rows = String::new();
+    let mut total = 0;
+    for library in result_set {
+        match library {
+            Ok((path, writable, services)) => {
+                total += 1;
+                // Library searches match names
+                let name = path.rsplit('/').next().unwrap_or(&path);
+                let url = format!(
+                    "/query?db={}&library={}",
+                    utf8_percent_encode(db, QUERY_VALUE),
+                    utf8_percent_encode(name, QUERY_VALUE)
+                );
+                rows.push_str(&format!(
+                    "<tr><td>{}</td><td><a href=\"{}\">{services}</a></td><td>{}</td></tr>",
+                    html_escape(&path),
+                    html_escape(&url),
+                    writable.map_or("NULL", |writable| if writable { "yes" } else { "no" })
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error retrieving phantom libraries: {}", e);
+            }
+        }
+    }
+
+    if total == 0 {
+        return Ok("<p>No phantom libraries found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} libraries neither on disk nor in the dyld shared cache</h2>\
+         <table class=\"sortable\"><thead><tr><th>Path</th><th>Services</th><th>Plantable by non-root</th></tr></thead><tbody>{rows}</tbody></table>",
+        total
+    ))
+}
+
+pub fn get_dynamic_loaders(db: &str, pagination: &Pagination) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(DYNAMIC_LOADERS)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
+            row.get::<_, Option<String>>(3).unwrap_or(None),         // codesign_flags
+        ))
+    })?;
+
+    let mut rows = Vec::new();
+    for service in result_set {
+        match service {
+            Ok((label, path, run_as_user, codesign_flags)) => {
+                let library_validation = codesign_flags.map_or("NULL", |flags| {
+                    if flags.split(',').any(|flag| flag == "library-validation") {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                });
+                rows.push(format!(
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{library_validation}</td></tr>",
+                    html_escape(&service_url(db, &label)),
+                    html_escape(&label),
+                    html_escape(&path),
+                    html_escape(&run_as_user)
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error retrieving dynamic loaders: {}", e);
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return Ok("<p>No services loading code at runtime found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} services loading code at runtime</h2>\
+         <p>These binaries import dlopen, dlsym or NSAddImage.</p>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Label</th><th>Path</th><th>Run as user</th><th>Library validation</th>",
+            &rows,
+            pagination
+        )
+    ))
+}
+
+// Get services whose binary has no 64-bit slice. Current macOS can't run
+// them: on an older image scanned with "--root", they stand out as leftovers.
+pub fn get_thirty_two_bit_services(
+    db: &str,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(THIRTY_TWO_BIT_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
+            row.get::<_, String>(3).unwrap_or(String::from("NULL")), // arch
+        ))
+    })?;
+
+    let mut rows = Vec::new();
+    for service in result_set {
+        match service {
+            Ok((label, path, run_as_user, arch)) => {
+                rows.push(format!(
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&service_url(db, &label)),
+                    html_escape(&label),
+                    html_escape(&path),
+                    html_escape(&run_as_user),
+                    html_escape(&arch)
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error retrieving 32-bit services: {}", e);
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return Ok("<p>No 32-bit only services found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} 32-bit only services</h2>\
+         <p>These binaries have no 64-bit slice, which macOS can't run since Catalina.</p>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Label</th><th>Path</th><th>Run as user</th><th>Architectures</th>",
+            &rows,
+            pagination
+        )
+    ))
+}
+
+// Get the services running as root whose binary isn't restricted: dyld loads
+// the libraries of their DYLD_INSERT_LIBRARIES, which makes them candidates
+// for injection
+pub fn get_unrestricted_root_services(
+    db: &str,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(UNRESTRICTED_ROOT_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // codesign_flags
+        ))
+    })?;
+
+    let mut rows = Vec::new();
+    for service in result_set {
+        match service {
+            Ok((label, path, codesign_flags)) => {
+                rows.push(format!(
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&service_url(db, &label)),
+                    html_escape(&label),
+                    html_escape(&path),
+                    html_escape(&codesign_flags.replace(',', ", "))
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error retrieving unrestricted root services: {}", e);
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return Ok("<p>No unrestricted root services found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} unrestricted root services</h2>\
+         <p>These binaries have neither a __RESTRICT segment nor the restrict code signing flag: dyld doesn't ignore their DYLD_INSERT_LIBRARIES. The hardened runtime (runtime flag) still does, unless an entitlement allows DYLD environment variables.</p>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Label</th><th>Path</th><th>Code signing flags</th>",
+            &rows,
+            pagination
+        )
+    ))
+}
+
+// Get the services with a risk score, highest first, with the factors adding
+// up to it
+pub fn get_top_risk_services(db: &str, pagination: &Pagination) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(TOP_RISK_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
+            row.get::<_, i64>(3)?,                                   // risk_score
+            row.get::<_, String>(4).unwrap_or_default(),             // risk_factors
+        ))
+    })?;
+
+    let mut rows = Vec::new();
+    for service in result_set {
+        match service {
+            Ok((label, path, run_as_user, score, factors)) => {
+                rows.push(format!(
+                    "<tr><td>{score}</td><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&service_url(db, &label)),
+                    html_escape(&label),
+                    html_escape(&path),
+                    html_escape(&run_as_user),
+                    html_escape(&factors.replace(',', ", "))
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error retrieving risk scores: {}", e);
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return Ok("<p>No services with a risk score found.</p>".to_string());
     }
-    let mut html = String::new();
-    html.push_str(
-        format!(
-            "<h2>Found {} services with library: {}</h2>",
-            services.len(),
-            library
+
+    Ok(format!(
+        "<h2>Found {} services with a risk score</h2>\
+         <p>Each factor adds its weight to the score: writable_binary and hijackable_library 3, root and powerful_entitlements 2, run_at_load, keep_alive and dangerous_symbols 1.</p>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Score</th><th>Label</th><th>Path</th><th>Run as user</th><th>Factors</th>",
+            &rows,
+            pagination
         )
-        .as_str(),
-    );
-    for service in services {
-        html.push_str(&service);
-    }
-    Ok(vec![html])
+    ))
 }
 
-pub fn get_services_by_entitlement(
-    db: &String,
-    entitlement: &str,
-) -> Result<Vec<String>, rusqlite::Error> {
-    let conn = match rusqlite::Connection::open(db) {
-        Ok(conn) => conn,
-        Err(e) => return Err(e),
-    };
+// Get services launching a script instead of a Mach-O binary, with their
+// interpreter. Root services come first, and among them scripts a non-root
+// user could replace.
+pub fn get_script_services(db: &str, pagination: &Pagination) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
 
-    let mut stmt = conn.prepare(SERVICES_BY_ENTITLEMENT)?;
-    let result_set = stmt.query_map(params![format!("%{}%", entitlement)], |row| {
+    let mut stmt = conn.prepare(SCRIPT_SERVICES)?;
+    let result_set = stmt.query_map([], |row| {
         Ok((
-            row.get::<_, String>(0)?, // label
-            row.get::<_, String>(1)?, // path
+            row.get::<_, String>(0)?,                                // label
+            row.get::<_, String>(1)?,                                // path
+            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
+            row.get::<_, String>(3).unwrap_or(String::from("NULL")), // interpreter
+            row.get::<_, Option<bool>>(4).unwrap_or(None),           // writable_by_nonroot
         ))
     })?;
-    let mut services = Vec::new();
+
+    let mut rows = Vec::new();
     for service in result_set {
         match service {
-            Ok((label, path)) => {
-                services.push(format!(
-                    "<li><strong>Label:</strong> <a href=\"/service?db={db}&label={label}\">{label}</a> ({path})<br>"
+            Ok((label, path, run_as_user, interpreter, writable)) => {
+                rows.push(format!(
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&service_url(db, &label)),
+                    html_escape(&label),
+                    html_escape(&path),
+                    html_escape(&interpreter),
+                    html_escape(&run_as_user),
+                    writable.map_or("NULL", |writable| if writable { "yes" } else { "no" })
                 ));
             }
             Err(e) => {
-                eprintln!("Error retrieving service by entitlement: {}", e);
+                eprintln!("Error retrieving script services: {}", e);
             }
         }
     }
-    if services.is_empty() {
-        return Ok(vec![format!(
-            "<p>No services found with entitlement: {}</p>",
-            entitlement
-        )]);
+
+    if rows.is_empty() {
+        return Ok("<p>No services launching a script found.</p>".to_string());
     }
-    let mut html = String::new();
-    html.push_str(
-        format!(
-            "<h2>Found {} services with entitlement: {}</h2>",
-            services.len(),
-            entitlement
+
+    Ok(format!(
+        "<h2>Found {} services launching a script</h2>\
+         {}",
+        rows.len(),
+        paginated_table(
+            "<th>Label</th><th>Path</th><th>Interpreter</th><th>Run as user</th><th>Writable by non-root</th>",
+            &rows,
+            pagination
         )
-        .as_str(),
-    );
-    for service in services {
-        html.push_str(&service);
+    ))
+}
+
+// Get the entitlements, libraries and symbols which first appeared in the
+// database at or after "since", e.g. those a "dora refresh" of a patched
+// binary brought in after the scan
+pub fn get_new_since(db: &str, since: &str) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut html = String::new();
+    let mut total = 0;
+    for (title, query) in [
+        ("Entitlements", NEW_ENTITLEMENTS_SINCE),
+        ("Libraries", NEW_LIBRARIES_SINCE),
+        ("Symbols", NEW_SYMBOLS_SINCE),
+    ] {
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok((
+                    row.get::<_, String>(0)?, // name
+                    row.get::<_, String>(1)?, // first_seen
+                    row.get::<_, i64>(2)?,    // services
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        if rows.is_empty() {
+            continue;
+        }
+
+        total += rows.len();
+        html.push_str(&format!(
+            "<h3>{} ({})</h3><table class=\"sortable\"><thead><tr><th>Name</th><th>First seen</th><th>Services</th></tr></thead><tbody>{}</tbody></table>",
+            title,
+            rows.len(),
+            rows.iter()
+                .map(|(name, first_seen, services)| format!(
+                    "<tr><td>{}</td><td>{}</td><td>{services}</td></tr>",
+                    html_escape(name),
+                    html_escape(first_seen)
+                ))
+                .collect::<String>()
+        ));
     }
 
-    Ok(vec![html])
+    if total == 0 {
+        return Ok(format!("<p>Nothing new since {}.</p>", html_escape(since)));
+    }
+
+    Ok(format!(
+        "<h2>Found {} new items since {}</h2>{}",
+        total,
+        html_escape(since),
+        html
+    ))
 }
 
-// Get service from SQLite database by label case insensitive.
-// Handle multiple services retrieved by label.
-pub fn get_services_by_label_pattern(
-    db: &String,
-    label_pattern: &str,
-) -> Result<String, rusqlite::Error> {
-    let conn = match rusqlite::Connection::open(db) {
-        Ok(conn) => conn,
-        Err(e) => return Err(e),
-    };
+// Node of the entitlement namespace: the entitlements below a dotted prefix
+#[derive(Default)]
+struct EntitlementNode {
+    // Number of services holding the entitlement named by the prefix itself,
+    // when it is an entitlement
+    services: Option<i64>,
+    children: BTreeMap<String, EntitlementNode>,
+}
 
-    let mut stmt = conn.prepare(SERVICES_BY_LABEL_PATTERN)?;
-    let result_set = stmt.query_map(params![format!("*{}*", label_pattern)], |row| {
-        Ok((
-            row.get::<_, String>(0)?, // label
-            row.get::<_, String>(1)?, // path
-        ))
-    })?;
+impl EntitlementNode {
+    fn insert(&mut self, name: &str, services: i64) {
+        let node = name.split('.').fold(self, |node, segment| {
+            node.children.entry(segment.to_string()).or_default()
+        });
+        node.services = Some(services);
+    }
 
-    let mut services = Vec::new();
-    for service in result_set {
-        match service {
-            Ok((label, path)) => {
-                services.push((label, path));
+    // Number of entitlements in the subtree, the node included
+    fn entitlement_count(&self) -> usize {
+        self.services.is_some() as usize
+            + self
+                .children
+                .values()
+                .map(EntitlementNode::entitlement_count)
+                .sum::<usize>()
+    }
+
+    // Render the children of the node, "prefix" being the node's dotted name.
+    // Prefixes with a single child and no entitlement of their own are merged
+    // with it (e.g. "com.apple" rather than "com" then "apple").
+    fn render(&self, prefix: &str) -> String {
+        let mut html = String::from("<ul>");
+        for (segment, child) in &self.children {
+            let mut name = if prefix.is_empty() {
+                segment.clone()
+            } else {
+                format!("{prefix}.{segment}")
+            };
+            let mut node = child;
+            while node.services.is_none() && node.children.len() == 1 {
+                let (segment, only_child) = node.children.iter().next().unwrap();
+                name = format!("{name}.{segment}");
+                node = only_child;
             }
-            Err(e) => {
-                eprintln!("Error retrieving service: {}", e);
+
+            let held_by = node
+                .services
+                .map(|services| format!(" - held by {services} services"))
+                .unwrap_or_default();
+            if node.children.is_empty() {
+                html.push_str(&format!("<li>{}{held_by}</li>", html_escape(&name)));
+            } else {
+                html.push_str(&format!(
+                    "<li><details><summary>{} ({} entitlements){held_by}</summary>{}</details></li>",
+                    html_escape(&name),
+                    node.entitlement_count(),
+                    node.render(&name)
+                ));
             }
         }
+        html.push_str("</ul>");
+        html
+    }
+}
+
+// Get all entitlements grouped by their dotted prefix (e.g. "com.apple.private")
+// into a collapsible tree, with the number of entitlements below each prefix and
+// the number of services holding each entitlement
+pub fn get_entitlement_tree(db: &str) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(ENTITLEMENT_SERVICE_COUNTS)?;
+    let entitlements = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?, // name
+                row.get::<_, i64>(1)?,    // services
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if entitlements.is_empty() {
+        return Ok("<p>No entitlements found.</p>".to_string());
+    }
+
+    let mut tree = EntitlementNode::default();
+    for (name, services) in &entitlements {
+        tree.insert(name, *services);
     }
+
+    Ok(format!(
+        "<h2>Found {} entitlements</h2>{}",
+        entitlements.len(),
+        tree.render("")
+    ))
+}
+
+// Get the dependency closure of the services with a label: their libraries,
+// the libraries those import and so on, down to "max_depth" imports.
+// Only libraries on disk have known imports, so the closure stops at the
+// dyld shared cache.
+pub fn get_library_closure(
+    db: &str,
+    label: &str,
+    max_depth: u32,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let services: Vec<(i64, String, String)> = conn
+        .prepare(SERVICES_ID_SOURCE_PATH_BY_LABEL)?
+        .query_map(params![label], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<Result<_, _>>()?;
+
     if services.is_empty() {
         return Ok(format!(
-            "<p>No service found with label: {label_pattern}</p>"
+            "<p>No service found with label: {}</p>",
+            html_escape(label)
         ));
     }
-    let mut html = String::new();
-    html.push_str(
-        format!(
-            "<h2>Found {} services with label pattern: {label_pattern}</h2>",
-            services.len()
-        )
-        .as_str(),
+
+    let mut html = format!(
+        "<h2>Dependency closure of <a href=\"{}\">{}</a> (up to {} levels)</h2>",
+        html_escape(&service_url(db, label)),
+        html_escape(label),
+        max_depth
     );
-    for (label, path) in services {
+
+    let mut stmt = conn.prepare(LIBRARY_CLOSURE)?;
+    for (service_id, source, path) in services {
+        let libraries = stmt
+            .query_map(params![service_id, max_depth], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,                      // name
+                    row.get::<_, String>(1)?,                      // path
+                    row.get::<_, Option<bool>>(2).unwrap_or(None), // in_shared_cache
+                    row.get::<_, u32>(3)?,                         // depth
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        html.push_str(&format!(
+            "<h3>{} ({}): {} libraries</h3>",
+            html_escape(&path),
+            html_escape(&source),
+            libraries.len()
+        ));
+        if libraries.is_empty() {
+            continue;
+        }
+
+        let rows: String = libraries
+            .iter()
+            .map(|(name, path, in_shared_cache, depth)| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    depth,
+                    html_escape(name),
+                    html_escape(path),
+                    if *in_shared_cache == Some(true) {
+                        "dyld shared cache"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect();
         html.push_str(&format!(
-            "<li><strong>Service:</strong> <a href=\"/service?db={db}&label={label}\">{label}</a> ({path})</li>"
+            "<table class=\"sortable\"><thead><tr><th>Depth</th><th>Name</th><th>Path</th><th></th></tr></thead><tbody>{}</tbody></table>",
+            rows
         ));
     }
+
     Ok(html)
 }
 
@@ -695,44 +3756,273 @@ pub fn get_services_by_label_pattern(
 //////// LOOK FOR SERVICE INFO BY LABEL //////////
 //////////////////////////////////////////////////
 
-// Get all service columns from SQLite database by label
-pub fn get_service_by_label(
-    conn: &rusqlite::Connection,
-    label: &str,
-) -> Option<(String, String, String, String, String, String)> {
+// Get services holding an entitlement of "mapping" while importing none of the
+// symbols associated with using it: a hint that the entitlement may be unneeded.
+// Services without any recorded symbol (e.g. "nm" failed) are not considered.
+pub fn get_overprivileged_services(
+    db: &str,
+    mapping: &EntitlementSymbols,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(SERVICES_BY_ENTITLEMENT_NAME)?;
+    let mut has_symbol = conn.prepare(SERVICE_HAS_SYMBOL)?;
+
+    let mut html = String::new();
+    let mut total = 0;
+    for (entitlement, symbols) in mapping {
+        let result_set = stmt.query_map(params![entitlement], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,    // id
+                row.get::<_, String>(1)?, // label
+                row.get::<_, String>(2)?, // path
+            ))
+        })?;
+
+        let mut services = Vec::new();
+        for service in result_set {
+            match service {
+                Ok((id, label, path)) => {
+                    let mut uses_entitlement = false;
+                    for symbol in symbols {
+                        if has_symbol.query_row(params![id, symbol], |row| row.get::<_, bool>(0))? {
+                            uses_entitlement = true;
+                            break;
+                        }
+                    }
+
+                    if !uses_entitlement {
+                        services.push((label, path));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error retrieving services by entitlement: {}", e);
+                }
+            }
+        }
+
+        if services.is_empty() {
+            continue;
+        }
+
+        total += services.len();
+        html.push_str(&format!(
+            "<h3>{} ({}) - imports none of: {}</h3>{}",
+            html_escape(entitlement),
+            services.len(),
+            html_escape(&symbols.join(", ")),
+            services_table(db, &services, None)
+        ));
+    }
+
+    if total == 0 {
+        return Ok("<p>No over-privileged services found.</p>".to_string());
+    }
+
+    Ok(format!(
+        "<h2>Found {} over-privileged services</h2>{}",
+        total, html
+    ))
+}
+
+// Get services importing symbols of "symbols" (GLOB patterns), grouped by
+// the pattern they match. A service shows up once per matched pattern.
+pub fn get_dangerous_symbol_services(
+    db: &str,
+    symbols: &[String],
+    sort: SortOrder,
+    pagination: &Pagination,
+) -> Result<String, rusqlite::Error> {
+    let conn = open_database(db)?;
+
+    let mut stmt = conn.prepare(&sort.sql(SERVICES_BY_SYMBOL))?;
+
+    let mut groups = Vec::new();
+    for symbol in symbols {
+        let mut services = stmt
+            .query_map(params![symbol], |row| {
+                Ok((
+                    row.get::<_, String>(0)?, // label
+                    row.get::<_, String>(1)?, // path
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        sort.arrange(&mut services);
+        if services.is_empty() {
+            continue;
+        }
+
+        groups.push((symbol.clone(), services));
+    }
+
+    if groups.is_empty() {
+        return Ok(format!(
+            "<p>No services import any of: {}</p>",
+            html_escape(&symbols.join(", "))
+        ));
+    }
+
+    Ok(format!(
+        "<h2>Found services importing {} of {} dangerous symbols</h2>{}",
+        groups.len(),
+        symbols.len(),
+        paginated_grouped_services(db, &groups, pagination)
+    ))
+}
+
+// Provenance of a database as shown on the about page.
+// "launch_paths" and "scan_dirs" are kept as their JSON array text.
+pub struct ScanInfo {
+    pub product_name: String,
+    pub product_version: String,
+    pub build_version: String,
+    pub launch_paths: String,
+    pub scan_dirs: String,
+    pub dora_version: String,
+    pub scanned_at: String,
+}
+
+// Get the scan info of a SQLite database.
+// Databases built before the "scan_info" table existed have none.
+pub fn get_scan_info(conn: &rusqlite::Connection) -> Option<ScanInfo> {
+    let mut stmt = conn.prepare(SCAN_INFO).ok()?;
+
+    let result_set = stmt.query_row([], |row| {
+        Ok(ScanInfo {
+            product_name: row.get::<_, String>(0).unwrap_or(String::from("NULL")),
+            product_version: row.get::<_, String>(1).unwrap_or(String::from("NULL")),
+            build_version: row.get::<_, String>(2).unwrap_or(String::from("NULL")),
+            launch_paths: row.get::<_, String>(3).unwrap_or(String::from("NULL")),
+            scan_dirs: row.get::<_, String>(4).unwrap_or(String::from("NULL")),
+            dora_version: row.get::<_, String>(5).unwrap_or(String::from("NULL")),
+            scanned_at: row.get::<_, String>(6).unwrap_or(String::from("NULL")),
+        })
+    });
+
+    result_set.ok()
+}
+
+// Service columns as shown on the service page.
+// NULL columns (e.g. binaries not launched by a plist) are rendered as "NULL".
+pub struct ServiceInfo {
+    pub label: String,
+    pub path: String,
+    pub run_as_user: String,
+    // "GroupName" of a daemon, if set
+    pub run_as_group: Option<String>,
+    pub run_at_load: String,
+    pub keep_alive: String,
+    pub plist_path: String,
+    pub disabled: String,
+    // JSON dictionary of the KeepAlive conditions, if any
+    pub keep_alive_conditions: Option<String>,
+    // "plist" or "binary"
+    pub source: String,
+    // Comma-separated CodeDirectory flags, if the signature could be read
+    pub codesign_flags: Option<String>,
+    pub platform_identifier: Option<i64>,
+    // "macho" or "script", and the shebang line of a script
+    pub binary_type: Option<String>,
+    pub interpreter: Option<String>,
+    // Deployment target of the binary
+    pub min_os: Option<String>,
+    pub sdk: Option<String>,
+    // "daemon", "agent", "binary", "kext" or "dext"
+    pub kind: Option<String>,
+    // Comma-separated architectures of the binary
+    pub arch: Option<String>,
+    // What a symlinked plist resolves to
+    pub plist_target: Option<String>,
+    // Program of the job as written in its plist, when relative
+    pub program: Option<String>,
+    // Whether the binary imports dlopen, dlsym or NSAddImage
+    pub uses_dynamic_loading: Option<bool>,
+    // TeamIdentifier of the signature, "not set" for platform binaries
+    pub team_id: Option<String>,
+    // Application bundle the program lies in, and its identifier
+    pub bundle_path: Option<String>,
+    pub bundle_id: Option<String>,
+    // "present", "none" or "error"
+    pub entitlements_status: Option<String>,
+    // Comma-separated "LimitLoadToSessionType" of the job, if set
+    pub session_types: Option<String>,
+    // Whether the binary has no 64-bit slice
+    pub is_32bit_only: Option<bool>,
+    // Whether the plist or binary lies in a folder of third-party software
+    pub third_party: Option<bool>,
+    // Risk score and the comma-separated factors adding up to it
+    pub risk_score: Option<i64>,
+    pub risk_factors: Option<String>,
+    // Whether dyld ignores DYLD_* variables for the binary
+    pub is_restricted: Option<bool>,
+}
+
+// Get all service columns from SQLite database by label.
+// A launchd job and a binary may share a label, hence several services.
+pub fn get_service_by_label(conn: &rusqlite::Connection, label: &str) -> Option<Vec<ServiceInfo>> {
     let mut stmt = conn.prepare(SERVICE_BY_LABEL).unwrap();
 
     // Get result set by label considering that some fields can be NULL.
-    let result_set = stmt.query_row(params![label], |row| {
-        Ok((
-            row.get::<_, String>(0)?,                                // label
-            row.get::<_, String>(1)?,                                // path
-            row.get::<_, String>(2).unwrap_or(String::from("NULL")), // run_as_user
-            row.get::<_, String>(3).unwrap_or(String::from("NULL")), // run_at_load
-            row.get::<_, String>(4).unwrap_or(String::from("NULL")), // keep_alive
-            row.get::<_, String>(5).unwrap_or(String::from("NULL")), // plist_path
-        ))
+    let result_set = stmt.query_map(params![label], |row| {
+        Ok(ServiceInfo {
+            label: row.get::<_, String>(0)?,
+            path: row.get::<_, String>(1)?,
+            run_as_user: row.get::<_, String>(2).unwrap_or(String::from("NULL")),
+            run_at_load: row.get::<_, String>(3).unwrap_or(String::from("NULL")),
+            keep_alive: row.get::<_, String>(4).unwrap_or(String::from("NULL")),
+            plist_path: row.get::<_, String>(5).unwrap_or(String::from("NULL")),
+            disabled: row.get::<_, String>(6).unwrap_or(String::from("NULL")),
+            keep_alive_conditions: row.get::<_, Option<String>>(7).unwrap_or(None),
+            source: row.get::<_, String>(8)?,
+            codesign_flags: row.get::<_, Option<String>>(9).unwrap_or(None),
+            platform_identifier: row.get::<_, Option<i64>>(10).unwrap_or(None),
+            binary_type: row.get::<_, Option<String>>(11).unwrap_or(None),
+            interpreter: row.get::<_, Option<String>>(12).unwrap_or(None),
+            min_os: row.get::<_, Option<String>>(13).unwrap_or(None),
+            sdk: row.get::<_, Option<String>>(14).unwrap_or(None),
+            run_as_group: row.get::<_, Option<String>>(15).unwrap_or(None),
+            kind: row.get::<_, Option<String>>(16).unwrap_or(None),
+            arch: row.get::<_, Option<String>>(17).unwrap_or(None),
+            plist_target: row.get::<_, Option<String>>(18).unwrap_or(None),
+            program: row.get::<_, Option<String>>(19).unwrap_or(None),
+            uses_dynamic_loading: row.get::<_, Option<bool>>(20).unwrap_or(None),
+            team_id: row.get::<_, Option<String>>(21).unwrap_or(None),
+            bundle_path: row.get::<_, Option<String>>(22).unwrap_or(None),
+            bundle_id: row.get::<_, Option<String>>(23).unwrap_or(None),
+            entitlements_status: row.get::<_, Option<String>>(24).unwrap_or(None),
+            session_types: row.get::<_, Option<String>>(25).unwrap_or(None),
+            is_32bit_only: row.get::<_, Option<bool>>(26).unwrap_or(None),
+            third_party: row.get::<_, Option<bool>>(27).unwrap_or(None),
+            risk_score: row.get::<_, Option<i64>>(28).unwrap_or(None),
+            risk_factors: row.get::<_, Option<String>>(29).unwrap_or(None),
+            is_restricted: row.get::<_, Option<bool>>(30).unwrap_or(None),
+        })
     });
 
-    match result_set {
-        Ok((label, path, run_as_user, run_at_load, keep_alive, plist_path)) => Some((
-            label,
-            path,
-            run_as_user,
-            run_at_load,
-            keep_alive,
-            plist_path,
-        )),
-        Err(_) => None,
+    let services = result_set.ok()?.collect::<Result<Vec<_>, _>>().ok()?;
+    if services.is_empty() {
+        None
+    } else {
+        Some(services)
     }
 }
 
-pub fn get_mach_service_by_label(conn: &rusqlite::Connection, label: &str) -> Option<Vec<String>> {
+// Get mach services by label from SQLite database, as
+// (name, value, host special port) tuples
+pub fn get_mach_service_by_label(
+    conn: &rusqlite::Connection,
+    label: &str,
+    source: &str,
+) -> Option<Vec<(String, String, Option<i64>)>> {
     let mut stmt = conn.prepare(MACH_SERVICES_BY_LABEL).unwrap();
 
     // Get result set by label considering that some fields can be NULL.
-    let result_set = stmt.query_map(params![label], |row| {
-        Ok(row.get::<_, String>(0).unwrap_or(String::from("NULL")))
+    let result_set = stmt.query_map(params![label, source], |row| {
+        Ok((
+            row.get::<_, String>(0).unwrap_or(String::from("NULL")), // name
+            row.get::<_, String>(1).unwrap_or(String::from("NULL")), // value
+            row.get::<_, Option<i64>>(2).unwrap_or(None),            // host_special_port
+        ))
     });
 
     let mut mach_services = Vec::new();
@@ -758,11 +4048,12 @@ pub fn get_mach_service_by_label(conn: &rusqlite::Connection, label: &str) -> Op
 pub fn get_entitlements_value_by_service_label(
     conn: &rusqlite::Connection,
     service_label: &str,
+    source: &str,
 ) -> Option<HashMap<String, String>> {
     let mut stmt = conn.prepare(ENTITLEMENTS_VALUE_BY_SERVICE_LABEL).unwrap();
 
     // Get result set by label considering that some fields can be NULL.
-    let result_set = stmt.query_map(params![service_label], |row| {
+    let result_set = stmt.query_map(params![service_label, source], |row| {
         Ok((
             row.get::<_, String>(0)?, // entitlement_name
             row.get::<_, String>(1)?, // entitlement_value
@@ -790,19 +4081,34 @@ pub fn get_entitlements_value_by_service_label(
     }
 }
 
-// Get libraries by label from SQLite database
+// Library columns as shown on the service page.
+// "in_shared_cache" is unknown (None) for paths that aren't absolute.
+pub struct LibraryInfo {
+    pub name: String,
+    pub path: String,
+    pub identifier: String,
+    pub team_id: String,
+    pub in_shared_cache: Option<bool>,
+}
+
+// Get libraries by label from SQLite database, together with the
+// library's own code-signing identifier and team identifier
 pub fn get_libraries_by_label(
     conn: &rusqlite::Connection,
     label: &str,
-) -> Option<Vec<(String, String)>> {
+    source: &str,
+) -> Option<Vec<LibraryInfo>> {
     let mut stmt = conn.prepare(LIBRARIES_BY_LABEL).unwrap();
 
     // Get result set by label considering that some fields can be NULL.
-    let result_set = stmt.query_map(params![label], |row| {
-        Ok((
-            row.get::<_, String>(0)?, // library name
-            row.get::<_, String>(1)?, // library path
-        ))
+    let result_set = stmt.query_map(params![label, source], |row| {
+        Ok(LibraryInfo {
+            name: row.get::<_, String>(0)?,
+            path: row.get::<_, String>(1)?,
+            identifier: row.get::<_, String>(2).unwrap_or(String::from("NULL")),
+            team_id: row.get::<_, String>(3).unwrap_or(String::from("NULL")),
+            in_shared_cache: row.get::<_, Option<bool>>(4).unwrap_or(None),
+        })
     });
 
     let mut libraries = Vec::new();
@@ -810,7 +4116,7 @@ pub fn get_libraries_by_label(
         Ok(rows) => {
             for row in rows {
                 match row {
-                    Ok((name, path)) => libraries.push((name, path)),
+                    Ok(library) => libraries.push(library),
                     Err(_) => return None,
                 }
             }
@@ -824,11 +4130,35 @@ pub fn get_libraries_by_label(
     }
 }
 
+// Get environment variables by label from SQLite database, as (name, value) tuples
+pub fn get_env_vars_by_label(
+    conn: &rusqlite::Connection,
+    label: &str,
+    source: &str,
+) -> Option<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(ENV_VARS_BY_LABEL).ok()?;
+
+    let result_set = stmt
+        .query_map(params![label, source], |row| {
+            Ok((
+                row.get::<_, String>(0)?,                                // name
+                row.get::<_, String>(1).unwrap_or(String::from("NULL")), // value
+            ))
+        })
+        .ok()?;
+
+    result_set.collect::<Result<Vec<_>, _>>().ok()
+}
+
 // Get symbols by label from SQLite database
-pub fn get_symbols_by_label(conn: &rusqlite::Connection, label: &str) -> Option<Vec<String>> {
+pub fn get_symbols_by_label(
+    conn: &rusqlite::Connection,
+    label: &str,
+    source: &str,
+) -> Option<Vec<(String, Option<String>)>> {
     let mut stmt = conn.prepare(SYMBOLS_BY_LABEL).unwrap();
-    let result_set: Vec<String> = stmt
-        .query_map(params![label], |row| row.get(0))
+    let result_set: Vec<(String, Option<String>)> = stmt
+        .query_map(params![label, source], |row| Ok((row.get(0)?, row.get(1)?)))
         .unwrap()
         .filter_map(Result::ok)
         .collect();
@@ -843,3 +4173,463 @@ pub fn get_symbols_by_label(conn: &rusqlite::Connection, label: &str) -> Option<
 ////////////////////////////////////////////////
 ////////////////////////////////////////////////
 ////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_service_plist_from_reader;
+
+    // Whether "text" matches "pattern" the way the search queries use them
+    fn sqlite_matches(sql: &str, text: &str, pattern: &str) -> bool {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.query_row(sql, params![text, pattern], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn like_matches(text: &str, pattern: &str) -> bool {
+        sqlite_matches("SELECT ?1 LIKE ?2 ESCAPE '\\'", text, pattern)
+    }
+
+    fn glob_matches(text: &str, pattern: &str) -> bool {
+        sqlite_matches("SELECT ?1 GLOB ?2", text, pattern)
+    }
+
+    // In-memory database with the schema and a service importing "symbols"
+    fn test_database(symbols: &[&str]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../creation_query.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO service (label, source, path) VALUES ('com.example.tool', 'binary', '/usr/bin/tool')",
+            [],
+        )
+        .unwrap();
+        let symbols = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        save_service_imported_symbols(conn.last_insert_rowid(), symbols, &conn).unwrap();
+        conn
+    }
+
+    // Whether a symbol search finds the service of "test_database"
+    fn symbol_search_finds(conn: &Connection, symbol: &str, symbol_match: SymbolMatch) -> bool {
+        let (sql, symbol_param) = symbol_match.query(
+            symbol,
+            [
+                SERVICES_BY_SYMBOL,
+                SERVICES_BY_SYMBOL_EXACT,
+                SERVICES_BY_SYMBOL_NOCASE,
+                SERVICES_BY_SYMBOL_EXACT_NOCASE,
+            ],
+        );
+        !query_services(conn, sql, SortOrder::Label, params![symbol_param])
+            .unwrap()
+            .is_empty()
+    }
+
+    #[test]
+    fn like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("100%", false), "%100\\%%");
+        assert_eq!(like_pattern("a_b", false), "%a\\_b%");
+        assert_eq!(like_pattern("a\\b", false), "%a\\\\b%");
+
+        assert!(like_matches("rate 100% done", &like_pattern("100%", false)));
+        assert!(!like_matches(
+            "rate 1000 done",
+            &like_pattern("100%", false)
+        ));
+        assert!(like_matches("get_task", &like_pattern("t_t", false)));
+        assert!(!like_matches("getxtask", &like_pattern("t_t", false)));
+    }
+
+    #[test]
+    fn like_pattern_honors_wildcards_on_request() {
+        assert_eq!(like_pattern("100%", true), "%100%%");
+        assert!(like_matches("rate 1000 done", &like_pattern("100%", true)));
+        assert!(like_matches("getxtask", &like_pattern("t_t", true)));
+    }
+
+    #[test]
+    fn glob_pattern_escapes_wildcards() {
+        assert_eq!(glob_pattern("a*b?[c]", false), "*a[*]b[?][[]c]*");
+
+        assert!(glob_matches("x[1]", &glob_pattern("[1]", false)));
+        assert!(!glob_matches("x1", &glob_pattern("[1]", false)));
+        assert!(glob_matches("a*b", &glob_pattern("a*b", false)));
+        assert!(!glob_matches("axxb", &glob_pattern("a*b", false)));
+        assert!(glob_matches("a?b", &glob_pattern("a?b", false)));
+        assert!(!glob_matches("axb", &glob_pattern("a?b", false)));
+    }
+
+    #[test]
+    fn glob_pattern_honors_wildcards_on_request() {
+        assert_eq!(glob_pattern("a*b", true), "*a*b*");
+        assert!(glob_matches("axxb", &glob_pattern("a*b", true)));
+        assert!(glob_matches("axb", &glob_pattern("a?b", true)));
+        assert!(glob_matches("x1", &glob_pattern("[0-9]", true)));
+    }
+
+    #[test]
+    fn service_url_round_trips_through_query() {
+        let (db, label) = ("dora_macOS 15.sqlite", "com.example.a&b c=d+e%f");
+        let url = service_url(db, label);
+        assert_eq!(
+            url,
+            "/service?db=dora_macOS%2015.sqlite&label=com.example.a%26b%20c%3Dd%2Be%25f"
+        );
+
+        let uri: axum::http::Uri = url.parse().unwrap();
+        let axum::extract::Query(input) =
+            axum::extract::Query::<HashMap<String, String>>::try_from_uri(&uri).unwrap();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input["db"], db);
+        assert_eq!(input["label"], label);
+    }
+
+    #[test]
+    fn symbol_match_case_sensitive_uses_glob() {
+        let conn = test_database(&["_task_for_pid", "_dlopen"]);
+        let symbol_match = |exact, wildcards| SymbolMatch {
+            exact,
+            wildcards,
+            case_sensitive: true,
+        };
+
+        assert!(symbol_search_finds(
+            &conn,
+            "task_for",
+            symbol_match(false, false)
+        ));
+        assert!(!symbol_search_finds(
+            &conn,
+            "TASK_FOR",
+            symbol_match(false, false)
+        ));
+        assert!(symbol_search_finds(
+            &conn,
+            "_task_for_pid",
+            symbol_match(true, false)
+        ));
+        assert!(!symbol_search_finds(
+            &conn,
+            "_TASK_FOR_PID",
+            symbol_match(true, false)
+        ));
+        // "*" only matches anything when wildcards are on
+        assert!(!symbol_search_finds(
+            &conn,
+            "_task*pid",
+            symbol_match(false, false)
+        ));
+        assert!(symbol_search_finds(
+            &conn,
+            "_task*pid",
+            symbol_match(false, true)
+        ));
+    }
+
+    #[test]
+    fn symbol_match_case_insensitive_uses_like() {
+        let conn = test_database(&["_task_for_pid", "_dlopen"]);
+        let symbol_match = |exact, wildcards| SymbolMatch {
+            exact,
+            wildcards,
+            case_sensitive: false,
+        };
+
+        assert!(symbol_search_finds(
+            &conn,
+            "TASK_FOR",
+            symbol_match(false, false)
+        ));
+        assert!(symbol_search_finds(
+            &conn,
+            "_DLOPEN",
+            symbol_match(true, false)
+        ));
+        assert!(!symbol_search_finds(
+            &conn,
+            "_DLOPE",
+            symbol_match(true, false)
+        ));
+        // "%" only matches anything when wildcards are on
+        assert!(!symbol_search_finds(
+            &conn,
+            "task%pid",
+            symbol_match(false, false)
+        ));
+        assert!(symbol_search_finds(
+            &conn,
+            "task%pid",
+            symbol_match(false, true)
+        ));
+    }
+
+    #[test]
+    fn insert_and_get_id_tells_same_named_libraries_apart() {
+        let conn = test_database(&[]);
+        let insert = |name: &str, path: &str| {
+            insert_and_get_id(
+                "library",
+                &["name", "path"],
+                &[name, path],
+                &["path"],
+                &conn,
+            )
+            .unwrap()
+        };
+
+        let system = insert("libfoo.dylib", "/usr/lib/libfoo.dylib");
+        let planted = insert("libfoo.dylib", "/Users/Shared/libfoo.dylib");
+        assert_ne!(system, planted);
+
+        // Saving a library again gives back the id of its path, not of the
+        // first library of that name
+        assert_eq!(
+            insert("libfoo.dylib", "/Users/Shared/libfoo.dylib"),
+            planted
+        );
+        assert_eq!(insert("libfoo.dylib", "/usr/lib/libfoo.dylib"), system);
+
+        let path_of = |id: i64| -> String {
+            conn.query_row("SELECT path FROM library WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(path_of(planted), "/Users/Shared/libfoo.dylib");
+        assert_eq!(path_of(system), "/usr/lib/libfoo.dylib");
+    }
+
+    const DAEMON_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Label</key>
+	<string>com.example.daemon</string>
+	<key>ProgramArguments</key>
+	<array>
+		<string>/usr/libexec/exampled</string>
+		<string>--verbose</string>
+	</array>
+	<key>RunAtLoad</key>
+	<true/>
+	<key>MachServices</key>
+	<dict>
+		<key>com.example.daemon.xpc</key>
+		<true/>
+		<key>com.example.daemon.port</key>
+		<dict>
+			<key>HostSpecialPort</key>
+			<integer>17</integer>
+		</dict>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    const DAEMON_ENTITLEMENTS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>com.apple.private.tcc.allow</key>
+	<array>
+		<string>kTCCServiceSystemPolicyAllFiles</string>
+	</array>
+	<key>com.apple.security.cs.allow-jit</key>
+	<true/>
+</dict>
+</plist>
+"#;
+
+    // Parse a service definition and its entitlements the way a scan does,
+    // save them to a fresh database and check every row they add
+    fn assert_saves_daemon(plist: &[u8], entitlements: &[u8]) {
+        let conn = test_database(&[]);
+        let json = parse_service_plist_from_reader(plist).unwrap();
+        let service_id = save_service(
+            "/Library/LaunchDaemons/com.example.daemon.plist",
+            &json,
+            &conn,
+        )
+        .unwrap();
+        save_mach_services(service_id, &json, &conn).unwrap();
+        let entitlements = parse_service_plist_from_reader(entitlements).unwrap();
+        save_service_entitlements(service_id, &entitlements, &conn).unwrap();
+
+        // Services are saved as text, flags included
+        let service: (String, String, String, String, String, String) = conn
+            .query_row(
+                "SELECT label, source, path, run_as_user, run_at_load, keep_alive FROM service WHERE id = ?1",
+                params![service_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            service,
+            (
+                "com.example.daemon".to_string(),
+                SOURCE_PLIST.to_string(),
+                "/usr/libexec/exampled".to_string(),
+                "root".to_string(),
+                "1".to_string(),
+                "0".to_string()
+            )
+        );
+
+        let mut stmt = conn
+            .prepare("SELECT name, value, host_special_port FROM mach_service WHERE service_id = ?1 ORDER BY name")
+            .unwrap();
+        let mach_services: Vec<(String, String, Option<i64>)> = stmt
+            .query_map(params![service_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            mach_services,
+            [
+                (
+                    "com.example.daemon.port".to_string(),
+                    r#"{"HostSpecialPort":17}"#.to_string(),
+                    Some(17)
+                ),
+                (
+                    "com.example.daemon.xpc".to_string(),
+                    "true".to_string(),
+                    None
+                ),
+            ]
+        );
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.name, se.value FROM service_entitlement se JOIN entitlement e ON e.id = se.entitlement_id WHERE se.service_id = ?1 ORDER BY e.name",
+            )
+            .unwrap();
+        let entitlements: Vec<(String, String)> = stmt
+            .query_map(params![service_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            entitlements,
+            [
+                (
+                    "com.apple.private.tcc.allow".to_string(),
+                    r#""kTCCServiceSystemPolicyAllFiles""#.to_string()
+                ),
+                (
+                    "com.apple.security.cs.allow-jit".to_string(),
+                    "true".to_string()
+                ),
+            ]
+        );
+    }
+
+    // Convert an XML plist to another encoding
+    fn convert_plist(xml: &str, write: impl Fn(&plist::Value, &mut Vec<u8>)) -> Vec<u8> {
+        let value = plist::Value::from_reader_xml(xml.as_bytes()).unwrap();
+        let mut bytes = Vec::new();
+        write(&value, &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn saves_service_from_xml_plist() {
+        assert_saves_daemon(DAEMON_PLIST.as_bytes(), DAEMON_ENTITLEMENTS.as_bytes());
+    }
+
+    #[test]
+    fn saves_service_from_binary_plist() {
+        let binary =
+            |value: &plist::Value, bytes: &mut Vec<u8>| value.to_writer_binary(bytes).unwrap();
+        let plist = convert_plist(DAEMON_PLIST, binary);
+        assert!(plist.starts_with(b"bplist00"));
+
+        assert_saves_daemon(&plist, &convert_plist(DAEMON_ENTITLEMENTS, binary));
+    }
+
+    #[test]
+    fn saves_service_from_json() {
+        let json = |value: &plist::Value, bytes: &mut Vec<u8>| {
+            serde_json::to_writer(bytes, value).unwrap()
+        };
+        let plist = convert_plist(DAEMON_PLIST, json);
+        assert!(plist.starts_with(b"{"));
+
+        assert_saves_daemon(&plist, &convert_plist(DAEMON_ENTITLEMENTS, json));
+    }
+
+    #[test]
+    fn parses_old_style_ascii_plist() {
+        let ascii = br#"{
+    Label = "com.example.daemon";
+    ProgramArguments = ("/usr/libexec/exampled", "--verbose");
+}"#;
+
+        assert_eq!(
+            parse_service_plist_from_reader(&ascii[..]).unwrap(),
+            serde_json::json!({
+                "Label": "com.example.daemon",
+                "ProgramArguments": ["/usr/libexec/exampled", "--verbose"],
+            })
+        );
+    }
+
+    #[test]
+    fn migrate_database_adds_missing_columns() {
+        let path = std::env::temp_dir().join(format!("dora_migrate_{}.sqlite", std::process::id()));
+        let db = path.to_string_lossy().into_owned();
+        {
+            // Schema of the first releases
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE service (
+                    id INTEGER PRIMARY KEY,
+                    label TEXT NOT NULL UNIQUE,
+                    path TEXT NOT NULL,
+                    run_as_user TEXT,
+                    run_at_load TEXT,
+                    keep_alive TEXT,
+                    plist_path TEXT UNIQUE
+                );
+                CREATE TABLE library (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE,
+                    path TEXT NOT NULL UNIQUE
+                );
+                INSERT INTO service (label, path) VALUES ('com.example.daemon', '/usr/libexec/exampled');",
+            )
+            .unwrap();
+        }
+
+        let added = migrate_database(&db);
+        let conn = Connection::open(&path).unwrap();
+        let service = conn.query_row(
+            "SELECT source, third_party, risk_score FROM service",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            },
+        );
+        let first_seen = conn.query_row("SELECT COUNT(first_seen) FROM library", [], |row| {
+            row.get::<_, i64>(0)
+        });
+        let review = conn.query_row("SELECT COUNT(*) FROM review", [], |row| {
+            row.get::<_, i64>(0)
+        });
+        drop(conn);
+        for file in [db.clone(), format!("{db}-wal"), format!("{db}-shm")] {
+            let _ = std::fs::remove_file(file);
+        }
+
+        assert!(added.unwrap() > 0);
+        assert_eq!(service.unwrap(), ("plist".to_string(), 0, None));
+        assert_eq!(first_seen.unwrap(), 0);
+        assert_eq!(review.unwrap(), 0);
+    }
+}
@@ -1,71 +1,373 @@
+use indicatif::{ProgressBar, ProgressStyle};
 use plist::Value;
 use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::{Cursor, Read};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 
-// Create SQLite database file name
-pub fn generate_sqlite_filename() -> String {
-    // Create sqlite db file name.
-    // The file name format is "dora_<product_name>_<product_version>_<build_version>.sqlite"
-
-    // Get product name
-    let product_name: String = std::process::Command::new("sw_vers")
-        .arg("-productName")
-        .output()
-        .expect("Failed to get product name")
-        .stdout
-        .into_iter()
-        .map(|b| b as char)
-        .collect::<String>()
-        .trim()
-        .to_string();
-
-    // Get product version
-    let product_version: String = std::process::Command::new("sw_vers")
-        .arg("-productVersion")
-        .output()
-        .expect("Failed to get product version")
-        .stdout
-        .into_iter()
-        .map(|b| b as char)
-        .collect::<String>()
-        .trim()
-        .to_string();
-
-    // Get build version
-    let build_version: String = std::process::Command::new("sw_vers")
-        .arg("-buildVersion")
-        .output()
-        .expect("Failed to get build version")
-        .stdout
+use crate::consts::{
+    DANGEROUS_SYMBOLS, ENTITLEMENT_SYMBOLS, EXECUTION_CONTEXTS, PROGRESS_LOG_INTERVAL,
+};
+use crate::error::DoraError;
+
+// Set by "--quiet": suppresses the banner and every non-error message
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// "println!" for informational messages, silenced by "--quiet".
+// Errors keep going through "eprintln!".
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::utils::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use info;
+
+// Product name, version and build of the running OS, as reported by "sw_vers"
+#[derive(Debug, Clone)]
+pub struct OsVersion {
+    pub product_name: String,
+    pub product_version: String,
+    pub build_version: String,
+}
+
+// Get a single "sw_vers" value, e.g. "-productName", or "Unknown" when
+// sw_vers can't be run
+fn sw_vers(arg: &str) -> String {
+    match std::process::Command::new("sw_vers").arg(arg).output() {
+        Ok(output) => output
+            .stdout
+            .into_iter()
+            .map(|b| b as char)
+            .collect::<String>()
+            .trim()
+            .to_string(),
+        Err(e) => {
+            eprintln!("Failed to run sw_vers {}: {}", arg, e);
+            String::from("Unknown")
+        }
+    }
+}
+
+pub fn get_os_version() -> OsVersion {
+    OsVersion {
+        product_name: sw_vers("-productName"),
+        product_version: sw_vers("-productVersion"),
+        build_version: sw_vers("-buildVersion"),
+    }
+}
+
+// Build of the running OS, read once with "sw_vers" for the whole server life
+pub fn host_build_version() -> &'static str {
+    static HOST_BUILD_VERSION: OnceLock<String> = OnceLock::new();
+    HOST_BUILD_VERSION.get_or_init(|| sw_vers("-buildVersion"))
+}
+
+// Context launchd runs the jobs of a folder in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionContext {
+    // System domain: as root, or as the plist's "UserName"
+    Root,
+    // Per-user domain: as the logged-in user
+    User,
+}
+
+impl ExecutionContext {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "root" => Some(ExecutionContext::Root),
+            "user" => Some(ExecutionContext::User),
+            _ => None,
+        }
+    }
+}
+
+// Launchd folder to execution context mapping used when saving services
+pub type ContextMap = BTreeMap<String, ExecutionContext>;
+
+// Set once at startup from the built-in mapping and "--context-map"
+static CONTEXT_MAP: OnceLock<ContextMap> = OnceLock::new();
+
+// Build the folder to execution context mapping: the built-in table, with the
+// entries of the optional JSON file ({"/folder": "root" | "user"}) added on top.
+// A folder listed in the file replaces the built-in entry.
+pub fn load_context_map(path: Option<&str>) -> Result<ContextMap, DoraError> {
+    let mut entries: BTreeMap<String, String> = EXECUTION_CONTEXTS
+        .iter()
+        .map(|(folder, context)| (folder.to_string(), context.to_string()))
+        .collect();
+
+    if let Some(path) = path {
+        let overrides: BTreeMap<String, String> = serde_json::from_reader(File::open(path)?)?;
+        entries.extend(overrides);
+    }
+
+    entries
         .into_iter()
-        .map(|b| b as char)
-        .collect::<String>()
-        .trim()
-        .to_string();
+        .map(
+            |(folder, context)| match ExecutionContext::from_name(&context) {
+                Some(context) => Ok((folder, context)),
+                None => Err(DoraError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "unknown execution context for {}: {} (expected root or user)",
+                        folder, context
+                    ),
+                ))),
+            },
+        )
+        .collect()
+}
+
+pub fn set_context_map(mapping: ContextMap) {
+    let _ = CONTEXT_MAP.set(mapping);
+}
+
+// Get the folder to execution context mapping, falling back to the built-in one
+pub fn context_map() -> &'static ContextMap {
+    CONTEXT_MAP.get_or_init(|| load_context_map(None).expect("Built-in mapping can't fail to load"))
+}
+
+// Get the execution context of the jobs of a plist from the longest folder of
+// the mapping containing it. Plists outside every folder are system jobs.
+pub fn execution_context(plist_path: &str) -> ExecutionContext {
+    let plist_path = Path::new(plist_path);
+    context_map()
+        .iter()
+        .filter(|(folder, _)| plist_path.starts_with(folder))
+        .max_by_key(|(folder, _)| folder.len())
+        .map_or(ExecutionContext::Root, |(_, context)| *context)
+}
+
+// Entitlement to symbol patterns mapping used by the over-privileged services analysis
+pub type EntitlementSymbols = BTreeMap<String, Vec<String>>;
+
+// Set once at startup from the built-in mapping and "--entitlement-symbols"
+static ENTITLEMENT_SYMBOLS_MAP: OnceLock<EntitlementSymbols> = OnceLock::new();
 
-    // Create the SQLite database file name
-    let sqlite_filename = format!(
+// Build the entitlement to symbol mapping: the built-in table, with the entries
+// of the optional JSON file ({"entitlement": ["symbol pattern", ...]}) added on top.
+// An entitlement listed in the file replaces the built-in entry, and an empty
+// list disables it.
+pub fn load_entitlement_symbols(path: Option<&str>) -> Result<EntitlementSymbols, DoraError> {
+    let mut mapping: EntitlementSymbols = ENTITLEMENT_SYMBOLS
+        .iter()
+        .map(|(entitlement, symbols)| {
+            let symbols = symbols.iter().map(|s| s.to_string()).collect();
+            (entitlement.to_string(), symbols)
+        })
+        .collect();
+
+    if let Some(path) = path {
+        let overrides: EntitlementSymbols = serde_json::from_reader(File::open(path)?)?;
+        mapping.extend(overrides);
+        mapping.retain(|_, symbols| !symbols.is_empty());
+    }
+
+    Ok(mapping)
+}
+
+pub fn set_entitlement_symbols(mapping: EntitlementSymbols) {
+    let _ = ENTITLEMENT_SYMBOLS_MAP.set(mapping);
+}
+
+// Get the entitlement to symbol mapping, falling back to the built-in one
+pub fn entitlement_symbols() -> &'static EntitlementSymbols {
+    ENTITLEMENT_SYMBOLS_MAP.get_or_init(|| {
+        load_entitlement_symbols(None).expect("Built-in mapping can't fail to load")
+    })
+}
+
+// Set once at startup from the built-in list or "--dangerous-symbols"
+static DANGEROUS_SYMBOLS_LIST: OnceLock<Vec<String>> = OnceLock::new();
+
+// Build the list of dangerous symbol patterns: the built-in one, or the JSON
+// array of patterns of the optional file (["_task_for_pid", "_mach_vm_*", ...])
+// instead
+pub fn load_dangerous_symbols(path: Option<&str>) -> Result<Vec<String>, DoraError> {
+    match path {
+        Some(path) => Ok(serde_json::from_reader(File::open(path)?)?),
+        None => Ok(DANGEROUS_SYMBOLS.iter().map(|s| s.to_string()).collect()),
+    }
+}
+
+pub fn set_dangerous_symbols(symbols: Vec<String>) {
+    let _ = DANGEROUS_SYMBOLS_LIST.set(symbols);
+}
+
+// Get the dangerous symbol patterns, falling back to the built-in list
+pub fn dangerous_symbols() -> &'static [String] {
+    DANGEROUS_SYMBOLS_LIST
+        .get_or_init(|| load_dangerous_symbols(None).expect("Built-in list can't fail to load"))
+}
+
+// Get the OS version of a system mounted at "root" from its SystemVersion.plist,
+// which is what "sw_vers" reads. Missing values are reported as "Unknown".
+pub fn read_os_version(root: &Path) -> OsVersion {
+    let plist_path = root.join("System/Library/CoreServices/SystemVersion.plist");
+    let plist = parse_service_plist(&plist_path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", plist_path.display(), e);
+        JsonValue::Null
+    });
+    let value = |key: &str| {
+        plist
+            .get(key)
+            .and_then(JsonValue::as_str)
+            .unwrap_or("Unknown")
+            .to_string()
+    };
+
+    OsVersion {
+        product_name: value("ProductName"),
+        product_version: value("ProductVersion"),
+        build_version: value("ProductBuildVersion"),
+    }
+}
+
+// Create SQLite database file name
+// The file name format is "dora_<product_name>_<product_version>_<build_version>.sqlite"
+pub fn generate_sqlite_filename(os_version: &OsVersion) -> String {
+    format!(
         "dora_{}_{}_{}.sqlite",
-        product_name, product_version, build_version
-    );
+        os_version.product_name, os_version.product_version, os_version.build_version
+    )
+}
+
+// Get the OS version back from a database file name made by "generate_sqlite_filename"
+pub fn parse_sqlite_filename(db: &str) -> Option<OsVersion> {
+    let stem = db.strip_prefix("dora_")?.strip_suffix(".sqlite")?;
+    let mut parts = stem.rsplitn(3, '_');
+    let build_version = parts.next()?.to_string();
+    let product_version = parts.next()?.to_string();
+    let product_name = parts.next()?.to_string();
 
-    sqlite_filename
+    Some(OsVersion {
+        product_name,
+        product_version,
+        build_version,
+    })
+}
+
+// Whether a non-root user could replace the file at "path": the file itself or
+// any of its parent directories is owned by a non-root user, group-writable by
+// a non-root group, or world-writable (directories with the sticky bit excepted,
+// since entries of other users can't be renamed there).
+// Returns None when the path can't be inspected.
+pub fn is_writable_by_nonroot(path: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    for component in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+        let metadata = std::fs::metadata(component).ok()?;
+        let mode = metadata.mode();
+
+        let sticky_dir = metadata.is_dir() && mode & 0o1000 != 0;
+        if metadata.uid() != 0
+            || (mode & 0o020 != 0 && metadata.gid() != 0)
+            || (mode & 0o002 != 0 && !sticky_dir)
+        {
+            return Some(true);
+        }
+    }
+
+    Some(false)
 }
 
 // Function that takes a path as input and parse the plist file
-pub fn parse_service_plist<P: AsRef<Path>>(
-    path: P,
-) -> Result<JsonValue, Box<dyn std::error::Error>> {
+pub fn parse_service_plist<P: AsRef<Path>>(path: P) -> Result<JsonValue, DoraError> {
     let file = File::open(path)?;
-    let plist_value = Value::from_reader(file)?;
+    parse_service_plist_from_reader(file)
+}
+
+// Parse a service definition from any reader: an XML, binary or old-style
+// ASCII plist, or the same dictionary already converted to JSON (e.g.
+// "plutil -convert json"). ASCII plists also start with "{": what isn't
+// valid JSON is read as a plist.
+pub fn parse_service_plist_from_reader<R: Read>(mut reader: R) -> Result<JsonValue, DoraError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.trim_ascii_start().starts_with(b"{")
+        && let Ok(json) = serde_json::from_slice(&bytes)
+    {
+        return Ok(json);
+    }
+
+    let plist_value = Value::from_reader(Cursor::new(bytes))?;
     let json = serde_json::to_value(plist_value)?;
 
     Ok(json)
 }
 
+// Convert JSON back to a plist value. Plists have no null: null values are
+// left out of their dictionary or array, and a null document gives None.
+pub fn json_to_plist(json: &JsonValue) -> Option<Value> {
+    match json {
+        JsonValue::Null => None,
+        JsonValue::Bool(b) => Some(Value::Boolean(*b)),
+        JsonValue::Number(n) => Some(match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Value::Integer(i.into()),
+            (None, Some(u)) => Value::Integer(u.into()),
+            (None, None) => Value::Real(n.as_f64().unwrap_or_default()),
+        }),
+        JsonValue::String(s) => Some(Value::String(s.clone())),
+        JsonValue::Array(values) => Some(Value::Array(
+            values.iter().filter_map(json_to_plist).collect(),
+        )),
+        JsonValue::Object(map) => Some(Value::Dictionary(
+            map.iter()
+                .filter_map(|(key, value)| Some((key.clone(), json_to_plist(value)?)))
+                .collect(),
+        )),
+    }
+}
+
+// Set by "dora serve --db": the only database the server exposes
+static SERVED_DATABASE: OnceLock<String> = OnceLock::new();
+
+pub fn set_served_database(db: String) {
+    let _ = SERVED_DATABASE.set(db);
+}
+
+// Set by "--no-style": pages are served without their stylesheet
+static NO_STYLE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_style(no_style: bool) {
+    NO_STYLE.store(no_style, Ordering::Relaxed);
+}
+
+pub fn is_unstyled() -> bool {
+    NO_STYLE.load(Ordering::Relaxed)
+}
+
+// Set by "--token": the secret every request to the server must carry
+static ACCESS_TOKEN: OnceLock<String> = OnceLock::new();
+
+pub fn set_access_token(token: String) {
+    let _ = ACCESS_TOKEN.set(token);
+}
+
+pub fn access_token() -> Option<&'static str> {
+    ACCESS_TOKEN.get().map(String::as_str)
+}
+
 // Get avaliable SQLite databases looking for ".sqlite" files
 pub fn get_available_databases() -> Vec<String> {
+    if let Some(db) = SERVED_DATABASE.get() {
+        return vec![db.clone()];
+    }
+
     let mut databases = Vec::new();
     let paths = std::fs::read_dir(".").expect("Failed to read current directory");
 
@@ -73,10 +375,11 @@ pub fn get_available_databases() -> Vec<String> {
         let entry = entry.expect("Failed to read entry");
         let path = entry.path();
 
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("sqlite") {
-            if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
-                databases.push(name.to_string());
-            }
+        if path.is_file()
+            && path.extension().and_then(|s| s.to_str()) == Some("sqlite")
+            && let Some(name) = path.file_name().and_then(|s| s.to_str())
+        {
+            databases.push(name.to_string());
         }
     }
 
@@ -85,6 +388,131 @@ pub fn get_available_databases() -> Vec<String> {
 
 // Function that validates db param
 // Make sure db is not empty, starts with "dora_", ends with ".sqlite" and not contains "/" character.
-pub fn is_valid_db(db: &String) -> bool {
-    !db.is_empty() && db.starts_with("dora_") && db.ends_with(".sqlite") && !db.contains('/')
+// When a single database is served, no other one is valid.
+pub fn is_valid_db(db: &str) -> bool {
+    !db.is_empty()
+        && db.starts_with("dora_")
+        && db.ends_with(".sqlite")
+        && !db.contains('/')
+        && SERVED_DATABASE.get().is_none_or(|served| served == db)
+}
+
+// Escape the characters with a meaning in HTML text and attribute values
+pub fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// HTML-escape "text" and wrap every case insensitive occurrence of "term" in
+// "<mark>". Text is only escaped when "term" is missing or empty.
+pub fn highlight(text: &str, term: Option<&str>) -> String {
+    let term = match term {
+        Some(term) if !term.is_empty() => term,
+        _ => return html_escape(text),
+    };
+
+    // ASCII lowercasing keeps byte offsets valid in the original text
+    let haystack = text.to_ascii_lowercase();
+    let needle = term.to_ascii_lowercase();
+
+    let mut html = String::with_capacity(text.len());
+    let mut start = 0;
+    while let Some(offset) = haystack[start..].find(&needle) {
+        let begin = start + offset;
+        let end = begin + needle.len();
+        html.push_str(&html_escape(&text[start..begin]));
+        html.push_str("<mark>");
+        html.push_str(&html_escape(&text[begin..end]));
+        html.push_str("</mark>");
+        start = end;
+    }
+    html.push_str(&html_escape(&text[start..]));
+    html
+}
+
+// Called with the number of files processed, the total and the file about to
+// be processed, e.g. to report the progress of a scan started from the web
+type ProgressFn = dyn Fn(u64, u64, &Path) + Send + Sync;
+
+#[derive(Clone)]
+pub struct ProgressObserver(Arc<ProgressFn>);
+
+impl ProgressObserver {
+    pub fn new(observer: impl Fn(u64, u64, &Path) + Send + Sync + 'static) -> Self {
+        ProgressObserver(Arc::new(observer))
+    }
+}
+
+impl std::fmt::Debug for ProgressObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressObserver")
+    }
+}
+
+// Progress of a database population.
+// Draws a progress bar, or, for non-interactive runs, prints a log line
+// every PROGRESS_LOG_INTERVAL processed files. An observer is told about
+// every file either way.
+pub struct ScanProgress {
+    bar: Option<ProgressBar>,
+    observer: Option<ProgressObserver>,
+    total: u64,
+    done: u64,
+}
+
+impl ScanProgress {
+    pub fn new(total: u64, show_bar: bool, observer: Option<ProgressObserver>) -> Self {
+        let bar = show_bar.then(|| {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {wide_msg}")
+                    .expect("Invalid progress bar template"),
+            );
+            bar
+        });
+
+        ScanProgress {
+            bar,
+            observer,
+            total,
+            done: 0,
+        }
+    }
+
+    // Account for a file about to be processed
+    pub fn inc(&mut self, path: &Path) {
+        self.done += 1;
+
+        if let Some(observer) = &self.observer {
+            (observer.0)(self.done, self.total, path);
+        }
+
+        match &self.bar {
+            Some(bar) => {
+                bar.set_message(path.display().to_string());
+                bar.inc(1);
+            }
+            None => {
+                if self.done.is_multiple_of(PROGRESS_LOG_INTERVAL) || self.done == self.total {
+                    info!("Processed {}/{} files", self.done, self.total);
+                }
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_with_message("done");
+        }
+    }
 }
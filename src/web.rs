@@ -1,27 +1,148 @@
-use axum::{extract::Form, response::Html};
-use std::collections::HashMap;
+use axum::{
+    Json,
+    body::Body,
+    extract::{Form, FromRef, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{
+        Html, IntoResponse, Redirect, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use percent_encoding::utf8_percent_encode;
+use serde_json::{Value as JsonValue, json};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::watch;
+use tokio_stream::{Stream, StreamExt, wrappers::WatchStream};
+use tokio_util::io::ReaderStream;
 
-use crate::consts::{HTML_BODY_TITLE, HTML_FORM_FIELDS, HTML_HEADER};
+use crate::consts::{
+    ALL_DATABASES, BINARY_TYPE_SCRIPT, CLOSURE_DEFAULT_DEPTH, CLOSURE_MAX_DEPTH,
+    ENTITLEMENTS_ERROR, ENTITLEMENTS_NONE, HTML_BODY_TITLE, HTML_FORM_FIELDS, HTML_HEADER,
+    HTML_STYLE, LISTENING_PORT, MAX_BATCH_LABELS, MAX_RECENT_QUERIES, PLIST_CONTENT_TYPE,
+    REVIEW_STATUSES, SQLITE_CONTENT_TYPE, TOKEN_COOKIE,
+};
+use crate::error::DoraError;
+use crate::macho::missing_tools;
 use crate::sqlite::{
-    get_entitlements_value_by_service_label, get_libraries_by_label, get_mach_service_by_label,
-    get_service_by_label, get_services_by_entitlement, get_services_by_entitlement_and_symbol,
-    get_services_by_label_pattern, get_services_by_library, get_services_by_symbol,
-    get_symbols_by_label,
+    Pagination, QUERY_VALUE, ScanOptions, SearchResults, ServiceFilters, ServiceInfo,
+    SigningOrigin, SortOrder, SymbolMatch, get_dangerous_symbol_services, get_dynamic_loaders,
+    get_entitlement_tree, get_entitlements_value_by_service_label, get_env_vars_by_label,
+    get_libraries_by_label, get_library_closure, get_mach_service_by_label, get_new_since,
+    get_overprivileged_services, get_phantom_libraries, get_review_by_label, get_scan_info,
+    get_script_services, get_service_bundle, get_service_by_label, get_services,
+    get_services_by_codesign_flag, get_services_by_entitlement,
+    get_services_by_entitlement_and_symbol, get_services_by_entitlement_value,
+    get_services_by_label_fuzzy, get_services_by_label_pattern, get_services_by_library,
+    get_services_by_path_pattern, get_services_by_signing_origin, get_services_by_symbol,
+    get_services_by_symbol_and_library, get_services_json_by_labels, get_symbols_by_label,
+    get_thirty_two_bit_services, get_top_risk_services, get_unrestricted_root_services,
+    get_writable_services, get_xpc_services, list_services, open_database, populate_db,
+    review_badge, set_review,
+};
+use crate::utils::{
+    OsVersion, ProgressObserver, access_token, dangerous_symbols, entitlement_symbols,
+    get_available_databases, highlight, host_build_version, html_escape, is_unstyled, is_valid_db,
+    json_to_plist, parse_sqlite_filename,
 };
-use crate::utils::{get_available_databases, is_valid_db};
 
-pub async fn index() -> Html<String> {
-    let databases = get_available_databases();
+// Middleware rejecting requests without the "--token" secret with 401.
+// The secret is accepted as a bearer token, a "token" query parameter or the
+// cookie set when it was given as a parameter, so that a browser opening
+// "/?token=.." can then follow the links of the pages. Without "--token",
+// every request goes through.
+pub async fn require_token(request: Request, next: Next) -> Response {
+    let Some(token) = access_token() else {
+        return next.run(request).await;
+    };
+    let cookie_value = utf8_percent_encode(token, QUERY_VALUE).to_string();
 
-    let db_options: String = databases
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let cookie = headers
+        .get_all(header::COOKIE)
         .iter()
-        .map(|db| format!(r#"<option value="{0}">{0}</option>"#, db))
-        .collect();
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, value)| value);
+    let param = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(params)| params.get("token").cloned());
+
+    if bearer.is_some_and(|bearer| secrets_match(bearer, token))
+        || cookie.is_some_and(|cookie| secrets_match(cookie, &cookie_value))
+    {
+        return next.run(request).await;
+    }
+
+    if param.is_some_and(|param| secrets_match(&param, token)) {
+        let mut response = next.run(request).await;
+        if let Ok(value) = HeaderValue::from_str(&format!(
+            "{TOKEN_COOKIE}={cookie_value}; Path=/; HttpOnly; SameSite=Strict"
+        )) {
+            response.headers_mut().insert(header::SET_COOKIE, value);
+        }
+        return response;
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        "Missing or invalid token",
+    )
+        .into_response()
+}
+
+// Compare two secrets in a time that doesn't depend on where they differ
+fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// Heading naming the database results come from, with a warning when it was
+// built for another OS build than the one running the server, as results
+// may then not match this machine
+fn database_heading(db: &str) -> String {
+    let host_build = host_build_version();
+    let heading = format!("<h2>Using: {}</h2>", html_escape(db));
+    match parse_sqlite_filename(db) {
+        Some(os_version) if host_build != "Unknown" && os_version.build_version != host_build => {
+            format!(
+                "{heading}
+                <p style=\"background: #fff3cd; border: 1px solid #e0a800; padding: 0.5em\">
+                    <strong>Warning:</strong> this database was built for {} {} ({}), but this
+                    machine runs build {host_build}: its data may not match this machine.
+                </p>",
+                html_escape(&os_version.product_name),
+                html_escape(&os_version.product_version),
+                html_escape(&os_version.build_version)
+            )
+        }
+        _ => heading,
+    }
+}
 
-    let html = format!(
-        r#"
-        <html>
-            {HTML_HEADER}   
+// Wrap "content" in the common page shell: header, title and search form
+fn render_page(db_options: &str, content: &str) -> Html<String> {
+    let style = if is_unstyled() { "" } else { HTML_STYLE };
+    Html(format!(
+        r#"<html>
+            <head>
+                {HTML_HEADER}
+                {style}
+            </head>
             <body>
                 {HTML_BODY_TITLE}
                 <form action="/query" method="post">
@@ -31,276 +152,2433 @@ pub async fn index() -> Html<String> {
                     </select>
                     {HTML_FORM_FIELDS}
                 </form>
+                {content}
             </body>
-        </html>
-        "#
-    );
-
-    Html(html)
+        </html>"#
+    ))
 }
 
-// Handler for the "/query" route
-// This route is used to query the database with a SQL query provided by the user
-// The user could submit:
-// • a service label as "service" key
-// • an entitlement name as "entitlement" key
-// • a library name as "library" key
-// • a symbol name as "symbol" key
-// • a combination of the above.
-pub async fn query(Form(input): Form<HashMap<String, String>>) -> Html<String> {
-    let databases = get_available_databases();
-
-    let db_options: String = databases
-        .iter()
-        .map(|db| format!(r#"<option value="{0}">{0}</option>"#, db))
-        .collect();
+// Page returned when the "db" parameter isn't a valid database name, or how
+// to create one when there is none yet
+fn invalid_db_page(db_options: &str, db: &str) -> Html<String> {
+    if get_available_databases().is_empty() {
+        return render_page(db_options, &no_databases_html());
+    }
+    render_page(
+        db_options,
+        &format!("<p>Invalid database name: {}</p>", html_escape(db)),
+    )
+}
 
-    // Extract the query parameters from the input
-    let db = input.get("db").cloned().unwrap_or_default();
-    if is_valid_db(&db) == false {
-        // If db is not valid, return an error message
-        return Html(format!(
-            r#"<html>
-                {HTML_HEADER}
-                <body>
-                    {HTML_BODY_TITLE}
-                    <p>Invalid database name: {}</p>
-                    <form action="/query" method="post">
-                        <label for="db">Choose database:</label>
-                        <select name="db" id="db">
-                            {db_options}
-                        </select>
-                        {HTML_FORM_FIELDS}
-                    </form>
-                </body>
-            </html>"#,
-            db
-        ));
+// Error of the JSON answers for an invalid "db" parameter, telling to scan
+// first when there is no database at all
+fn invalid_db_message(db: &str) -> String {
+    if get_available_databases().is_empty() {
+        "No databases found: run `dora scan` first".to_string()
+    } else {
+        format!("Invalid database name: {}", db)
     }
+}
 
-    let service = input.get("service").cloned().unwrap_or_default();
-    let entitlement = input.get("entitlement").cloned().unwrap_or_default();
-    let library = input.get("library").cloned().unwrap_or_default();
-    let symbol = input.get("symbol").cloned().unwrap_or_default();
+// Instructions shown when the server finds no database, e.g. when it was
+// started before any scan: pages have nothing to search until there is one
+fn no_databases_html() -> String {
+    let folder = std::env::current_dir()
+        .map(|folder| folder.display().to_string())
+        .unwrap_or_else(|_| ".".to_string());
+    let scan_link = if HOST_SCAN.get().is_some() {
+        r#"<p>Or <a href="/scan">scan the host</a> from here.</p>"#
+    } else {
+        ""
+    };
 
-    let mut services_html: String = "<p>No query parameters provided.</p>".to_string();
+    format!(
+        "<h3>No databases found</h3>\
+         <p>Run <code>dora scan</code> first: it saves a <code>dora_*.sqlite</code> database in the folder the server runs from ({}), which these pages then search. Databases copied there show up without restarting the server.</p>\
+         <p>To try the pages without scanning, <code>dora sample</code> creates a small database of fabricated services.</p>{scan_link}",
+        html_escape(&folder)
+    )
+}
 
-    if !service.is_empty() {
-        services_html = get_services_by_label_pattern(&db, &service).unwrap_or_else(|e| {
-            eprintln!("Error retrieving service by label pattern: {}", e);
-            "<p>Error retrieving service.</p>".to_string()
-        });
-    } else if !entitlement.is_empty() {
-        if !symbol.is_empty() {
-            // If both entitlement and symbol are provided, get services by both
-            let services = get_services_by_entitlement_and_symbol(&db, &entitlement, &symbol)
-                .unwrap_or_else(|e| {
-                    eprintln!("Error retrieving services by entitlement and symbol: {}", e);
-                    vec![format!("<p>Error retrieving services.</p>")]
-                });
-
-            services_html = services.join("\n");
-        } else {
-            let services = get_services_by_entitlement(&db, &entitlement).unwrap_or_else(|e| {
-                eprintln!("Error retrieving services by entitlement: {}", e);
-                vec![format!("<p>Error retrieving services.</p>")]
-            });
+// Representation asked for by a client of the routes serving both HTML pages
+// and JSON
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ResponseFormat {
+    Html,
+    Json,
+}
 
-            services_html = services.join("\n");
+impl ResponseFormat {
+    // A "format=json|html" parameter wins. Otherwise JSON is only served when
+    // the Accept header prefers "application/json" to "text/html": browsers
+    // and clients accepting anything ("*/*") get HTML.
+    fn negotiate(headers: &HeaderMap, input: &HashMap<String, String>) -> Self {
+        match input
+            .get("format")
+            .map(|format| format.to_lowercase())
+            .as_deref()
+        {
+            Some("json") => return ResponseFormat::Json,
+            Some("html") => return ResponseFormat::Html,
+            _ => {}
         }
-    } else if !library.is_empty() {
-        let services = get_services_by_library(&db, &library).unwrap_or_else(|e| {
-            eprintln!("Error retrieving services by library: {}", e);
-            vec![format!("<p>Error retrieving services.</p>")]
-        });
 
-        services_html = services.join("\n");
-    } else if !symbol.is_empty() {
-        let services = get_services_by_symbol(&db, &symbol).unwrap_or_else(|e| {
-            eprintln!("Error retrieving services by symbol: {}", e);
-            vec![format!("<p>Error retrieving services.</p>")]
-        });
+        // Quality of each media range, e.g. "application/json;q=0.9"
+        let accepted: Vec<(String, f32)> = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let media_type = parts.next().unwrap_or_default().to_lowercase();
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (media_type, quality)
+            })
+            .collect();
+        let quality = |media_type: &str| {
+            accepted
+                .iter()
+                .find(|(accepted, _)| accepted == media_type)
+                .map(|(_, quality)| *quality)
+        };
 
-        services_html = services.join("\n");
+        match (quality("application/json"), quality("text/html")) {
+            (Some(json), html) if json > 0.0 && json > html.unwrap_or(0.0) => ResponseFormat::Json,
+            _ => ResponseFormat::Html,
+        }
     }
+}
 
-    return Html(format!(
-        r#"<html>
-            {HTML_HEADER}
-            <body>
-                {HTML_BODY_TITLE}
-                <form action="/query" method="post">
-                    <label for="db">Choose database:</label>
-                    <select name="db" id="db">
-                        {db_options}
-                    </select>
-                    {HTML_FORM_FIELDS}
-                </form>
-                <h2>Using: {db}</h2>
-                <ul>{services_html}</ul>
-            </body>
-        </html>"#
-    ));
+// Mark a response of a negotiated route as depending on the Accept header,
+// so that caches don't serve a JSON answer to a browser or the other way round
+fn negotiated(response: impl IntoResponse) -> Response {
+    let mut response = response.into_response();
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    response
 }
 
-// For a given service label, get all entitlements, libraries, symbols and mach services associated with it.
-pub async fn service(Form(input): Form<HashMap<String, String>>) -> Html<String> {
-    let databases = get_available_databases();
-    let db_options: String = databases
+// JSON error of the negotiated routes, shaped like those of the "/api" routes
+fn json_error(status: StatusCode, error: &str) -> Response {
+    negotiated((status, Json(json!({ "error": error }))))
+}
+
+// Parse a boolean query parameter ("true"/"false" or "1"/"0").
+// Missing or unrecognized values leave the filter unset.
+fn parse_bool_param(input: &HashMap<String, String>, key: &str) -> Option<bool> {
+    match input.get(key).map(|v| v.to_lowercase()).as_deref() {
+        Some("true") | Some("1") => Some(true),
+        Some("false") | Some("0") => Some(false),
+        _ => None,
+    }
+}
+
+// Build the <option> list of the database <select>.
+// When several databases are available, an extra "all" entry searches them all at once.
+fn database_options(databases: &[String]) -> String {
+    if databases.is_empty() {
+        return r#"<option value="" disabled selected>No databases found</option>"#.to_string();
+    }
+
+    let mut db_options: String = databases
         .iter()
-        .map(|db| format!(r#"<option value="{0}">{0}</option>"#, db))
+        .map(|db| format!(r#"<option value="{0}">{0}</option>"#, html_escape(db)))
         .collect();
 
-    // Extract the query parameters from the input
-    let db = input.get("db").cloned().unwrap_or_default();
-    if is_valid_db(&db) == false {
-        return Html(format!(
-            r#"<html>
-                {HTML_HEADER}
-                <body>
-                    {HTML_BODY_TITLE}
-                    <p>Invalid database name: {}</p>
-                    <form action="/query" method="post">
-                        <label for="db">Choose database:</label>
-                        <select name="db" id="db">
-                            {db_options}
-                        </select>
-                        {HTML_FORM_FIELDS}
-                    </form>
-                </body>
-            </html>"#,
-            db
+    if databases.len() > 1 {
+        db_options.push_str(&format!(
+            r#"<option value="{ALL_DATABASES}">All databases</option>"#
         ));
     }
 
-    let service_label = input.get("label").cloned().unwrap_or_default();
+    db_options
+}
 
-    let conn = match rusqlite::Connection::open(&db) {
-        Ok(conn) => conn,
-        Err(e) => return Html(format!("Failed to open database: {}", e)),
+// Lists the searches recently run on this server, as links replaying them
+pub async fn index(State(recent_queries): State<RecentQueries>) -> Html<String> {
+    let databases = get_available_databases();
+
+    let db_options = database_options(&databases);
+
+    let scan_link = if HOST_SCAN.get().is_some() {
+        r#"<p><a href="/scan">Rescan the host</a></p>"#
+    } else {
+        ""
     };
 
-    let service_html = match get_service_by_label(&conn, &service_label) {
-        Some((label, path, run_as_user, run_at_load, keep_alive, plist_path)) => {
-            format!(
-                "<ul>
-                    <li><strong>Service:</strong> {}</li>
-                    <li><strong>Path:</strong> {}</li>
-                    <li><strong>Run as user:</strong> {}</li>
-                    <li><strong>Run at load:</strong> {}</li>
-                    <li><strong>Keep alive:</strong> {}</li>
-                    <li><strong>Plist path:</strong> {}</li>
-                </ul>",
-                label, path, run_as_user, run_at_load, keep_alive, plist_path
-            )
-        }
-        None => {
+    if databases.is_empty() {
+        return render_page(&db_options, &no_databases_html());
+    }
+
+    let recent = recent_queries.lock().unwrap();
+    if recent.is_empty() {
+        return render_page(&db_options, scan_link);
+    }
+
+    let items: String = recent
+        .iter()
+        .map(|query| {
             format!(
-                "<h2>Service: {}</h2>
-                <p>No service found with label: {}</p>",
-                service_label, service_label
+                "<li><a href=\"{}\">{}</a></li>",
+                html_escape(&query.url()),
+                html_escape(&query.summary())
             )
+        })
+        .collect();
+
+    render_page(
+        &db_options,
+        &format!("{scan_link}<h3>Recent searches</h3><ul>{items}</ul>"),
+    )
+}
+
+// Progress of the scan started from the web interface, pushed to the
+// "/scan/progress" streams
+#[derive(Debug, Clone)]
+pub enum ScanEvent {
+    // No scan was started since the server is up
+    Idle,
+    Progress { done: u64, total: u64, path: String },
+    // The scan summary, with the database it rebuilt
+    Done(JsonValue),
+    Failed(String),
+}
+
+impl ScanEvent {
+    fn is_running(&self) -> bool {
+        matches!(self, ScanEvent::Progress { .. })
+    }
+
+    // Server-Sent Event named after the variant, with a JSON payload.
+    // Failures are "failed" events: "error" is taken by EventSource for
+    // connection errors.
+    fn to_sse(&self) -> Event {
+        let (name, data) = match self {
+            ScanEvent::Idle => ("idle", json!({})),
+            ScanEvent::Progress { done, total, path } => (
+                "progress",
+                json!({ "done": done, "total": total, "path": path }),
+            ),
+            ScanEvent::Done(summary) => ("done", summary.clone()),
+            ScanEvent::Failed(error) => ("failed", json!({ "error": error })),
+        };
+        Event::default().event(name).data(data.to_string())
+    }
+}
+
+// Latest event of the scan started from the web interface. Every
+// "/scan/progress" stream watches it: a slow client skips intermediate
+// progress events rather than falling behind.
+#[derive(Clone)]
+pub struct ScanStatus(Arc<watch::Sender<ScanEvent>>);
+
+impl Default for ScanStatus {
+    fn default() -> Self {
+        ScanStatus(Arc::new(watch::Sender::new(ScanEvent::Idle)))
+    }
+}
+
+// State shared by the handlers of the server
+#[derive(Clone, Default)]
+pub struct AppState {
+    pub recent_queries: RecentQueries,
+    pub scan_status: ScanStatus,
+}
+
+impl FromRef<AppState> for RecentQueries {
+    fn from_ref(state: &AppState) -> Self {
+        state.recent_queries.clone()
+    }
+}
+
+impl FromRef<AppState> for ScanStatus {
+    fn from_ref(state: &AppState) -> Self {
+        state.scan_status.clone()
+    }
+}
+
+// Scan of the host the web interface can start again: the database it
+// rebuilds, the OS it describes, the scan options and the tools they need
+pub struct HostScan {
+    pub database: String,
+    pub os_version: OsVersion,
+    pub options: ScanOptions,
+    pub tools: Vec<&'static str>,
+}
+
+// Only set when dora runs without a command, i.e. scans the host and serves it
+static HOST_SCAN: OnceLock<HostScan> = OnceLock::new();
+
+pub fn set_host_scan(scan: HostScan) {
+    let _ = HOST_SCAN.set(scan);
+}
+
+// Scan the host again into a temporary file, which then replaces its
+// database: the previous one stays browsable until the scan completes
+fn rescan_host(scan: &HostScan, status: &ScanStatus) -> Result<JsonValue, DoraError> {
+    let partial = format!("{}.partial", scan.database);
+    let remove = |path: &str| {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{path}{suffix}"));
         }
     };
+    remove(&partial);
 
-    // Get Mach services for the service
-    let mach_services_html = match get_mach_service_by_label(&conn, &service_label) {
-        Some(mach_services) => {
-            if mach_services.is_empty() {
-                "<h3>Mach Services:</h3><p>No Mach services found for this service.</p>".to_string()
-            } else {
-                let mach_services_count = mach_services.len();
-                format!(
-                    "<h3>Mach Services ({mach_services_count})</h3><ul>{}</ul>",
-                    mach_services
-                        .iter()
-                        .map(|ms| format!("<li>{}</li>", ms))
-                        .collect::<String>()
-                )
-            }
+    let sender = status.0.clone();
+    let options = ScanOptions {
+        progress: false,
+        observer: Some(ProgressObserver::new(move |done, total, path| {
+            sender.send_replace(ScanEvent::Progress {
+                done,
+                total,
+                path: path.display().to_string(),
+            });
+        })),
+        ..scan.options.clone()
+    };
+    let summary = match populate_db(&partial, &scan.os_version, &options) {
+        Ok(summary) => summary,
+        Err(e) => {
+            remove(&partial);
+            return Err(e);
         }
-        None => "<h3>Mach Services:</h3><p>Error retrieving Mach services.</p>".to_string(),
     };
 
-    // Get entitlements for the service
-    let entitlements_html = match get_entitlements_value_by_service_label(&conn, &service_label) {
-        Some(entitlements) => {
-            if entitlements.is_empty() {
-                "<h3>Entitlements:</h3><p>No entitlements found for this service.</p>".to_string()
-            } else {
-                let entitlements_count = entitlements.len();
-                format!(
-                    "<h3>Entitlements ({entitlements_count})</h3><ul>{}</ul>",
-                    entitlements
-                        .iter()
-                        .map(|(k, v)| format!("<li>{}: {}</li>", k, v))
-                        .collect::<String>()
-                )
+    remove(&scan.database);
+    std::fs::rename(&partial, &scan.database)?;
+
+    let mut summary = summary.to_json();
+    summary["db"] = json!(scan.database);
+    Ok(summary)
+}
+
+// Handler for the "/scan" route
+// Page starting a new scan of the host and following its progress
+pub async fn scan_page() -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let Some(scan) = HOST_SCAN.get() else {
+        return render_page(
+            &db_options,
+            "<p>Scans can only be started when dora runs without a command, scanning the host.</p>",
+        );
+    };
+
+    render_page(
+        &db_options,
+        &format!(
+            r#"<h2>Host scan</h2>
+            <form action="/scan" method="post">
+                <button type="submit">Rescan the host into {}</button>
+            </form>
+            <p><progress id="scan-progress" value="0" max="1"></progress></p>
+            <p id="scan-status"></p>
+            <script>
+                const bar = document.getElementById("scan-progress");
+                const status = document.getElementById("scan-status");
+                const events = new EventSource("/scan/progress");
+                events.addEventListener("idle", () => {{
+                    status.textContent = "No scan started since the server is up.";
+                    events.close();
+                }});
+                events.addEventListener("progress", (event) => {{
+                    const progress = JSON.parse(event.data);
+                    bar.max = Math.max(progress.total, 1);
+                    bar.value = progress.done;
+                    status.textContent = `${{progress.done}}/${{progress.total}} ${{progress.path}}`;
+                }});
+                events.addEventListener("done", (event) => {{
+                    const summary = JSON.parse(event.data);
+                    bar.value = bar.max;
+                    status.textContent = `Scan completed in ${{summary.elapsed_seconds.toFixed(1)}}s: ` +
+                        `${{summary.services}} services, ${{summary.entitlements}} entitlements, ` +
+                        `${{summary.libraries}} libraries, ${{summary.symbols}} symbols`;
+                    events.close();
+                }});
+                events.addEventListener("failed", (event) => {{
+                    status.textContent = `Scan failed: ${{JSON.parse(event.data).error}}`;
+                    events.close();
+                }});
+            </script>"#,
+            html_escape(&scan.database)
+        ),
+    )
+}
+
+// Handler for the POST "/scan" route
+// Start a new scan of the host in the background, one at a time, then show
+// its progress
+pub async fn start_scan(State(status): State<ScanStatus>) -> Response {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let Some(scan) = HOST_SCAN.get() else {
+        return (
+            StatusCode::NOT_FOUND,
+            render_page(
+                &db_options,
+                "<p>Scans can only be started when dora runs without a command, scanning the host.</p>",
+            ),
+        )
+            .into_response();
+    };
+
+    let missing = missing_tools(&scan.tools);
+    if !missing.is_empty() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            render_page(
+                &db_options,
+                &format!("<p>Missing external tools: {}</p>", missing.join(", ")),
+            ),
+        )
+            .into_response();
+    }
+
+    let started = status.0.send_if_modified(|event| {
+        if event.is_running() {
+            return false;
+        }
+        *event = ScanEvent::Progress {
+            done: 0,
+            total: 0,
+            path: String::new(),
+        };
+        true
+    });
+    if !started {
+        return (
+            StatusCode::CONFLICT,
+            render_page(
+                &db_options,
+                r#"<p>A scan is already running: <a href="/scan">follow it</a>.</p>"#,
+            ),
+        )
+            .into_response();
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let event = match rescan_host(scan, &status) {
+            Ok(summary) => ScanEvent::Done(summary),
+            Err(e) => {
+                eprintln!("Failed to scan the host: {}", e);
+                ScanEvent::Failed(e.to_string())
             }
+        };
+        status.0.send_replace(event);
+    });
+
+    Redirect::to("/scan").into_response()
+}
+
+// Handler for the "/scan/progress" route
+// Server-Sent Events following the scan started from the web interface:
+// "progress" events (files processed, total, current path) then a "done"
+// event with the summary, or "failed". "idle" when no scan was started.
+pub async fn scan_progress(
+    State(status): State<ScanStatus>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = WatchStream::new(status.0.subscribe()).map(|event| Ok(event.to_sse()));
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+// Handler for the "/health" route
+// Liveness/readiness probe: it only counts the available databases and never
+// opens one, so it answers even while a scan is still running.
+pub async fn health() -> Json<JsonValue> {
+    Json(json!({
+        "status": "ok",
+        "databases": get_available_databases().len(),
+    }))
+}
+
+// Handler for the "/api/databases" route
+// Machine readable list of the available databases with the OS version parsed
+// from their file name, their size in bytes and their modification time
+// (seconds since the Unix epoch)
+pub async fn api_databases() -> Json<JsonValue> {
+    let databases: Vec<JsonValue> = get_available_databases()
+        .into_iter()
+        .map(|db| {
+            let os_version = parse_sqlite_filename(&db);
+            let metadata = std::fs::metadata(&db).ok();
+            let modified = metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
+
+            json!({
+                "name": db,
+                "product_name": os_version.as_ref().map(|v| &v.product_name),
+                "product_version": os_version.as_ref().map(|v| &v.product_version),
+                "build_version": os_version.as_ref().map(|v| &v.build_version),
+                "size": metadata.as_ref().map(|m| m.len()),
+                "modified": modified,
+            })
+        })
+        .collect();
+
+    Json(JsonValue::from(databases))
+}
+
+// Handler for the "/download" route
+// Send a database file as an attachment, e.g. to browse it on another host.
+// The file is streamed rather than read into memory.
+pub async fn download(Query(input): Query<HashMap<String, String>>) -> Response {
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) || !get_available_databases().contains(&db) {
+        return (StatusCode::BAD_REQUEST, invalid_db_message(&db)).into_response();
+    }
+
+    let file = match tokio::fs::File::open(&db).await {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error opening database {}: {}", db, e);
+            return (StatusCode::NOT_FOUND, "Database not found").into_response();
         }
-        None => "<h3>Entitlements:</h3><p>Error retrieving entitlements.</p>".to_string(),
     };
 
-    // Get libraries for the service
-    let libraries_html = match get_libraries_by_label(&conn, &service_label) {
-        Some(libraries) => {
-            if libraries.is_empty() {
-                "<h3>Libraries:</h3><p>No libraries found for this service.</p>".to_string()
-            } else {
-                let libraries_count = libraries.len();
-                format!(
-                    "<h3>Libraries ({libraries_count})</h3><ul>{}</ul>",
-                    libraries
-                        .iter()
-                        .map(|(name, path)| format!("<li>{} ({})</li>", name, path))
-                        .collect::<String>()
-                )
-            }
+    let length = file.metadata().await.ok().map(|metadata| metadata.len());
+    let mut response = Body::from_stream(ReaderStream::new(file)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(SQLITE_CONTENT_TYPE),
+    );
+    // Names that aren't valid in a header are sent without a file name
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", db)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    if let Some(length) = length {
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+    }
+
+    response
+}
+
+// Handler for the "/about" route
+// Show where a database comes from: the OS it was built on, the paths that
+// were scanned, the dora version and when the scan ran
+pub async fn about(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let conn = match open_database(&db) {
+        Ok(conn) => conn,
+        Err(e) => return Html(format!("Failed to open database: {}", e)),
+    };
+
+    let about_html = match get_scan_info(&conn) {
+        Some(info) => format!(
+            "<ul>
+                <li><strong>Product:</strong> {} {} ({})</li>
+                <li><strong>Launch paths:</strong> {}</li>
+                <li><strong>Scanned folders:</strong> {}</li>
+                <li><strong>Dora version:</strong> {}</li>
+                <li><strong>Scanned at (UTC):</strong> {}</li>
+            </ul>",
+            html_escape(&info.product_name),
+            html_escape(&info.product_version),
+            html_escape(&info.build_version),
+            html_escape(&info.launch_paths),
+            html_escape(&info.scan_dirs),
+            html_escape(&info.dora_version),
+            html_escape(&info.scanned_at)
+        ),
+        None => "<p>No scan information recorded in this database.</p>".to_string(),
+    };
+
+    render_page(
+        &db_options,
+        &format!("{}{about_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/query" route
+// This route is used to query the database with a SQL query provided by the user
+// The user could submit:
+// • a service label as "service" key
+// • a binary path as "path" key
+// • an entitlement name as "entitlement" key
+// • a library name as "library" key
+// • a symbol name as "symbol" key, matched exactly when "exact" is checked and
+//   regardless of case unless "case_sensitive" is checked
+// • a combination of the above.
+// Results are sorted by label, by path when "sort" is "path" or naturally by
+// label (numbers by value) when it is "natural".
+// Search terms match literally unless "wildcards" is checked, in which case
+// "%"/"_" (entitlement, library, symbol) and "*"/"?"/"[...]" (label, path,
+// case-sensitive symbol) are honored.
+// Accepts GET as well, so that recent searches can be replayed from links.
+// Clients asking for JSON (see ResponseFormat) get the services found in each
+// database instead of the page.
+pub async fn query(
+    State(recent_queries): State<RecentQueries>,
+    headers: HeaderMap,
+    Form(input): Form<HashMap<String, String>>,
+) -> Response {
+    let format = ResponseFormat::negotiate(&headers, &input);
+    let databases = get_available_databases();
+
+    let db_options = database_options(&databases);
+
+    // Extract the query parameters from the input
+    let db = input.get("db").cloned().unwrap_or_default();
+    if db != ALL_DATABASES && !is_valid_db(&db) {
+        return match format {
+            ResponseFormat::Json => json_error(StatusCode::BAD_REQUEST, &invalid_db_message(&db)),
+            ResponseFormat::Html => negotiated(invalid_db_page(&db_options, &db)),
+        };
+    }
+
+    let search = SearchQuery::from_input(&input);
+    if !search.is_empty() {
+        remember_query(&recent_queries, RecentQuery::from_input(&input));
+    }
+
+    // Run the search against the chosen database, or against each of them for "all"
+    let selected_databases = if db == ALL_DATABASES {
+        databases
+    } else {
+        vec![db]
+    };
+
+    if format == ResponseFormat::Json {
+        if search.is_empty() {
+            return json_error(StatusCode::BAD_REQUEST, "No query parameters provided");
         }
-        None => "<h3>Libraries:</h3><p>Error retrieving libraries.</p>".to_string(),
+        let results: Vec<JsonValue> = selected_databases
+            .iter()
+            .map(|db| match search_database(db, &search) {
+                Some(Ok(results)) => results.json(db),
+                Some(Err(e)) => {
+                    eprintln!("Error searching services in {}: {}", db, e);
+                    json!({ "db": db, "error": "Error retrieving services" })
+                }
+                None => json!({ "db": db, "services": [] }),
+            })
+            .collect();
+        return negotiated(Json(json!({ "results": results })));
+    }
+
+    let results_html: String = selected_databases
+        .iter()
+        .map(|db| {
+            let services_html = match search_database(db, &search) {
+                Some(Ok(results)) => results.html(db),
+                Some(Err(e)) => {
+                    eprintln!("Error searching services in {}: {}", db, e);
+                    "<p>Error retrieving services.</p>".to_string()
+                }
+                None => "<p>No query parameters provided.</p>".to_string(),
+            };
+            format!("{}{services_html}", database_heading(db))
+        })
+        .collect();
+
+    let curl_html = if search.is_empty() {
+        String::new()
+    } else {
+        let mut params = RecentQuery::from_input(&input).0;
+        params.push(("format".to_string(), "json".to_string()));
+        curl_command_html(&headers, "/query", &params)
     };
 
-    // Get symbols for the service
-    let symbols_html = match get_symbols_by_label(&conn, &service_label) {
-        Some(symbols) => {
-            if symbols.is_empty() {
-                "<h3>Symbols:</h3><p>No symbols found for this service.</p>".to_string()
-            } else {
-                let symbols_count = symbols.len();
-                format!(
-                    "<h3>Symbols ({symbols_count})</h3><ul>{}</ul>",
-                    symbols
-                        .iter()
-                        .map(|s| format!("<li>{}</li>", s))
-                        .collect::<String>()
-                )
-            }
+    negotiated(render_page(
+        &db_options,
+        &format!("{results_html}{curl_html}"),
+    ))
+}
+
+// The parameters of "keys" set in "input", in order, empty ones left out
+fn present_params(input: &HashMap<String, String>, keys: &[&str]) -> Vec<(String, String)> {
+    keys.iter()
+        .filter_map(|key| {
+            input
+                .get(*key)
+                .filter(|value| !value.is_empty())
+                .map(|value| (key.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+// "curl" command getting the results of a page from its JSON counterpart, so
+// that a search made in the browser can be carried over to a script.
+// The address is the one the browser used, and with "--token" the secret is
+// left to a DORA_TOKEN variable rather than printed in the page.
+fn curl_command_html(headers: &HeaderMap, path: &str, params: &[(String, String)]) -> String {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map_or(format!("127.0.0.1:{}", LISTENING_PORT), String::from);
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{key}={}", utf8_percent_encode(value, QUERY_VALUE)))
+        .collect();
+    let url = format!("http://{host}{path}?{}", query.join("&"));
+    let authorization = if access_token().is_some() {
+        " -H \"Authorization: Bearer $DORA_TOKEN\""
+    } else {
+        ""
+    };
+
+    section(
+        "As JSON",
+        None,
+        &format!(
+            "<pre><code>{}</code></pre>",
+            html_escape(&format!("curl{authorization} {}", shell_quote(&url)))
+        ),
+    )
+}
+
+// Searches recently submitted to the "/query" route, newest first.
+// They are only kept in memory, for the lifetime of the server.
+pub type RecentQueries = Arc<Mutex<VecDeque<RecentQuery>>>;
+
+// Parameters of a search, in the order of RECENT_QUERY_KEYS, empty ones left out
+#[derive(PartialEq)]
+pub struct RecentQuery(Vec<(String, String)>);
+
+// Parameters of the "/query" route worth remembering
+const RECENT_QUERY_KEYS: &[&str] = &[
+    "db",
+    "service",
+    "path",
+    "entitlement",
+    "entitlement_value",
+    "library",
+    "symbol",
+    "exact",
+    "case_sensitive",
+    "wildcards",
+    "fuzzy",
+    "sort",
+];
+
+impl RecentQuery {
+    fn from_input(input: &HashMap<String, String>) -> Self {
+        RecentQuery(present_params(input, RECENT_QUERY_KEYS))
+    }
+
+    // Link running the search again
+    fn url(&self) -> String {
+        let params: Vec<String> = self
+            .0
+            .iter()
+            .map(|(key, value)| format!("{key}={}", utf8_percent_encode(value, QUERY_VALUE)))
+            .collect();
+        format!("/query?{}", params.join("&"))
+    }
+
+    // Text of the link, e.g. "dora_macOS_15.0_24A335.sqlite: symbol=fork, exact"
+    fn summary(&self) -> String {
+        let db = self
+            .0
+            .iter()
+            .find(|(key, _)| key == "db")
+            .map_or("", |(_, value)| value.as_str());
+        let fields: Vec<String> = self
+            .0
+            .iter()
+            .filter(|(key, _)| key != "db")
+            .map(|(key, value)| match key.as_str() {
+                // Checkboxes are submitted with a meaningless "on" value
+                "exact" | "case_sensitive" | "wildcards" => key.clone(),
+                _ => format!("{key}={value}"),
+            })
+            .collect();
+        format!("{db}: {}", fields.join(", "))
+    }
+}
+
+// Put "query" at the top of the recent searches, moving it there if it was
+// already run, and forget the oldest ones past MAX_RECENT_QUERIES
+fn remember_query(recent_queries: &RecentQueries, query: RecentQuery) {
+    let mut recent = recent_queries.lock().unwrap();
+    recent.retain(|other| *other != query);
+    recent.push_front(query);
+    recent.truncate(MAX_RECENT_QUERIES);
+}
+
+// Search fields submitted to the "/query" route
+struct SearchQuery {
+    service: String,
+    path: String,
+    entitlement: String,
+    entitlement_value: String,
+    library: String,
+    symbol: String,
+    exact: bool,
+    case_sensitive: bool,
+    wildcards: bool,
+    // Rank labels by fuzzy similarity instead of matching them as a pattern
+    fuzzy: bool,
+    sort: SortOrder,
+}
+
+impl SearchQuery {
+    fn from_input(input: &HashMap<String, String>) -> Self {
+        let field = |key: &str| input.get(key).cloned().unwrap_or_default();
+
+        SearchQuery {
+            service: field("service"),
+            path: field("path"),
+            entitlement: field("entitlement"),
+            entitlement_value: field("entitlement_value"),
+            library: field("library"),
+            symbol: field("symbol"),
+            // Unchecked checkboxes are not submitted at all
+            exact: input.contains_key("exact"),
+            case_sensitive: input.contains_key("case_sensitive"),
+            wildcards: input.contains_key("wildcards"),
+            fuzzy: input.contains_key("fuzzy"),
+            sort: SortOrder::from_param(input.get("sort").map(String::as_str)),
         }
-        None => "<h3>Symbols:</h3><p>Error retrieving symbols.</p>".to_string(),
+    }
+
+    // Whether no search field was filled in
+    fn is_empty(&self) -> bool {
+        [
+            &self.service,
+            &self.path,
+            &self.entitlement,
+            &self.entitlement_value,
+            &self.library,
+            &self.symbol,
+        ]
+        .iter()
+        .all(|field| field.is_empty())
+    }
+}
+
+// Run the search matching the submitted fields against a single database.
+// None when no search field was filled in.
+fn search_database(
+    db: &str,
+    search: &SearchQuery,
+) -> Option<Result<SearchResults, rusqlite::Error>> {
+    let SearchQuery {
+        service,
+        path,
+        entitlement,
+        entitlement_value,
+        library,
+        symbol,
+        exact,
+        case_sensitive,
+        wildcards,
+        fuzzy,
+        sort,
+    } = search;
+    let (wildcards, sort) = (*wildcards, *sort);
+    let symbol_match = SymbolMatch {
+        exact: *exact,
+        wildcards,
+        case_sensitive: *case_sensitive,
     };
 
-    // Combine all HTML parts
-    let html = format!(
-        r#"<html>
-            {HTML_HEADER}
-            <body>
-                {HTML_BODY_TITLE}
-                <form action="/query" method="post">
-                    <label for="db">Choose database:</label>
-                    <select name="db" id="db">
-                        {db_options}
-                    </select>
-                    {HTML_FORM_FIELDS}
-                </form>
-                <h2>Using: {db}</h2>
-                <p>{service_html}</p>
-                <p>{mach_services_html}</p>
-                <p>{entitlements_html}</p>
-                <p>{libraries_html}</p>
-                <p>{symbols_html}</p>
-            </body>
-        </html>"#
+    // The first submitted field picks the query. Searches by entitlement can be
+    // narrowed down by value or symbol, and searches by library by symbol.
+    let services = if !service.is_empty() && *fuzzy {
+        get_services_by_label_fuzzy(db, service)
+    } else if !service.is_empty() {
+        get_services_by_label_pattern(db, service, wildcards, sort)
+    } else if !path.is_empty() {
+        get_services_by_path_pattern(db, path, wildcards, sort)
+    } else if !entitlement_value.is_empty() {
+        get_services_by_entitlement_value(db, entitlement, entitlement_value, wildcards, sort)
+    } else if !entitlement.is_empty() && !symbol.is_empty() {
+        get_services_by_entitlement_and_symbol(db, entitlement, symbol, symbol_match, sort)
+    } else if !entitlement.is_empty() {
+        get_services_by_entitlement(db, entitlement, wildcards, sort)
+    } else if !library.is_empty() && !symbol.is_empty() {
+        get_services_by_symbol_and_library(db, symbol, library, symbol_match, sort)
+    } else if !library.is_empty() {
+        get_services_by_library(db, library, wildcards, sort)
+    } else if !symbol.is_empty() {
+        get_services_by_symbol(db, symbol, symbol_match, sort)
+    } else {
+        return None;
+    };
+
+    Some(services)
+}
+
+// Quote "arg" for a POSIX shell when it holds anything but safe characters
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+,:@%".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Commands re-running the tools dora used on the binary of a service, to
+// check its summary by hand
+fn tool_commands_html(path: &str, binary_type: Option<&str>) -> String {
+    let path = shell_quote(path);
+    let commands = if binary_type == Some(BINARY_TYPE_SCRIPT) {
+        vec![format!("cat {path}")]
+    } else {
+        vec![
+            format!("codesign -dv --entitlements :- {path}"),
+            format!("otool -L {path}"),
+            format!("nm -u {path}"),
+        ]
+    };
+
+    section(
+        "Verify with",
+        None,
+        &format!(
+            "<pre><code>{}</code></pre>",
+            html_escape(&commands.join("\n"))
+        ),
+    )
+}
+
+// Collapsible section of a service page, open by default. "count" is the
+// number of entries listed, when there are any.
+fn section(title: &str, count: Option<usize>, body: &str) -> String {
+    let count = count.map_or(String::new(), |count| format!(" ({count})"));
+    format!("<details open><summary><h3>{title}{count}</h3></summary>{body}</details>")
+}
+
+// Sortable table of already escaped cells: callers pass values through
+// html_escape (or highlight) and only add their own markup around them
+fn table(headers: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let cells = |tag: &str, cells: &[String]| {
+        cells
+            .iter()
+            .map(|cell| format!("<{tag}>{cell}</{tag}>"))
+            .collect::<String>()
+    };
+    let headers: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    format!(
+        "<table class=\"sortable\"><thead><tr>{}</tr></thead><tbody>{}</tbody></table>",
+        cells("th", &headers),
+        rows.map(|row| format!("<tr>{}</tr>", cells("td", &row)))
+            .collect::<String>()
+    )
+}
+
+// Render the details of a single service: its columns followed by its Mach
+// services, environment variables, entitlements, libraries and symbols.
+// "term" is the search that led to the service: its occurrences are marked
+// in the entitlement, library and symbol lists.
+fn service_details(
+    db: &str,
+    conn: &rusqlite::Connection,
+    info: ServiceInfo,
+    term: Option<&str>,
+) -> String {
+    // List the KeepAlive conditions next to the keep alive flag
+    let keep_alive = match info
+        .keep_alive_conditions
+        .as_deref()
+        .and_then(|conditions| serde_json::from_str::<JsonValue>(conditions).ok())
+    {
+        Some(JsonValue::Object(conditions)) => format!(
+            "{} (when: {})",
+            info.keep_alive,
+            conditions
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        _ => info.keep_alive,
+    };
+
+    // Values are escaped where they are rendered, but the path which carries
+    // its own markup
+    let rows = [
+        ("Service", info.label.clone()),
+        ("Source", info.source.clone()),
+        ("Kind", info.kind.clone().unwrap_or("NULL".to_string())),
+        (
+            "Path",
+            match info.program.as_deref() {
+                Some(program) if info.path.starts_with('/') => format!(
+                    "{} <strong>(relative program {} resolved against WorkingDirectory)</strong>",
+                    html_escape(&info.path),
+                    html_escape(program)
+                ),
+                Some(_) => format!(
+                    "{} <strong>(relative program without WorkingDirectory: unresolved)</strong>",
+                    html_escape(&info.path)
+                ),
+                None => html_escape(&info.path),
+            },
+        ),
+        (
+            "Application",
+            match (info.bundle_path.as_deref(), info.bundle_id.as_deref()) {
+                (Some(bundle_path), Some(bundle_id)) => format!("{} ({})", bundle_id, bundle_path),
+                (Some(bundle_path), None) => bundle_path.to_string(),
+                (None, _) => "NULL".to_string(),
+            },
+        ),
+        (
+            "Binary type",
+            match (info.binary_type.as_deref(), info.interpreter.as_deref()) {
+                (Some(binary_type), Some(interpreter)) => {
+                    format!("{} ({})", binary_type, interpreter)
+                }
+                (Some(binary_type), None) => binary_type.to_string(),
+                (None, _) => "NULL".to_string(),
+            },
+        ),
+        (
+            "Risk score",
+            match (info.risk_score, info.risk_factors.as_deref()) {
+                (Some(score), Some(factors)) if !factors.is_empty() => {
+                    format!("{} ({})", score, factors.replace(',', ", "))
+                }
+                (Some(score), _) => score.to_string(),
+                (None, _) => "NULL".to_string(),
+            },
+        ),
+        (
+            "Third party",
+            info.third_party
+                .map_or("NULL", |third_party| if third_party { "yes" } else { "no" })
+                .to_string(),
+        ),
+        ("Run as user", info.run_as_user.clone()),
+        (
+            "Run as group",
+            info.run_as_group.clone().unwrap_or("NULL".to_string()),
+        ),
+        (
+            "Session types",
+            info.session_types.clone().unwrap_or("NULL".to_string()),
+        ),
+        ("Run at load", info.run_at_load.clone()),
+        ("Keep alive", keep_alive),
+        ("Disabled", info.disabled.clone()),
+        (
+            "Plist path",
+            match info.plist_target.as_deref() {
+                Some(target) => format!("{} (symlink to {})", info.plist_path, target),
+                None => info.plist_path.clone(),
+            },
+        ),
+        (
+            "Architectures",
+            match (info.arch.as_deref(), info.is_32bit_only) {
+                (Some(arch), Some(true)) => format!("{} (32-bit only)", arch),
+                (Some(arch), _) => arch.to_string(),
+                (None, _) => "NULL".to_string(),
+            },
+        ),
+        (
+            "Loads code at runtime",
+            info.uses_dynamic_loading
+                .map_or("NULL", |dynamic| if dynamic { "yes" } else { "no" })
+                .to_string(),
+        ),
+        (
+            "Restricted",
+            info.is_restricted
+                .map_or("NULL", |restricted| if restricted { "yes" } else { "no" })
+                .to_string(),
+        ),
+        (
+            "Entitlements",
+            info.entitlements_status
+                .clone()
+                .unwrap_or("NULL".to_string()),
+        ),
+        (
+            "Code signing flags",
+            match info.codesign_flags.as_deref() {
+                Some("") => "none",
+                Some(flags) => flags,
+                None => "NULL",
+            }
+            .to_string(),
+        ),
+        ("Team", info.team_id.clone().unwrap_or("NULL".to_string())),
+        (
+            "Platform identifier",
+            info.platform_identifier
+                .map_or("NULL".to_string(), |platform| platform.to_string()),
+        ),
+        (
+            "Minimum OS version",
+            info.min_os.clone().unwrap_or("NULL".to_string()),
+        ),
+        (
+            "SDK version",
+            info.sdk.clone().unwrap_or("NULL".to_string()),
+        ),
+    ];
+    let service_html = format!(
+        "<table class=\"details\"><tbody>{}</tbody></table>",
+        rows.iter()
+            .map(|(name, value)| {
+                let value = if *name == "Path" {
+                    value.clone()
+                } else {
+                    html_escape(value)
+                };
+                format!("<tr><th>{}</th><td>{}</td></tr>", name, value)
+            })
+            .collect::<String>()
+    );
+
+    // Get Mach services for the service
+    let mach_services_html = match get_mach_service_by_label(conn, &info.label, &info.source) {
+        Some(mach_services) if mach_services.is_empty() => section(
+            "Mach Services",
+            None,
+            "<p>No Mach services found for this service.</p>",
+        ),
+        Some(mach_services) => section(
+            "Mach Services",
+            Some(mach_services.len()),
+            &table(
+                &["Name", "Value", "HostSpecialPort"],
+                mach_services
+                    .iter()
+                    .map(|(name, value, host_special_port)| {
+                        vec![
+                            html_escape(name),
+                            html_escape(value),
+                            host_special_port
+                                .map_or(String::new(), |port| format!("<strong>{}</strong>", port)),
+                        ]
+                    }),
+            ),
+        ),
+        None => section(
+            "Mach Services",
+            None,
+            "<p>Error retrieving Mach services.</p>",
+        ),
+    };
+
+    // Get environment variables for the service
+    let env_vars_html = match get_env_vars_by_label(conn, &info.label, &info.source) {
+        Some(env_vars) if env_vars.is_empty() => section(
+            "Environment variables",
+            None,
+            "<p>No environment variables found for this service.</p>",
+        ),
+        Some(env_vars) => section(
+            "Environment variables",
+            Some(env_vars.len()),
+            &table(
+                &["Name", "Value"],
+                env_vars.iter().map(|(name, value)| {
+                    if name.starts_with("DYLD_") {
+                        vec![
+                            format!("<strong>{}</strong>", html_escape(name)),
+                            format!("<strong>{}</strong>", html_escape(value)),
+                        ]
+                    } else {
+                        vec![html_escape(name), html_escape(value)]
+                    }
+                }),
+            ),
+        ),
+        None => section(
+            "Environment variables",
+            None,
+            "<p>Error retrieving environment variables.</p>",
+        ),
+    };
+
+    // Get entitlements for the service. A binary without any was either
+    // signed without entitlements or couldn't be read.
+    let entitlements_html = match info.entitlements_status.as_deref() {
+        Some(ENTITLEMENTS_NONE) => section(
+            "Entitlements",
+            None,
+            "<p>The binary is signed without entitlements.</p>",
+        ),
+        Some(ENTITLEMENTS_ERROR) => section(
+            "Entitlements",
+            None,
+            "<p>The entitlements of the binary couldn't be read: it is unsigned, unreadable or codesign failed on it.</p>",
+        ),
+        _ => match get_entitlements_value_by_service_label(conn, &info.label, &info.source) {
+            Some(entitlements) if entitlements.is_empty() => section(
+                "Entitlements",
+                None,
+                "<p>No entitlements found for this service.</p>",
+            ),
+            Some(entitlements) => section(
+                "Entitlements",
+                Some(entitlements.len()),
+                &table(
+                    &["Entitlement", "Value"],
+                    entitlements
+                        .iter()
+                        .map(|(k, v)| vec![highlight(k, term), highlight(v, term)]),
+                ),
+            ),
+            None => section(
+                "Entitlements",
+                None,
+                "<p>Error retrieving entitlements.</p>",
+            ),
+        },
+    };
+
+    // Get libraries for the service
+    let libraries_html = match get_libraries_by_label(conn, &info.label, &info.source) {
+        Some(libraries) if libraries.is_empty() => section(
+            "Libraries",
+            None,
+            "<p>No libraries found for this service.</p>",
+        ),
+        Some(libraries) => section(
+            "Libraries",
+            Some(libraries.len()),
+            &table(
+                &["Name", "Path", "Shared cache", "Identifier", "Team"],
+                libraries.iter().map(|library| {
+                    vec![
+                        highlight(&library.name, term),
+                        highlight(&library.path, term),
+                        if library.in_shared_cache == Some(true) {
+                            "<strong>yes</strong>".to_string()
+                        } else {
+                            String::new()
+                        },
+                        html_escape(&library.identifier),
+                        html_escape(&library.team_id),
+                    ]
+                }),
+            ),
+        ),
+        None => section("Libraries", None, "<p>Error retrieving libraries.</p>"),
+    };
+
+    // Get symbols for the service
+    let symbols_html = match get_symbols_by_label(conn, &info.label, &info.source) {
+        Some(symbols) if symbols.is_empty() => {
+            section("Symbols", None, "<p>No symbols found for this service.</p>")
+        }
+        Some(symbols) => section(
+            "Symbols",
+            Some(symbols.len()),
+            &format!(
+                "<ul>{}</ul>",
+                symbols
+                    .iter()
+                    .map(|(name, demangled)| match demangled {
+                        // Readable name, with the raw one on hover
+                        Some(demangled) => format!(
+                            "<li title=\"{}\">{}</li>",
+                            html_escape(name),
+                            highlight(demangled, term)
+                        ),
+                        None => format!("<li>{}</li>", highlight(name, term)),
+                    })
+                    .collect::<String>()
+            ),
+        ),
+        None => section("Symbols", None, "<p>Error retrieving symbols.</p>"),
+    };
+
+    let commands_html = tool_commands_html(&info.path, info.binary_type.as_deref());
+    let review_html = review_form(db, conn, &info.label, &info.source);
+
+    format!(
+        "<section class=\"service\">
+        {service_html}
+        {review_html}
+        {commands_html}
+        {mach_services_html}
+        {env_vars_html}
+        {entitlements_html}
+        {libraries_html}
+        {symbols_html}
+        </section>"
+    )
+}
+
+// Review status of a service with the form changing it
+fn review_form(db: &str, conn: &rusqlite::Connection, label: &str, source: &str) -> String {
+    let review = get_review_by_label(conn, label, source);
+    let current = review.as_ref().map(|(status, _, _)| status.as_str());
+
+    let (badge, updated) = match &review {
+        Some((status, _, updated_at)) => (
+            review_badge(status),
+            format!("<p>Last updated: {}</p>", html_escape(updated_at)),
+        ),
+        None => (String::new(), String::new()),
+    };
+    let options: String = std::iter::once(("", "not reviewed"))
+        .chain(REVIEW_STATUSES.iter().map(|status| (*status, *status)))
+        .map(|(value, text)| {
+            let selected = if current.unwrap_or("") == value {
+                " selected"
+            } else {
+                ""
+            };
+            format!(r#"<option value="{value}"{selected}>{text}</option>"#)
+        })
+        .collect();
+    let note = review
+        .as_ref()
+        .and_then(|(_, note, _)| note.as_deref())
+        .unwrap_or("");
+
+    let form = format!(
+        r#"{updated}
+        <form action="/review" method="post">
+            <input type="hidden" name="db" value="{}">
+            <input type="hidden" name="label" value="{}">
+            <input type="hidden" name="source" value="{}">
+            <select name="status">{options}</select>
+            <input type="text" name="note" size="60" placeholder="Note" value="{}">
+            <button type="submit">Save</button>
+        </form>"#,
+        html_escape(db),
+        html_escape(label),
+        html_escape(source),
+        html_escape(note)
+    );
+
+    section(&format!("Review{badge}"), None, &form)
+}
+
+// Handler for the "/review" route
+// Set the review status and note of a service ("status" empty to clear them),
+// then go back to its page
+pub async fn review(Form(input): Form<HashMap<String, String>>) -> Response {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let field = |key: &str| input.get(key).map(String::as_str).unwrap_or_default();
+    let (db, label, source) = (field("db"), field("label"), field("source"));
+    if !is_valid_db(db) || !databases.iter().any(|available| available == db) {
+        return invalid_db_page(&db_options, db).into_response();
+    }
+
+    let status = match field("status") {
+        "" => None,
+        status if REVIEW_STATUSES.contains(&status) => Some(status),
+        status => {
+            return (
+                StatusCode::BAD_REQUEST,
+                render_page(
+                    &db_options,
+                    &format!("<p>Invalid review status: {}</p>", html_escape(status)),
+                ),
+            )
+                .into_response();
+        }
+    };
+
+    if let Err(e) = set_review(db, label, source, status, field("note")) {
+        eprintln!("Error saving the review of {}: {}", label, e);
+        // Databases built before the review table existed lack it
+        let hint = if e.is_fatal() {
+            " Databases built by older versions may need a \"dora migrate\"."
+        } else {
+            ""
+        };
+        return render_page(
+            &db_options,
+            &format!(
+                "<p>Error saving the review of {}: {}.{hint}</p>",
+                html_escape(label),
+                html_escape(&e.to_string())
+            ),
+        )
+        .into_response();
+    }
+
+    Redirect::to(&format!(
+        "/service?db={}&label={}",
+        utf8_percent_encode(db, QUERY_VALUE),
+        utf8_percent_encode(label, QUERY_VALUE)
+    ))
+    .into_response()
+}
+
+// Weak ETag of a service page. The page only changes with the database file
+// (its write-ahead log included), the query parameters, the databases listed
+// in the search form and the dora version.
+fn service_etag(db: &str, input: &HashMap<String, String>, databases: &[String]) -> Option<String> {
+    let modified = |path: &str| {
+        std::fs::metadata(path)
+            .ok()
+            .map(|metadata| (metadata.modified().ok(), metadata.len()))
+    };
+
+    let mut hasher = DefaultHasher::new();
+    modified(db)?.hash(&mut hasher);
+    modified(&format!("{db}-wal")).hash(&mut hasher);
+    let mut params: Vec<_> = input.iter().collect();
+    params.sort();
+    params.hash(&mut hasher);
+    databases.hash(&mut hasher);
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+
+    Some(format!("W/\"{:016x}\"", hasher.finish()))
+}
+
+// Whether an "If-None-Match" header matches "etag", with the weak comparison
+// used for GET requests
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || weak(tag) == weak(etag))
+}
+
+// For a given service label, get all entitlements, libraries, symbols and mach services associated with it.
+// Pages carry an ETag: a browser revalidating one gets a 304 as long as the
+// database didn't change. Clients asking for JSON get the services as
+// "/api/services/batch" describes them, without ETag.
+// The route is a GET: "db" and "label" come from the query string, as built by
+// the links of the search results.
+pub async fn service(headers: HeaderMap, Query(input): Query<HashMap<String, String>>) -> Response {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    // Extract the query parameters from the input
+    let db = input.get("db").cloned().unwrap_or_default();
+    let service_label = input.get("label").cloned().unwrap_or_default();
+    if ResponseFormat::negotiate(&headers, &input) == ResponseFormat::Json {
+        return service_json(&db, &service_label);
+    }
+    if !is_valid_db(&db) {
+        return negotiated(invalid_db_page(&db_options, &db));
+    }
+
+    let etag = service_etag(&db, &input, &databases);
+    if let Some(etag) = &etag
+        && etag_matches(&headers, etag)
+    {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        if let Ok(value) = HeaderValue::from_str(etag) {
+            response.headers_mut().insert(header::ETAG, value);
+        }
+        return negotiated(response);
+    }
+
+    let term = input.get("highlight").map(String::as_str);
+
+    let conn = match open_database(&db) {
+        Ok(conn) => conn,
+        Err(e) => return negotiated(Html(format!("Failed to open database: {}", e))),
+    };
+
+    // A launchd job and a binary may share a label: show all of them
+    let service_html = match get_service_by_label(&conn, &service_label) {
+        Some(services) => {
+            let export_url = format!(
+                "/service/export?db={}&label={}",
+                utf8_percent_encode(&db, QUERY_VALUE),
+                utf8_percent_encode(&service_label, QUERY_VALUE)
+            );
+            format!(
+                r#"<p>Export: <a href="{export_url}">JSON</a> | <a href="{export_url}&format=plist">plist</a></p>{}"#,
+                services
+                    .into_iter()
+                    .map(|info| service_details(&db, &conn, info, term))
+                    .collect::<String>()
+            )
+        }
+        None => {
+            format!(
+                "<h2>Service: {}</h2>
+                <p>No service found with label: {}</p>",
+                html_escape(&service_label),
+                html_escape(&service_label)
+            )
+        }
+    };
+
+    let mut response = render_page(
+        &db_options,
+        &format!("{}{service_html}", database_heading(&db)),
+    )
+    .into_response();
+    if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+        let headers = response.headers_mut();
+        headers.insert(header::ETAG, value);
+        // Let browsers keep the page but check it is still current first
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    }
+
+    negotiated(response)
+}
+
+// JSON answer of the "/service" route: every service of the label, 404 when
+// there is none
+fn service_json(db: &str, label: &str) -> Response {
+    if !is_valid_db(db) {
+        return json_error(StatusCode::BAD_REQUEST, &invalid_db_message(db));
+    }
+
+    match get_services_json_by_labels(db, &[label.to_string()]) {
+        Ok(mut services) => match services.remove(label) {
+            Some(services @ JsonValue::Array(_)) => negotiated(Json(
+                json!({ "db": db, "label": label, "services": services }),
+            )),
+            _ => json_error(
+                StatusCode::NOT_FOUND,
+                &format!("No service found with label: {}", label),
+            ),
+        },
+        Err(e) => {
+            eprintln!("Error retrieving service {}: {}", label, e);
+            json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error retrieving service",
+            )
+        }
+    }
+}
+
+// Handler for the "/service/export" route
+// Send everything stored about the services of a label as one file, e.g. to
+// attach it to a bug report: their fields, Mach services, entitlements,
+// environment variables, libraries and symbols, and where the database comes
+// from. JSON by default, an XML plist with "format=plist".
+pub async fn service_export(Query(input): Query<HashMap<String, String>>) -> Response {
+    let db = input.get("db").cloned().unwrap_or_default();
+    let label = input.get("label").cloned().unwrap_or_default();
+    if !is_valid_db(&db) || !get_available_databases().contains(&db) {
+        return (StatusCode::BAD_REQUEST, invalid_db_message(&db)).into_response();
+    }
+
+    let bundle = match get_service_bundle(&db, &label) {
+        Ok(Some(bundle)) => bundle,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("No service found with label: {}", label),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            eprintln!("Error exporting service {}: {}", label, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Error exporting service").into_response();
+        }
+    };
+
+    let (body, content_type, extension) = if input.get("format").map(String::as_str)
+        == Some("plist")
+    {
+        let mut xml = Vec::new();
+        if let Err(e) = json_to_plist(&bundle)
+            .unwrap_or(plist::Value::Dictionary(plist::Dictionary::new()))
+            .to_writer_xml(&mut xml)
+        {
+            eprintln!("Error exporting service {}: {}", label, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Error exporting service").into_response();
+        }
+        (xml, PLIST_CONTENT_TYPE, "plist")
+    } else {
+        let json = serde_json::to_vec_pretty(&bundle).unwrap_or_default();
+        (json, "application/json", "json")
+    };
+
+    let mut response = body.into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    // Labels may hold quotes or slashes: keep the file name to safe characters
+    let file_name: String = label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if let Ok(disposition) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}.{}\"",
+        file_name, extension
+    )) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    response
+}
+
+// Parameters of the "/services" route that "/api/services" takes as well
+const SERVICES_PARAM_KEYS: &[&str] = &[
+    "db",
+    "disabled",
+    "run_at_load",
+    "keep_alive",
+    "dyld_env",
+    "arm64",
+    "session_type",
+    "third_party",
+    "sort",
+];
+
+// Read the "/services" and "/api/services" filters from the query parameters
+fn service_filters(input: &HashMap<String, String>) -> ServiceFilters {
+    ServiceFilters {
+        disabled: parse_bool_param(input, "disabled"),
+        run_at_load: parse_bool_param(input, "run_at_load"),
+        keep_alive: parse_bool_param(input, "keep_alive"),
+        dyld_env: parse_bool_param(input, "dyld_env"),
+        arm64: parse_bool_param(input, "arm64"),
+        session_type: input
+            .get("session_type")
+            .map(|session_type| session_type.trim().to_string())
+            .filter(|session_type| !session_type.is_empty()),
+        third_party: parse_bool_param(input, "third_party"),
+    }
+}
+
+// Pagination of a browse route from its "page" and "per_page" parameters.
+// Its links keep the other parameters, but the "--token" secret.
+fn pagination(path: &str, input: &HashMap<String, String>) -> Pagination {
+    let mut params: Vec<(&String, &String)> = input
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "page" | "token"))
+        .collect();
+    params.sort();
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(key, QUERY_VALUE),
+                utf8_percent_encode(value, QUERY_VALUE)
+            )
+        })
+        .collect();
+
+    Pagination::from_params(
+        input.get("page").map(String::as_str),
+        input.get("per_page").map(String::as_str),
+        format!("{path}?{}", query.join("&")),
+    )
+}
+
+// Handler for the "/services" route
+// Browse all services of a database, optionally filtered by:
+// • "disabled": whether the plist marks the service as disabled by default
+// • "run_at_load": whether launchd starts the service as soon as it is loaded
+// • "keep_alive": whether launchd keeps the service running
+// • "dyld_env": whether the service sets any "DYLD_*" environment variable
+// • "arm64": whether the binary has an arm64 slice, "arm64=false" listing
+//   Intel-only binaries
+// • "session_type": a session the job is limited to by its
+//   "LimitLoadToSessionType", e.g. "LoginWindow"
+// • "third_party": whether the plist or binary lies in a folder of
+//   third-party software ("/Library/LaunchDaemons", "/usr/local", ...)
+// Services are listed by label, by path with "sort=path" or naturally by
+// label with "sort=natural", "per_page" at a time ("page" from 1).
+// Clients asking for JSON get the answer of "/api/services".
+pub async fn services(
+    headers: HeaderMap,
+    Query(input): Query<HashMap<String, String>>,
+) -> Response {
+    if ResponseFormat::negotiate(&headers, &input) == ResponseFormat::Json {
+        return negotiated(api_services(Query(input)).await);
+    }
+
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return negotiated(invalid_db_page(&db_options, &db));
+    }
+
+    let filters = service_filters(&input);
+    let sort = SortOrder::from_param(input.get("sort").map(String::as_str));
+
+    let pagination = pagination("/services", &input);
+
+    let services_html = get_services(&db, &filters, sort, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving services: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+    let curl_html = curl_command_html(
+        &headers,
+        "/api/services",
+        &present_params(&input, SERVICES_PARAM_KEYS),
     );
 
-    Html(html)
+    negotiated(render_page(
+        &db_options,
+        &format!("{}{services_html}{curl_html}", database_heading(&db)),
+    ))
+}
+
+// Handler for the "/overprivileged" route
+// List services holding a powerful entitlement but importing none of the symbols
+// associated with using it, according to the entitlement to symbol mapping
+pub async fn overprivileged(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let services_html =
+        get_overprivileged_services(&db, entitlement_symbols()).unwrap_or_else(|e| {
+            eprintln!("Error retrieving over-privileged services: {}", e);
+            "<p>Error retrieving services.</p>".to_string()
+        });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/origin" route
+// List services signed by Apple ("origin=apple", the default) or by a
+// third-party team ("origin=thirdparty")
+pub async fn origin(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let origin_param = input.get("origin").map(String::as_str).unwrap_or("apple");
+    let Some(origin) = SigningOrigin::from_param(origin_param) else {
+        return render_page(
+            &db_options,
+            &format!(
+                "<p>Invalid origin: {} (apple or thirdparty)</p>",
+                html_escape(origin_param)
+            ),
+        );
+    };
+    let sort = SortOrder::from_param(input.get("sort").map(String::as_str));
+
+    // Link to the other origin
+    let (other, other_text) = match origin {
+        SigningOrigin::Apple => ("thirdparty", "third-party services"),
+        SigningOrigin::ThirdParty => ("apple", "Apple services"),
+    };
+    let toggle = format!(
+        "<p><a href=\"/origin?db={}&origin={other}\">Show {other_text}</a></p>",
+        utf8_percent_encode(&db, QUERY_VALUE)
+    );
+
+    let pagination = pagination("/origin", &input);
+
+    let services_html = get_services_by_signing_origin(&db, origin, sort, &pagination)
+        .unwrap_or_else(|e| {
+            eprintln!("Error retrieving services by signing origin: {}", e);
+            "<p>Error retrieving services.</p>".to_string()
+        });
+
+    render_page(
+        &db_options,
+        &format!("{}{toggle}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/by-flag" route
+// List services whose code signature has a CodeDirectory flag (e.g. "runtime"
+// for the hardened runtime, "library-validation"), or lacks it with "present=false"
+pub async fn by_flag(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let flag = input.get("flag").cloned().unwrap_or_default();
+    if flag.is_empty() {
+        return render_page(&db_options, "<p>Missing flag parameter</p>");
+    }
+    let present = parse_bool_param(&input, "present").unwrap_or(true);
+    let sort = SortOrder::from_param(input.get("sort").map(String::as_str));
+
+    let pagination = pagination("/by-flag", &input);
+
+    let services_html = get_services_by_codesign_flag(&db, &flag, present, sort, &pagination)
+        .unwrap_or_else(|e| {
+            eprintln!("Error retrieving services by code signing flag: {}", e);
+            "<p>Error retrieving services.</p>".to_string()
+        });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/dangerous-symbols" route
+// List services importing symbols of the dangerous symbols list, grouped by symbol
+pub async fn dangerous_symbols_services(
+    Query(input): Query<HashMap<String, String>>,
+) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+    let sort = SortOrder::from_param(input.get("sort").map(String::as_str));
+
+    let pagination = pagination("/dangerous-symbols", &input);
+
+    let services_html = get_dangerous_symbol_services(&db, dangerous_symbols(), sort, &pagination)
+        .unwrap_or_else(|e| {
+            eprintln!("Error retrieving services by dangerous symbol: {}", e);
+            "<p>Error retrieving services.</p>".to_string()
+        });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/writable-daemons" route
+// List services whose binary could be replaced by a non-root user,
+// root services first
+pub async fn writable_daemons(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/writable-daemons", &input);
+
+    let services_html = get_writable_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving writable services: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/scripts" route
+// List services launching a script rather than a Mach-O binary
+pub async fn scripts(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/scripts", &input);
+
+    let services_html = get_script_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving script services: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/dynamic-loaders" route
+// List services whose binary loads code at runtime (dlopen, dlsym, NSAddImage)
+pub async fn dynamic_loaders(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/dynamic-loaders", &input);
+
+    let services_html = get_dynamic_loaders(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving dynamic loaders: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/unrestricted-root" route
+// List root services whose binary dyld doesn't restrict, the ones
+// DYLD_INSERT_LIBRARIES may inject into
+pub async fn unrestricted_root(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/unrestricted-root", &input);
+
+    let services_html = get_unrestricted_root_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving unrestricted root services: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/thirtytwobit" route
+// List services whose binary has no 64-bit slice
+pub async fn thirty_two_bit(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/thirtytwobit", &input);
+
+    let services_html = get_thirty_two_bit_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving 32-bit services: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/top-risk" route
+// List services by risk score, highest first, as a triage list
+pub async fn top_risk(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/top-risk", &input);
+
+    let services_html = get_top_risk_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving risk scores: {}", e);
+        "<p>Error retrieving services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/phantom-libraries" route
+// List the libraries imported by services but found neither on disk nor in
+// the dyld shared cache
+pub async fn phantom_libraries(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let libraries_html = get_phantom_libraries(&db).unwrap_or_else(|e| {
+        eprintln!("Error retrieving phantom libraries: {}", e);
+        "<p>Error retrieving libraries.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{libraries_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/xpc-services" route
+// List the XPC services embedded in bundles, with a link to the service of
+// their executable
+pub async fn xpc_services(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let pagination = pagination("/xpc-services", &input);
+
+    let xpc_services_html = get_xpc_services(&db, &pagination).unwrap_or_else(|e| {
+        eprintln!("Error retrieving XPC services: {}", e);
+        "<p>Error retrieving XPC services.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{xpc_services_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/new-since" route
+// List the entitlements, libraries and symbols first seen at or after "date"
+pub async fn new_since(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let date = input.get("date").cloned().unwrap_or_default();
+    if date.is_empty() {
+        return render_page(&db_options, "<p>Missing date parameter</p>");
+    }
+
+    let items_html = get_new_since(&db, &date).unwrap_or_else(|e| {
+        eprintln!("Error retrieving new items: {}", e);
+        "<p>Error retrieving new items.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{items_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/entitlement-tree" route
+// Browse the entitlement namespace: entitlements grouped by dotted prefix
+pub async fn entitlement_tree(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let tree_html = get_entitlement_tree(&db).unwrap_or_else(|e| {
+        eprintln!("Error retrieving entitlement tree: {}", e);
+        "<p>Error retrieving entitlements.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{tree_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/closure" route
+// List the libraries a service depends on, directly or through other
+// libraries. "depth" caps the number of imports followed.
+pub async fn closure(Query(input): Query<HashMap<String, String>>) -> Html<String> {
+    let databases = get_available_databases();
+    let db_options = database_options(&databases);
+
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return invalid_db_page(&db_options, &db);
+    }
+
+    let label = input.get("label").cloned().unwrap_or_default();
+    let depth = input
+        .get("depth")
+        .and_then(|depth| depth.parse::<u32>().ok())
+        .unwrap_or(CLOSURE_DEFAULT_DEPTH)
+        .clamp(1, CLOSURE_MAX_DEPTH);
+
+    let closure_html = get_library_closure(&db, &label, depth).unwrap_or_else(|e| {
+        eprintln!("Error retrieving dependency closure: {}", e);
+        "<p>Error retrieving dependency closure.</p>".to_string()
+    });
+
+    render_page(
+        &db_options,
+        &format!("{}{closure_html}", database_heading(&db)),
+    )
+}
+
+// Handler for the "/api/services" route
+// JSON version of "/services", taking the same filters and "sort".
+// "limit" and "offset" select a page of the results, while "total" always
+// counts every matching service.
+pub async fn api_services(
+    Query(input): Query<HashMap<String, String>>,
+) -> (StatusCode, Json<JsonValue>) {
+    let db = input.get("db").cloned().unwrap_or_default();
+    if !is_valid_db(&db) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": invalid_db_message(&db) })),
+        );
+    }
+
+    let filters = service_filters(&input);
+    let sort = SortOrder::from_param(input.get("sort").map(String::as_str));
+    let offset: usize = input
+        .get("offset")
+        .and_then(|o| o.parse().ok())
+        .unwrap_or(0);
+    let limit: Option<usize> = input.get("limit").and_then(|l| l.parse().ok());
+
+    let services = match list_services(&db, &filters, sort) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Error retrieving services: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Error retrieving services" })),
+            );
+        }
+    };
+
+    let total = services.len();
+    let page: Vec<JsonValue> = services
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(label, path)| json!({ "label": label, "path": path }))
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "db": db,
+            "total": total,
+            "offset": offset,
+            "limit": limit,
+            "services": page,
+        })),
+    )
+}
+
+// Handler for the "/api/services/batch" route
+// Every detail of many services in one request: the JSON body names the
+// database and the labels, as in {"db": "...", "labels": ["...", ...]}. The
+// answer maps each label to the array of its services, or null when unknown.
+pub async fn api_services_batch(Json(body): Json<JsonValue>) -> (StatusCode, Json<JsonValue>) {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": error })));
+
+    let db = body["db"].as_str().unwrap_or_default();
+    if !is_valid_db(db) {
+        return bad_request(invalid_db_message(db));
+    }
+
+    let labels: Option<Vec<String>> = body["labels"].as_array().and_then(|labels| {
+        labels
+            .iter()
+            .map(|label| label.as_str().map(String::from))
+            .collect()
+    });
+    let Some(labels) = labels else {
+        return bad_request("\"labels\" must be an array of strings".to_string());
+    };
+    if labels.len() > MAX_BATCH_LABELS {
+        return bad_request(format!(
+            "At most {} labels can be looked up at once",
+            MAX_BATCH_LABELS
+        ));
+    }
+
+    match get_services_json_by_labels(db, &labels) {
+        Ok(services) => (
+            StatusCode::OK,
+            Json(json!({ "db": db, "services": services })),
+        ),
+        Err(e) => {
+            eprintln!("Error retrieving services: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Error retrieving services" })),
+            )
+        }
+    }
+}
+
+// Handler for the "/openapi.json" route
+// Hand-written OpenAPI 3 description of the machine readable routes
+pub async fn openapi() -> Json<JsonValue> {
+    let db_param = json!({
+        "name": "db",
+        "in": "query",
+        "required": true,
+        "description": "Database file name, as listed by /api/databases",
+        "schema": { "type": "string", "example": "dora_macOS_15.0_24A335.sqlite" },
+    });
+    let filter_param = |name: &str, description: &str| {
+        json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "description": description,
+            "schema": { "type": "string", "enum": ["true", "false", "1", "0"] },
+        })
+    };
+    let error = json!({
+        "description": "Invalid request or database error",
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+
+    Json(json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "dora",
+            "description": env!("CARGO_PKG_DESCRIPTION"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/health": {
+                "get": {
+                    "summary": "Liveness probe, never opens a database",
+                    "responses": {
+                        "200": {
+                            "description": "Server is up",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "status": { "type": "string", "example": "ok" },
+                                    "databases": { "type": "integer" },
+                                },
+                            } } },
+                        },
+                    },
+                },
+            },
+            "/api/databases": {
+                "get": {
+                    "summary": "Available databases",
+                    "responses": {
+                        "200": {
+                            "description": "Databases with the OS version parsed from their name",
+                            "content": { "application/json": { "schema": {
+                                "type": "array",
+                                "items": { "$ref": "#/components/schemas/Database" },
+                            } } },
+                        },
+                    },
+                },
+            },
+            "/api/services": {
+                "get": {
+                    "summary": "Services of a database, optionally filtered, one page at a time",
+                    "parameters": [
+                        db_param,
+                        filter_param("disabled", "Whether the plist disables the service by default"),
+                        filter_param("run_at_load", "Whether launchd starts the service when loaded"),
+                        filter_param("keep_alive", "Whether launchd keeps the service running"),
+                        filter_param("dyld_env", "Whether the service sets a DYLD_* environment variable"),
+                        filter_param("arm64", "Whether the binary has an arm64 slice (arm64, arm64e, ...)"),
+                        {
+                            "name": "session_type",
+                            "in": "query",
+                            "required": false,
+                            "description": "A session the job is limited to by LimitLoadToSessionType (Aqua, LoginWindow, Background, System, ...)",
+                            "schema": { "type": "string" },
+                        },
+                        filter_param("third_party", "Whether the plist or binary lies in a folder of third-party software (/Library/LaunchDaemons, /usr/local, ...)"),
+                        {
+                            "name": "sort",
+                            "in": "query",
+                            "required": false,
+                            "schema": { "type": "string", "enum": ["label", "path", "natural"], "default": "label" },
+                        },
+                        {
+                            "name": "limit",
+                            "in": "query",
+                            "required": false,
+                            "description": "Maximum number of services returned (all by default)",
+                            "schema": { "type": "integer", "minimum": 0 },
+                        },
+                        {
+                            "name": "offset",
+                            "in": "query",
+                            "required": false,
+                            "description": "Number of matching services skipped",
+                            "schema": { "type": "integer", "minimum": 0, "default": 0 },
+                        },
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "A page of matching services",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "db": { "type": "string" },
+                                    "total": { "type": "integer", "description": "Number of matching services, ignoring limit and offset" },
+                                    "offset": { "type": "integer" },
+                                    "limit": { "type": "integer", "nullable": true },
+                                    "services": {
+                                        "type": "array",
+                                        "items": { "$ref": "#/components/schemas/Service" },
+                                    },
+                                },
+                            } } },
+                        },
+                        "400": error,
+                        "500": error,
+                    },
+                },
+            },
+            "/api/services/batch": {
+                "post": {
+                    "summary": "Every detail (entitlements, libraries, symbols, Mach services, ...) of the services of many labels",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "required": ["db", "labels"],
+                            "properties": {
+                                "db": { "type": "string", "example": "dora_macOS_15.0_24A335.sqlite" },
+                                "labels": {
+                                    "type": "array",
+                                    "maxItems": MAX_BATCH_LABELS,
+                                    "items": { "type": "string" },
+                                },
+                            },
+                        } } },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Services by label, null for unknown labels. A launchd job and a binary may share a label, hence arrays.",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "db": { "type": "string" },
+                                    "services": {
+                                        "type": "object",
+                                        "additionalProperties": {
+                                            "type": "array",
+                                            "nullable": true,
+                                            "items": { "type": "object" },
+                                        },
+                                    },
+                                },
+                            } } },
+                        },
+                        "400": error,
+                        "500": error,
+                    },
+                },
+            },
+            "/download": {
+                "get": {
+                    "summary": "The database file itself, as an attachment",
+                    "parameters": [db_param],
+                    "responses": {
+                        "200": {
+                            "description": "SQLite database file",
+                            "content": { SQLITE_CONTENT_TYPE: { "schema": { "type": "string", "format": "binary" } } },
+                        },
+                        "400": { "description": "Invalid database name" },
+                        "404": { "description": "Database not found" },
+                    },
+                },
+            },
+            "/service/export": {
+                "get": {
+                    "summary": "Everything stored about the services of a label, as one attachment",
+                    "parameters": [
+                        db_param,
+                        {
+                            "name": "label",
+                            "in": "query",
+                            "required": true,
+                            "description": "Service label",
+                            "schema": { "type": "string" },
+                        },
+                        {
+                            "name": "format",
+                            "in": "query",
+                            "required": false,
+                            "description": "File format, JSON unless plist",
+                            "schema": { "type": "string", "enum": ["json", "plist"] },
+                        },
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The services of the label as /api/services/batch describes them, with the scan info of the database. Plists leave out null values.",
+                            "content": {
+                                "application/json": { "schema": { "type": "object" } },
+                                PLIST_CONTENT_TYPE: { "schema": { "type": "string" } },
+                            },
+                        },
+                        "400": { "description": "Invalid database name" },
+                        "404": { "description": "No service found with the label" },
+                    },
+                },
+            },
+        },
+        "components": {
+            "schemas": {
+                "Database": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "product_name": { "type": "string", "nullable": true },
+                        "product_version": { "type": "string", "nullable": true },
+                        "build_version": { "type": "string", "nullable": true },
+                        "size": { "type": "integer", "nullable": true, "description": "Size in bytes" },
+                        "modified": { "type": "integer", "nullable": true, "description": "Modification time, seconds since the Unix epoch" },
+                    },
+                },
+                "Service": {
+                    "type": "object",
+                    "properties": {
+                        "label": { "type": "string" },
+                        "path": { "type": "string" },
+                    },
+                },
+                "Error": {
+                    "type": "object",
+                    "properties": {
+                        "error": { "type": "string" },
+                    },
+                },
+            },
+        },
+    }))
 }